@@ -0,0 +1,75 @@
+// Compares the streaming StanzaReader against str_to_table on a synthetic Packages file.
+// Run with `cargo bench --bench deb822`.
+#![allow(clippy::needless_return)]
+use std::time::{Duration, Instant};
+
+use pakige::deb::deb822::{Stanza, StanzaReader};
+use pakige::deb::str_to_table;
+
+const STANZAS: usize = 20000;
+const ROUNDS: u32 = 5;
+
+fn packages_file () -> String
+{
+    let mut data = String::new();
+
+    for i in 0..STANZAS
+    {
+        data.push_str(&format!("Package: package{i}\n\
+                                Version: 1.{i}-1\n\
+                                Architecture: amd64\n\
+                                Maintainer: Debian Maintainers <maintainers@example.org>\n\
+                                Installed-Size: {i}\n\
+                                Depends: libc6 (>= 2.36), libfoo{i} | libbar\n\
+                                Description: synthetic package {i}\n \
+                                This is the extended description of the package.\n \
+                                .\n \
+                                It spans several continuation lines.\n\
+                                Filename: pool/main/p/package{i}/package{i}_1.{i}-1_amd64.deb\n\
+                                Size: {i}\n\
+                                SHA256: 5bd7b1a2ac2c8b3c1f6c1c8d5c3c8e5e2a8c4c7d9b8e7f6a5b4c3d2e1f0a9b8c\n\n"));
+    }
+
+    return data;
+}
+
+fn time<F: FnMut() -> usize> (name: &str, data: &str, mut run: F)
+{
+    let mut total = Duration::ZERO;
+    let mut fields = 0;
+
+    for _ in 0..ROUNDS
+    {
+        let start = Instant::now();
+        fields = run();
+        total += start.elapsed();
+    }
+
+    let average = total / ROUNDS;
+    let throughput = data.len() as f64 / average.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{name:<16} {average:>12.2?} per file  {throughput:>8.1} MiB/s  ({fields} fields)");
+}
+
+fn main ()
+{
+    let data = packages_file();
+    println!("{} stanzas, {} KiB", STANZAS, data.len() / 1024);
+
+    time("str_to_table", &data, || {
+        data.split("\n\n")
+            .filter(|stanza| !stanza.trim().is_empty())
+            .map(|stanza| str_to_table(stanza).unwrap().len())
+            .sum()
+    });
+
+    time("StanzaReader", &data, || {
+        let mut reader = StanzaReader::new(data.as_bytes());
+        let mut stanza = Stanza::new();
+        let mut fields = 0;
+        while reader.read_stanza(&mut stanza).unwrap()
+        {
+            fields += stanza.len();
+        }
+        fields
+    });
+}
@@ -0,0 +1,211 @@
+use flate2::bufread::MultiGzDecoder;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use xz2::bufread::XzDecoder;
+
+// Decompression of the formats found in Debian archives: gzip (flate2), xz (xz2, over liblzma)
+// and zstd. Files made of several concatenated members, streams or frames, as produced by
+// parallel compressors such as pigz, pixz or zstdmt, are decompressed whole. Decompression can
+// be done all at once, or as the data is read, so that large files need not be held in memory.
+
+static GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+static XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+static ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression
+{
+    None,
+    Gzip,
+    Xz,
+    Zstd
+}
+
+impl Compression
+{
+    /* Maps a file extension (`gz`, `xz`, `zst`) to its compression; an empty one is uncompressed */
+    pub fn from_extension (extension: &str) -> Option<Compression>
+    {
+        return match extension
+        {
+            "" => Some(Compression::None),
+            "gz" => Some(Compression::Gzip),
+            "xz" => Some(Compression::Xz),
+            "zst" => Some(Compression::Zstd),
+            _ => None
+        };
+    }
+
+    /* Whether `data` starts as this compression's files do */
+    fn has_magic (&self, data: &[u8]) -> bool
+    {
+        return match self
+        {
+            Compression::None => true,
+            Compression::Gzip => data.starts_with(GZIP_MAGIC),
+            Compression::Xz => data.starts_with(XZ_MAGIC),
+            // A zstd file may also start with a skippable frame, magic 0x184d2a5?
+            Compression::Zstd => data.starts_with(ZSTD_MAGIC)
+                || (data.len() >= 4 && data[0] & 0xf0 == 0x50 && data[1..4] == [0x2a, 0x4d, 0x18])
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecompressError
+{
+    InvalidHeader,
+    CorruptData,
+    UnexpectedEnd
+}
+
+impl fmt::Display for DecompressError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            DecompressError::InvalidHeader => write!(f, "data does not start with the expected header"),
+            DecompressError::CorruptData => write!(f, "compressed data is corrupt"),
+            DecompressError::UnexpectedEnd => write!(f, "compressed data ends unexpectedly")
+        }
+    }
+}
+
+impl std::error::Error for DecompressError {}
+
+impl From<io::Error> for DecompressError
+{
+    fn from (error: io::Error) -> DecompressError
+    {
+        return match error.kind()
+        {
+            io::ErrorKind::UnexpectedEof => DecompressError::UnexpectedEnd,
+            _ => DecompressError::CorruptData
+        };
+    }
+}
+
+// Wraps `reader` in a reader of its decompressed data. The header is checked up front; errors
+// found later come out of read() as io::Error, which converts into a DecompressError. Errors of
+// `reader` itself come out the same way, unchanged: callers that need to tell them apart from
+// bad data mark them on the way in, as archive.rs does.
+pub fn decoder<'a, R: BufRead + 'a> (mut reader: R, compression: Compression) -> Result<Box<dyn Read + 'a>, DecompressError>
+{
+    if !compression.has_magic (reader.fill_buf()?)
+    {
+        return Err(DecompressError::InvalidHeader);
+    }
+
+    return Ok(match compression
+    {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?)
+    });
+}
+
+pub fn decompress (data: &[u8], compression: Compression) -> Result<Vec<u8>, DecompressError>
+{
+    let mut output = Vec::new();
+    decoder (data, compression)?.read_to_end(&mut output)?;
+    return Ok(output);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::io::Write;
+
+    static TEXT: &[u8] = b"Package: hello\nVersion: 2.10-3\nArchitecture: amd64\n";
+
+    fn compress (data: &[u8], compression: Compression) -> Vec<u8>
+    {
+        return match compression
+        {
+            Compression::None => data.to_vec(),
+            Compression::Gzip =>
+            {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            },
+            Compression::Xz =>
+            {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            },
+            Compression::Zstd =>
+            {
+                // As dpkg-deb does, so that corruption is caught
+                let mut encoder = zstd::Encoder::new(Vec::new(), 3).unwrap();
+                encoder.include_checksum(true).unwrap();
+                encoder.write_all(data).unwrap();
+                encoder.finish().unwrap()
+            }
+        };
+    }
+
+    static COMPRESSIONS: [Compression; 4] = [Compression::None, Compression::Gzip, Compression::Xz, Compression::Zstd];
+
+    #[test]
+    fn round_trips ()
+    {
+        // Large enough to span several blocks
+        let large: Vec<u8> = TEXT.iter().cycle().take(1 << 20).copied().collect();
+        for compression in COMPRESSIONS
+        {
+            for data in [b"".as_slice(), TEXT, &large]
+            {
+                assert_eq!(decompress (&compress (data, compression), compression).unwrap(), data, "{:?}", compression);
+            }
+        }
+    }
+
+    #[test]
+    fn concatenated ()
+    {
+        for compression in COMPRESSIONS
+        {
+            let mut data = compress (&TEXT[..20], compression);
+            data.extend(compress (&TEXT[20..], compression));
+            assert_eq!(decompress (&data, compression).unwrap(), TEXT, "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn extensions ()
+    {
+        assert_eq!(Compression::from_extension (""), Some(Compression::None));
+        assert_eq!(Compression::from_extension ("gz"), Some(Compression::Gzip));
+        assert_eq!(Compression::from_extension ("xz"), Some(Compression::Xz));
+        assert_eq!(Compression::from_extension ("zst"), Some(Compression::Zstd));
+        assert_eq!(Compression::from_extension ("bz2"), None);
+    }
+
+    #[test]
+    fn errors ()
+    {
+        for compression in [Compression::Gzip, Compression::Xz, Compression::Zstd]
+        {
+            let data = compress (TEXT, compression);
+
+            assert_eq!(decompress (TEXT, compression), Err(DecompressError::InvalidHeader), "{:?}", compression);
+            assert_eq!(decompress (b"", compression), Err(DecompressError::InvalidHeader), "{:?}", compression);
+            assert_eq!(decompress (&data[..data.len() / 2], compression), Err(DecompressError::UnexpectedEnd), "{:?}", compression);
+
+            let mut corrupt = data.clone();
+            let middle = corrupt.len() / 2;
+            corrupt[middle] ^= 0x55;
+            assert!(decompress (&corrupt, compression).is_err(), "{:?}", compression);
+        }
+
+        // A checksum that does not match
+        let mut data = compress (TEXT, Compression::Gzip);
+        let crc = data.len() - 8;
+        data[crc] ^= 0xff;
+        assert_eq!(decompress (&data, Compression::Gzip), Err(DecompressError::CorruptData));
+    }
+}
@@ -1,21 +1,83 @@
-use crate::{Pakige, PakigeParseError, VerOp};
+use crate::{PakigeParseError, VerOp};
 use std::collections::{HashMap, VecDeque};
-use std::hash::Hash;
+use std::fmt;
+use std::io::BufRead;
 use std::str::FromStr;
+use std::sync::OnceLock;
 use deb_version7::DebVersion;
 use regex::Regex;
 
+use architectures::Arch;
+
+mod archive;
+pub use archive::{ArchiveError, DataArchive, DebArchive};
+pub use tar::{EntryContents, EntryKind, TarEntry, TarReader};
+
+pub mod deb822;
+pub use deb822::{Fields, ParseMode};
+use deb822::{Stanza, StanzaReader};
+
+mod changelog;
+pub use changelog::{Changelog, ChangelogEntry};
+
+mod changes;
+pub use changes::{Changes, ChangesFile, ChecksumTableError};
+
+mod clearsign;
+pub use clearsign::{parse_clearsigned, ClearSigned};
+
+mod conffiles;
+pub use conffiles::{Conffile, Conffiles};
+
+mod date;
+pub use date::DateTime;
+
+mod description;
+pub use description::{Description, DescriptionLine};
+
+mod dsc;
+pub use dsc::Dsc;
+
+mod md5sums;
+pub use md5sums::Md5Sums;
+
+mod mirror;
+pub use mirror::{FileMismatch, MirrorReport};
+
+mod shlibs;
+pub use shlibs::{Shlibs, ShlibsEntry};
+
+mod symbols;
+pub use symbols::{Symbol, Symbols, SymbolsLibrary};
+
+mod triggers;
+pub use triggers::{Trigger, TriggerKind, Triggers};
+
+mod verify;
+pub use verify::{verify, verify_installed, VerifyReport};
+
+mod relationships;
+mod release;
+mod source;
+mod tar;
+mod writer;
+pub use relationships::RelationError;
+pub use release::Release;
+pub use source::{BinaryTemplate, SourceControl, SourceParagraph};
+
 mod setters;
 use setters::{set_package, set_source, set_version, set_section, set_priority,
               set_architecture, set_essential, set_depends, set_recommends,
               set_suggests, set_enhances, set_pre_depends, set_breaks, set_conflicts,
               set_provides, set_replaces, set_installed_size, set_maintainer,
-              set_description, set_homepage, set_built_using, set_multi_arch};
+              set_description, set_homepage, set_built_using, set_multi_arch,
+              set_filename, set_size, set_md5sum, set_sha1, set_sha256, set_sha512,
+              set_description_md5, set_directory, set_extra_source_only};
 
 pub struct BinaryDeb
 {
     pub package: String, /* Mandatory */
-    pub source: Option<String>,
+    pub source: Option<SourceRef>,
     pub version: DebVersion, /* Mandatory */
     pub section: Option<String>, /* Recommended */
     pub priority: Option<String>, /* Recommended */
@@ -32,40 +94,73 @@ pub struct BinaryDeb
     pub replaces: Option<ProvidesPackageList>,
     pub installed_size: Option<u64>,
     pub maintainer: String, /* Mandatory */
-    pub description: String, /* Mandatory */
+    pub description: Description, /* Mandatory */
     pub homepage: Option<String>,
     pub built_using: Option<ProvidesPackageList>,
     pub multi_arch: MultiArch,
     pub all_fields: Fields
 }
 
-fn str_to_table (data: &str) -> Result<Fields,PakigeParseError>
+pub fn str_to_table (data: &str) -> Result<Fields,PakigeParseError>
+{
+    return str_to_table_with_mode (data, ParseMode::Strict);
+}
+
+// https://man7.org/linux/man-pages/man5/deb822.5.html
+// The field name
+// is composed of US-ASCII characters excluding control characters,
+// space, and colon (i.e., characters in the ranges U+0021 ‘!’
+// through U+0039 ‘9’, and U+003B ‘;’ through U+007E ‘~’,
+// inclusive).  Field names must not begin with the comment
+// character (U+0023 ‘#’), nor with the hyphen character (U+002D
+// ‘-’).
+// The line rules are compiled once, since str_to_table runs for every stanza of index files
+fn normal_line_rules () -> &'static Regex
+{
+    static RULES: OnceLock<Regex> = OnceLock::new();
+    return RULES.get_or_init(|| Regex::new(r"^[[:space:]]*([[!-9--#---][;-~]][[!-9][;-~]]*)[[:space:]]*:[[:space:]]*(.*)[[:space:]]*$").unwrap());
+}
+
+fn continuation_rules () -> &'static Regex
+{
+    static RULES: OnceLock<Regex> = OnceLock::new();
+    return RULES.get_or_init(|| Regex::new(r"^([ \t].*)$").unwrap());
+}
+
+fn comment_rules () -> &'static Regex
+{
+    static RULES: OnceLock<Regex> = OnceLock::new();
+    return RULES.get_or_init(|| Regex::new(r"^#.*$").unwrap());
+}
+
+pub fn str_to_table_with_mode (data: &str, mode: ParseMode) -> Result<Fields,PakigeParseError>
 {
-    // https://man7.org/linux/man-pages/man5/deb822.5.html
-    // The field name
-    // is composed of US-ASCII characters excluding control characters,
-    // space, and colon (i.e., characters in the ranges U+0021 ‘!’
-    // through U+0039 ‘9’, and U+003B ‘;’ through U+007E ‘~’,
-    // inclusive).  Field names must not begin with the comment
-    // character (U+0023 ‘#’), nor with the hyphen character (U+002D
-    // ‘-’).
-    let normal_line = Regex::new(r"^[[:space:]]*([[!-9--#---][;-~]][[!-9][;-~]]*)[[:space:]]*:[[:space:]]*(.*)[[:space:]]*$").unwrap();
-    let continuation = Regex::new(r"^( .*)$").unwrap();
+    let normal_line = normal_line_rules();
+    let continuation = continuation_rules();
+    let comment_line = comment_rules();
 
     let mut lines: VecDeque<&str> = data.trim().lines().collect();
-    let mut fields: Fields = HashMap::new();
+    let mut fields = Fields::new();
+    let mut continuation_lines = 0; // Of the last field, for placing comments
 
     while let Some(line) = lines.pop_front()
     {
         /* There should never be continuation lines in this outer loop */
         /* The first loop starts with the first line of the stanza, which can not be a continuation line */
+        // Look for comment line
+        if mode != ParseMode::Strict && comment_line.is_match(line)
+        {
+            fields.push_comment (mode, fields.len(), continuation_lines, line);
+        }
         // Look for normal line, capture key-value pairs
-        if let Some(captures) = normal_line.captures(line)
+        else if let Some(captures) = normal_line.captures(line)
         {
+            // Note: "Field names are not case-sensitive." RFC 822
+            // The spelling is kept as written; Fields looks keys up case-insensitively.
             let key = captures.get(1)
                 .ok_or(PakigeParseError::InvalidFormat)?
                 .as_str()
-                .to_lowercase(); // Note: "Field names are not case-sensitive." RFC 822
+                .to_string();
             let value = captures.get(2)
                 .ok_or(PakigeParseError::InvalidFormat)?
                 .as_str();
@@ -78,11 +173,18 @@ fn str_to_table (data: &str) -> Result<Fields,PakigeParseError>
 
             // We may need to append further 
             let mut field_data = String::from(value);
+            let mut inner_comments = Vec::new();
+            continuation_lines = 0;
 
             // Look ahead for continuation lines
             while let Some(next_line) = lines.pop_front()
             {
-                if let Some(capture_append) = continuation.captures(next_line)
+                // Comments may also sit between continuation lines
+                if mode != ParseMode::Strict && comment_line.is_match(next_line)
+                {
+                    inner_comments.push((continuation_lines, next_line));
+                }
+                else if let Some(capture_append) = continuation.captures(next_line)
                 {
                     let to_append = capture_append.get(1)
                     .ok_or(PakigeParseError::InvalidFormat)?
@@ -90,6 +192,7 @@ fn str_to_table (data: &str) -> Result<Fields,PakigeParseError>
 
                     field_data.push('\n'); // still preserves the double space that indicates to not wrap
                     field_data.push_str(to_append);
+                    continuation_lines += 1;
                 }
                 /* Break/Base case */
                 else
@@ -99,8 +202,13 @@ fn str_to_table (data: &str) -> Result<Fields,PakigeParseError>
                 }
             }
 
-            // Insert into Hashmap
+            // Append, keeping the order of the stanza; comments that sat between the continuation
+            // lines are placed inside the field
             fields.insert(key, field_data);
+            for (lines_before, comment) in inner_comments
+            {
+                fields.push_comment (mode, fields.len(), lines_before, comment);
+            }
         }
         // Else, not valid Debian Control syntax
         else
@@ -117,7 +225,26 @@ fn str_to_table (data: &str) -> Result<Fields,PakigeParseError>
     return Ok(fields);
 }
 
-type Fields = HashMap<String, String>;
+impl BinaryDeb
+{
+    /* The typed architecture, or None for `Architecture: all` and architectures dpkg does not know */
+    pub fn arch (&self) -> Option<Arch>
+    {
+        return Arch::from_str (&self.architecture).ok();
+    }
+
+    // The source package this binary was built from: the Source field's name and version when
+    // given, else the binary's own name and version, as dpkg-query's ${source:Package} and
+    // ${source:Version} do
+    pub fn effective_source (&self) -> (&str, &DebVersion)
+    {
+        return match &self.source
+        {
+            Some(source) => (&source.name, source.version.as_ref().unwrap_or(&self.version)),
+            None => (&self.package, &self.version)
+        };
+    }
+}
 
 impl FromStr for BinaryDeb
 {
@@ -125,9 +252,16 @@ impl FromStr for BinaryDeb
 
     fn from_str (data: &str) -> Result<Self, Self::Err>
     {
-        let mut fields = str_to_table(data)?;
+        let fields = str_to_table(data)?;
+        return BinaryDeb::from_fields (fields);
+    }
+}
 
-        let mut deb = BinaryDeb {
+impl BinaryDeb
+{
+    fn from_fields (fields: Fields) -> Result<Self, PakigeParseError>
+    {
+        let deb = BinaryDeb {
             //all_fields: fields,
             package: set_package (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
             source: set_source (&fields)?,
@@ -158,83 +292,776 @@ impl FromStr for BinaryDeb
     }
 }
 
+/* The extra fields a stanza carries in an APT Packages index */
 pub struct BinaryIndexFields
 {
-    filename: String, /* Mandatory */
-    size: u64, /* Mandatory */
-    md5sum: Option<String>, /* Recommended */
-    sha1: Option<String>, /* Recommended */
-    sha256: Option<String>, /* Recommended */
-    sha512: Option<String>, /* Recommended */
-    desc_md5: Option<String>
+    pub filename: String, /* Mandatory */
+    pub size: u64, /* Mandatory */
+    pub md5sum: Option<String>, /* Recommended */
+    pub sha1: Option<String>, /* Recommended */
+    pub sha256: Option<String>, /* Recommended */
+    pub sha512: Option<String>, /* Recommended */
+    pub desc_md5: Option<String>
+}
+
+impl BinaryIndexFields
+{
+    fn from_fields (fields: &Fields) -> Result<Self, PakigeParseError>
+    {
+        return Ok(BinaryIndexFields {
+            filename: set_filename (fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            size: set_size (fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            md5sum: set_md5sum (fields)?, /* Recommended */
+            sha1: set_sha1 (fields)?, /* Recommended */
+            sha256: set_sha256 (fields)?, /* Recommended */
+            sha512: set_sha512 (fields)?, /* Recommended */
+            desc_md5: set_description_md5 (fields)?
+        });
+    }
+}
+
+// Entries are kept in file order, with hash indexes into them by package name and by the
+// virtual package names that entries Provide.
+#[derive(Default)]
+pub struct PackageIndex
+{
+    entries: Vec<(BinaryDeb, BinaryIndexFields)>,
+    by_name: HashMap<String, Vec<usize>>,
+    by_provides: HashMap<String, Vec<usize>>
+}
+
+impl PackageIndex
+{
+    pub fn new () -> Self
+    {
+        return PackageIndex::default();
+    }
+
+    pub fn push (&mut self, deb: BinaryDeb, index_fields: BinaryIndexFields)
+    {
+        let position = self.entries.len();
+
+        self.by_name.entry(deb.package.clone()).or_default().push(position);
+        if let Some(provides) = &deb.provides
+        {
+            for virtual_package in &provides.0
+            {
+                let providers = self.by_provides.entry(virtual_package.package.clone()).or_default();
+                // A package may provide the same name more than once (e.g. at different versions)
+                if providers.last() != Some(&position)
+                {
+                    providers.push(position);
+                }
+            }
+        }
+
+        self.entries.push((deb, index_fields));
+    }
+
+    pub fn entries (&self) -> &[(BinaryDeb, BinaryIndexFields)]
+    {
+        return &self.entries;
+    }
+
+    pub fn len (&self) -> usize
+    {
+        return self.entries.len();
+    }
+
+    pub fn is_empty (&self) -> bool
+    {
+        return self.entries.is_empty();
+    }
+
+    fn lookup<'a> (&'a self, table: &'a HashMap<String, Vec<usize>>, name: &str) -> impl Iterator<Item = &'a (BinaryDeb, BinaryIndexFields)>
+    {
+        return table.get(name)
+            .into_iter()
+            .flatten()
+            .map(|position| &self.entries[*position]);
+    }
+
+    /* Every version of the named (real) package, for all architectures */
+    pub fn get (&self, name: &str) -> Vec<&(BinaryDeb, BinaryIndexFields)>
+    {
+        return self.lookup (&self.by_name, name).collect();
+    }
+
+    /* Versions of the named package built for `arch`; `Architecture: all` packages match any arch */
+    pub fn get_arch (&self, name: &str, arch: &str) -> Vec<&(BinaryDeb, BinaryIndexFields)>
+    {
+        return self.lookup (&self.by_name, name)
+            .filter(|(deb, _)| deb.architecture == arch || deb.architecture == "all")
+            .collect();
+    }
+
+    /* Versions of the named package that satisfy a version constraint such as `(>= 1.2)` */
+    pub fn get_version (&self, name: &str, version: &VersionRef) -> Vec<&(BinaryDeb, BinaryIndexFields)>
+    {
+        return self.lookup (&self.by_name, name)
+            .filter(|(deb, _)| version.satisfied_by (&deb.version))
+            .collect();
+    }
+
+    /* Packages that list the given (usually virtual) package in their Provides */
+    pub fn providers (&self, name: &str) -> Vec<&(BinaryDeb, BinaryIndexFields)>
+    {
+        return self.lookup (&self.by_provides, name).collect();
+    }
+
+    // Every entry that can satisfy a relation: real packages of that name at a matching version,
+    // plus packages providing it. As in dpkg, an unversioned Provides never satisfies a
+    // versioned relation, while a versioned Provides is checked against the constraint.
+    pub fn satisfying (&self, relation: &PackageRef) -> Vec<&(BinaryDeb, BinaryIndexFields)>
+    {
+        let real = self.lookup (&self.by_name, &relation.package)
+            .filter(|(deb, _)| match &relation.version
+            {
+                Some(version) => version.satisfied_by (&deb.version),
+                None => true
+            });
+
+        let provided = self.lookup (&self.by_provides, &relation.package)
+            .filter(|(deb, _)| deb.provides.iter()
+                .flat_map(|provides| provides.0.iter())
+                .filter(|provided| provided.package == relation.package)
+                .any(|provided| match (&relation.version, &provided.version)
+                {
+                    (None, _) => true,
+                    (Some(wanted), Some(given)) => wanted.satisfied_by (&given.version_string),
+                    (Some(_), None) => false
+                }));
+
+        return real.chain(provided).collect();
+    }
+}
+
+/* The extra fields a stanza carries in an APT Sources index */
+pub struct SourceIndexFields
+{
+    pub directory: String, /* Mandatory */
+    pub section: Option<String>,
+    pub priority: Option<String>,
+    pub extra_source_only: bool // Kept in the archive only because a binary was Built-Using it
+}
+
+impl SourceIndexFields
+{
+    fn from_fields (fields: &Fields) -> Result<Self, PakigeParseError>
+    {
+        return Ok(SourceIndexFields {
+            directory: set_directory (fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            section: set_section (fields)?,
+            priority: fields.get("priority").cloned(),
+            extra_source_only: set_extra_source_only (fields)?.unwrap_or (false) // Has default value
+        });
+    }
+}
+
+// Entries are kept in file order, with hash indexes into them by source package name and by the
+// names of the binary packages each source builds.
+#[derive(Default)]
+pub struct SourceIndex
+{
+    entries: Vec<(Dsc, SourceIndexFields)>,
+    by_name: HashMap<String, Vec<usize>>,
+    by_binary: HashMap<String, Vec<usize>>
+}
+
+impl SourceIndex
+{
+    pub fn new () -> Self
+    {
+        return SourceIndex::default();
+    }
+
+    pub fn push (&mut self, dsc: Dsc, index_fields: SourceIndexFields)
+    {
+        let position = self.entries.len();
+
+        self.by_name.entry(dsc.source.clone()).or_default().push(position);
+        for binary in &dsc.binary
+        {
+            let sources = self.by_binary.entry(binary.clone()).or_default();
+            if sources.last() != Some(&position)
+            {
+                sources.push(position);
+            }
+        }
+
+        self.entries.push((dsc, index_fields));
+    }
+
+    pub fn entries (&self) -> &[(Dsc, SourceIndexFields)]
+    {
+        return &self.entries;
+    }
+
+    pub fn len (&self) -> usize
+    {
+        return self.entries.len();
+    }
+
+    pub fn is_empty (&self) -> bool
+    {
+        return self.entries.is_empty();
+    }
+
+    fn lookup<'a> (&'a self, table: &'a HashMap<String, Vec<usize>>, name: &str) -> impl Iterator<Item = &'a (Dsc, SourceIndexFields)>
+    {
+        return table.get(name)
+            .into_iter()
+            .flatten()
+            .map(|position| &self.entries[*position]);
+    }
+
+    /* Every version of the named source package */
+    pub fn get (&self, name: &str) -> Vec<&(Dsc, SourceIndexFields)>
+    {
+        return self.lookup (&self.by_name, name).collect();
+    }
+
+    /* Versions of the named source package that satisfy a version constraint */
+    pub fn get_version (&self, name: &str, version: &VersionRef) -> Vec<&(Dsc, SourceIndexFields)>
+    {
+        return self.lookup (&self.by_name, name)
+            .filter(|(dsc, _)| version.satisfied_by (&dsc.version))
+            .collect();
+    }
+
+    /* Source packages that list the named binary package in their Binary field */
+    pub fn building (&self, binary: &str) -> Vec<&(Dsc, SourceIndexFields)>
+    {
+        return self.lookup (&self.by_binary, binary).collect();
+    }
+
+    // The source stanza a binary package was built from. The binary's Source field names the
+    // source package, optionally with its version as `src (version)`; with no Source field the
+    // source has the binary's name, and with no version it has the binary's version.
+    pub fn source_of (&self, deb: &BinaryDeb) -> Option<&(Dsc, SourceIndexFields)>
+    {
+        let (name, version) = deb.effective_source();
+        return self.lookup (&self.by_name, name).find(|(dsc, _)| dsc.version == *version);
+    }
+}
+
+/* A stanza that failed to parse, located by the offending line when known, or else the line the stanza starts on (1-based) */
+#[derive(Debug)]
+pub struct StanzaError
+{
+    pub line: usize,
+    pub error: PakigeParseError
+}
+
+impl fmt::Display for StanzaError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+impl std::error::Error for StanzaError {}
+
+// Parses every stanza of an APT Packages file. A bad stanza does not stop the rest of the
+// file from being read; its error is returned alongside the stanzas that did parse.
+pub fn parse_packages_file (data: &str) -> (PackageIndex, Vec<StanzaError>)
+{
+    return read_packages_file (data.as_bytes());
 }
 
-pub struct PackageIndex(Vec<(BinaryDeb, BinaryIndexFields)>); // TODO: would rather this be keyword indexed
+/* As parse_packages_file(), streaming the file one stanza at a time */
+pub fn read_packages_file<R: BufRead> (reader: R) -> (PackageIndex, Vec<StanzaError>)
+{
+    let mut packages = PackageIndex::new();
+    let mut errors = Vec::new();
+    let mut reader = StanzaReader::new (reader);
+    let mut stanza = Stanza::new();
+
+    loop
+    {
+        match reader.read_stanza (&mut stanza)
+        {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(error) =>
+            {
+                let stop = matches!(error.error, PakigeParseError::IoError(_));
+                errors.push(error);
+                if stop
+                {
+                    break;
+                }
+                continue;
+            }
+        }
 
-// impl From<&str> for PackageIndex
-// {
-//     fn from (data: &str) -> Self
-//     {
-//         //
-//     }
-// }
+        let fields = stanza.to_fields();
+        let parsed = BinaryIndexFields::from_fields (&fields).and_then(|index_fields| {
+            Ok((BinaryDeb::from_fields (fields)?, index_fields))
+        });
 
-//pub mod architectures; //TODO
+        match parsed
+        {
+            Ok((deb, index_fields)) => packages.push(deb, index_fields),
+            Err(error) => errors.push(StanzaError { line: stanza.line(), error })
+        }
+    }
 
+    return (packages, errors);
+}
+
+// Stanzas that fail to parse are skipped; use parse_packages_file() to see their errors
+impl From<&str> for PackageIndex
+{
+    fn from (data: &str) -> Self
+    {
+        return parse_packages_file (data).0;
+    }
+}
+
+// Parses every stanza of an APT Sources file. As with parse_packages_file(), a bad stanza does
+// not stop the rest of the file from being read.
+pub fn parse_sources_file (data: &str) -> (SourceIndex, Vec<StanzaError>)
+{
+    return read_sources_file (data.as_bytes());
+}
+
+/* As parse_sources_file(), streaming the file one stanza at a time */
+pub fn read_sources_file<R: BufRead> (reader: R) -> (SourceIndex, Vec<StanzaError>)
+{
+    let mut sources = SourceIndex::new();
+    let mut errors = Vec::new();
+    let mut reader = StanzaReader::new (reader);
+    let mut stanza = Stanza::new();
+
+    loop
+    {
+        match reader.read_stanza (&mut stanza)
+        {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(error) =>
+            {
+                let stop = matches!(error.error, PakigeParseError::IoError(_));
+                errors.push(error);
+                if stop
+                {
+                    break;
+                }
+                continue;
+            }
+        }
+
+        let fields = stanza.to_fields();
+        let parsed = SourceIndexFields::from_fields (&fields).and_then(|index_fields| {
+            Ok((Dsc::from_index_fields (fields)?, index_fields))
+        });
+
+        match parsed
+        {
+            Ok((dsc, index_fields)) => sources.push(dsc, index_fields),
+            Err(error) => errors.push(StanzaError { line: stanza.line(), error })
+        }
+    }
+
+    return (sources, errors);
+}
+
+// Stanzas that fail to parse are skipped; use parse_sources_file() to see their errors
+impl From<&str> for SourceIndex
+{
+    fn from (data: &str) -> Self
+    {
+        return parse_sources_file (data).0;
+    }
+}
+
+pub mod architectures;
+
+#[derive(Debug, Clone)]
 pub struct PackageRef
 {
-    package: String,
-    architecture: Option<String>,
-    version: Option<VersionRef>
+    pub package: String,
+    pub architecture: Option<String>,
+    pub version: Option<VersionRef>,
+    pub arch_restrictions: Vec<ArchRestriction>, // `[amd64 !i386]`, empty when unrestricted
+    pub build_profiles: Vec<Vec<BuildProfile>> // `<!nocheck cross> <stage1>`, empty when unrestricted
+}
+// Build profiles form a disjunction of conjunctions:
+// Outer Vec are the `<...>` groups, any of which may hold
+// Inner Vec are the terms within one group, all of which must hold
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchRestriction
+{
+    pub negated: bool,
+    pub architecture: String
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildProfile
+{
+    pub negated: bool,
+    pub name: String
+}
+
+/* The Source field of a binary package */
+#[derive(Debug, Clone)]
+pub struct SourceRef
+{
+    pub name: String,
+    pub version: Option<DebVersion> // None when the source version is the binary's version
+}
+
+#[derive(Debug, Clone)]
 pub struct VersionRef
 {
-    operation: VerOp,
-    version_string: DebVersion
+    pub operation: VerOp,
+    pub version_string: DebVersion
 }
 
 /* Depends, Pre-Depends, Recommends, Suggests, Enhances */
-pub struct DependsPackageList(Vec<Vec<PackageRef>>);
+#[derive(Debug, Clone)]
+pub struct DependsPackageList(pub Vec<Vec<PackageRef>>);
 // Inner Vec are pipe expressions (groups): `pkg | pkg | pkg`
 // Outer Vec are comma expressions between groups: pkg, pkg
 
 /* Breaks, Conflicts, Replaces, Provides */
-pub struct ProvidesPackageList(Vec<PackageRef>);
+#[derive(Debug, Clone)]
+pub struct ProvidesPackageList(pub Vec<PackageRef>);
 // These types only use comma expressions between packages
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub enum MultiArch
 {
+    #[default]
     No,
     Same,
     Foreign,
     Allowed
 }
 
-impl Default for MultiArch
+
+/* One line of a Files or Checksums-* table */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChecksum
+{
+    pub checksum: String, // Lowercase hex digest
+    pub size: u64,
+    pub filename: String
+}
+
+/* One line of a Package-List field: `package type section priority [key=value ...]` */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageListEntry
+{
+    pub package: String,
+    pub package_type: String, // deb, udeb, ...
+    pub section: String,
+    pub priority: String,
+    pub architecture: Vec<String>, // From `arch=`, empty if not given
+    pub extra: Vec<(String, String)> // Other keys, such as `profile=` or `essential=`
+}
+
+#[cfg(test)]
+mod tests
 {
-    fn default() -> Self
+    use super::*;
+
+    static PACKAGES: &str = "\
+Package: hello
+Version: 2.10-3
+Architecture: amd64
+Maintainer: Santiago Vila <sanvila@debian.org>
+Installed-Size: 280
+Depends: libc6 (>= 2.34)
+Description: example package based on GNU hello
+ The GNU hello program produces a familiar, friendly greeting.
+Filename: pool/main/h/hello/hello_2.10-3_amd64.deb
+Size: 53080
+SHA256: 08c8d8a9ad8c16ab4b2aeb2a0b1e8f7a3a4ca68c7e4b94a6e2c2c2a6f46c0a8f
+
+Package: broken
+Version: 1.0
+Architecture: amd64
+Maintainer: Nobody <nobody@example.org>
+Depends: libc6 (>= 2.34
+Description: a package with a bad relation
+Filename: pool/main/b/broken/broken_1.0_amd64.deb
+Size: 100
+
+Package: hello-traditional
+Version: 2.10-3
+Architecture: all
+Maintainer: Santiago Vila <sanvila@debian.org>
+Description: transitional package
+Filename: pool/main/h/hello/hello-traditional_2.10-3_all.deb
+Size: 1000
+";
+
+    #[test]
+    fn packages_file ()
     {
-        MultiArch::No
+        let (index, errors) = parse_packages_file (PACKAGES);
+        assert_eq!(index.len(), 2);
+        assert_eq!(errors.len(), 1);
+
+        // Reported at the first line of the bad stanza
+        assert_eq!(errors[0].line, 13);
+        assert!(matches!(&errors[0].error, PakigeParseError::InvalidRelationship(field, RelationError::UnterminatedVersion) if field == "depends"));
+
+        let (deb, index_fields) = &index.entries()[0];
+        assert_eq!(deb.package, "hello");
+        assert_eq!(deb.installed_size, Some(280));
+        assert_eq!(index_fields.filename, "pool/main/h/hello/hello_2.10-3_amd64.deb");
+        assert_eq!(index_fields.size, 53080);
+        assert!(index_fields.md5sum.is_none());
+
+        assert_eq!(index.get("hello-traditional").len(), 1);
+        assert_eq!(index.get_arch("hello-traditional", "arm64").len(), 1);
+        assert_eq!(index.get_arch("hello", "arm64").len(), 0);
+        assert!(index.get("broken").is_empty());
     }
-}
 
-// pub fn parse_packages_file (data: &str) -> Vec<BinaryDeb>
-// {
-//     //
-// }
+    #[test]
+    fn packages_file_missing_fields ()
+    {
+        let (index, errors) = parse_packages_file ("Package: foo\nVersion: 1\nArchitecture: all\n");
+        assert!(index.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0].error, PakigeParseError::MissingMandatoryField));
 
+        let (index, errors) = parse_packages_file ("\n\n");
+        assert!(index.is_empty() && errors.is_empty());
+    }
 
+    #[test]
+    fn packages_file_bad_syntax ()
+    {
+        let data = format!("Package: foo\nthis is not a field\nVersion: 1\n\n{}", PACKAGES);
+        let (index, errors) = read_packages_file (std::io::BufReader::new(data.as_bytes()));
+        assert_eq!(index.len(), 2);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].line, 2);
+        assert!(matches!(errors[0].error, PakigeParseError::InvalidFormat));
+        assert_eq!(errors[1].line, 17);
+    }
 
-// for source debs
-/*
-let comment_line = Regex::new(r"^#.*$").unwrap();
+    static PROVIDERS: &str = "\
+Package: exim4-daemon-light
+Version: 4.96-15
+Architecture: amd64
+Maintainer: Exim4 Maintainers <pkg-exim4-maintainers@lists.alioth.debian.org>
+Provides: mail-transport-agent
+Description: lightweight Exim MTA (v4) daemon
+Filename: pool/main/e/exim4/exim4-daemon-light_4.96-15_amd64.deb
+Size: 1
 
+Package: python3-foo
+Version: 1.5-1
+Architecture: all
+Maintainer: Nobody <nobody@example.org>
+Provides: python3-foo-api (= 3), python3-foo-api (= 2)
+Description: foo for Python 3
+Filename: pool/main/p/foo/python3-foo_1.5-1_all.deb
+Size: 1
 
-// Look for comment line
-else if comment_line.is_match(line)
-{
-continue;
+Package: python3-foo-api
+Version: 1.0
+Architecture: all
+Maintainer: Nobody <nobody@example.org>
+Description: the real foo API package
+Filename: pool/main/p/foo-api/python3-foo-api_1.0_all.deb
+Size: 1
+";
+
+    fn satisfying (index: &PackageIndex, relation: &str) -> Vec<String>
+    {
+        let relation = relationships::parse_package_ref (relation).unwrap();
+        return index.satisfying (&relation).iter().map(|(deb, _)| deb.package.clone()).collect();
+    }
+
+    #[test]
+    fn satisfying_relations ()
+    {
+        let index = PackageIndex::from(PROVIDERS);
+        assert_eq!(index.len(), 3);
+
+        assert_eq!(index.providers ("mail-transport-agent").len(), 1);
+        // Provided twice, indexed once
+        assert_eq!(index.providers ("python3-foo-api").len(), 1);
+
+        assert_eq!(satisfying (&index, "mail-transport-agent"), ["exim4-daemon-light"]);
+        // An unversioned Provides never satisfies a versioned relation
+        assert!(satisfying (&index, "mail-transport-agent (>= 1)").is_empty());
+
+        assert_eq!(satisfying (&index, "python3-foo-api"), ["python3-foo-api", "python3-foo"]);
+        assert_eq!(satisfying (&index, "python3-foo-api (>= 3)"), ["python3-foo"]);
+        assert_eq!(satisfying (&index, "python3-foo-api (<< 2)"), ["python3-foo-api"]);
+        assert_eq!(satisfying (&index, "python3-foo-api (= 2)"), ["python3-foo"]);
+        assert!(satisfying (&index, "python3-foo-api (>> 3)").is_empty());
+
+        assert!(satisfying (&index, "nonexistent").is_empty());
+    }
+
+    #[test]
+    fn effective_source ()
+    {
+        let binary = |fields: &str| BinaryDeb::from_str (&format!(
+            "{}Architecture: amd64\nMaintainer: Nobody <nobody@example.org>\nDescription: test\n", fields));
+
+        let libc6 = binary ("Package: libc6\nSource: glibc (2.36-9)\nVersion: 2.36-9+b1\n").unwrap();
+        let (name, version) = libc6.effective_source();
+        assert_eq!((name, version.to_string().as_str()), ("glibc", "2.36-9"));
+
+        let libc6 = binary ("Package: libc6\nSource: glibc\nVersion: 2.36-9\n").unwrap();
+        let (name, version) = libc6.effective_source();
+        assert_eq!((name, version.to_string().as_str()), ("glibc", "2.36-9"));
+
+        let hello = binary ("Package: hello\nVersion: 2.10-3\n").unwrap();
+        let (name, version) = hello.effective_source();
+        assert_eq!((name, version.to_string().as_str()), ("hello", "2.10-3"));
+
+        let error = binary ("Package: libc6\nSource: glibc (2.36-9\nVersion: 2.36-9\n");
+        assert!(matches!(error, Err(PakigeParseError::InvalidValue)));
+    }
+
+    static SOURCES: &str = "\
+Package: glibc
+Binary: libc6, libc-bin, libc6
+Version: 2.36-9
+Maintainer: GNU Libc Maintainers <debian-glibc@lists.debian.org>
+Architecture: any all
+Format: 3.0 (quilt)
+Files:
+ 025f253325b46929cd34f2a7c3c55e7c 18483468 glibc_2.36.orig.tar.xz
+Directory: pool/main/g/glibc
+Priority: source
+Section: libs
+
+Package: glibc
+Binary: libc6, libc-bin
+Version: 2.37-1
+Maintainer: GNU Libc Maintainers <debian-glibc@lists.debian.org>
+Architecture: any all
+Format: 3.0 (quilt)
+Files:
+ 6e9552c9bd8e61c8f277c21220160234 18885920 glibc_2.37.orig.tar.xz
+Directory: pool/main/g/glibc
+
+Package: hello
+Binary: hello
+Version: 2.10-3
+Maintainer: Santiago Vila <sanvila@debian.org>
+Architecture: any
+Format: 3.0 (quilt)
+Files:
+ 025f253325b46929cd34f2a7c3c55e7c 725946 hello_2.10.orig.tar.gz
+Directory: pool/main/h/hello
+Extra-Source-Only: yes
+
+Package: nodir
+Binary: nodir
+Version: 1.0
+Maintainer: Nobody <nobody@example.org>
+Architecture: all
+Format: 1.0
+Files:
+ 025f253325b46929cd34f2a7c3c55e7c 100 nodir_1.0.tar.gz
+";
+
+    fn versions (entries: Vec<&(Dsc, SourceIndexFields)>) -> Vec<String>
+    {
+        return entries.iter().map(|(dsc, _)| format!("{} {}", dsc.source, dsc.version)).collect();
+    }
+
+    #[test]
+    fn sources_file ()
+    {
+        let (index, errors) = parse_sources_file (SOURCES);
+        assert_eq!(index.len(), 3);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 34);
+        assert!(matches!(errors[0].error, PakigeParseError::MissingMandatoryField));
+
+        let (dsc, index_fields) = &index.entries()[0];
+        assert!(!dsc.is_signed());
+        assert_eq!(dsc.binary, ["libc6", "libc-bin", "libc6"]);
+        assert_eq!(index_fields.directory, "pool/main/g/glibc");
+        assert_eq!(index_fields.section.as_deref(), Some("libs"));
+        assert_eq!(index_fields.priority.as_deref(), Some("source"));
+        assert!(!index_fields.extra_source_only);
+        assert!(index.get("hello")[0].1.extra_source_only);
+
+        assert_eq!(versions (index.get("glibc")), ["glibc 2.36-9", "glibc 2.37-1"]);
+        assert!(index.get("nodir").is_empty());
+        let newer = VersionRef { operation: VerOp::GtEq, version_string: DebVersion::from_str ("2.37").unwrap() };
+        assert_eq!(versions (index.get_version("glibc", &newer)), ["glibc 2.37-1"]);
+
+        // A binary listed twice in one Binary field is indexed once
+        assert_eq!(versions (index.building("libc6")), ["glibc 2.36-9", "glibc 2.37-1"]);
+        assert_eq!(versions (index.building("hello")), ["hello 2.10-3"]);
+        assert!(index.building("glibc").is_empty());
+
+        assert_eq!(SourceIndex::from(SOURCES).len(), 3);
+    }
+
+    #[test]
+    fn source_of_binaries ()
+    {
+        let index = SourceIndex::from(SOURCES);
+        let binary = |fields: &str| BinaryDeb::from_str (&format!(
+            "{}Architecture: amd64\nMaintainer: Nobody <nobody@example.org>\nDescription: test\n", fields)).unwrap();
+
+        // A binNMU names its source version
+        let libc6 = binary ("Package: libc6\nSource: glibc (2.36-9)\nVersion: 2.36-9+b1\n");
+        assert_eq!(versions (index.source_of(&libc6).into_iter().collect()), ["glibc 2.36-9"]);
+
+        let libc6 = binary ("Package: libc6\nSource: glibc\nVersion: 2.37-1\n");
+        assert_eq!(versions (index.source_of(&libc6).into_iter().collect()), ["glibc 2.37-1"]);
+
+        // No Source field: the source has the binary's name
+        let hello = binary ("Package: hello\nVersion: 2.10-3\n");
+        assert_eq!(versions (index.source_of(&hello).into_iter().collect()), ["hello 2.10-3"]);
+
+        let hello = binary ("Package: hello\nVersion: 2.10-4\n");
+        assert!(index.source_of(&hello).is_none());
+    }
+
+    #[test]
+    fn comment_positions ()
+    {
+        let data = "\
+# before
+Source: foo
+Build-Depends: a,
+# inside, after its first line
+ b,
+# inside, after b
+ c
+# between
+Section: devel
+# last";
+        assert!(matches!(str_to_table (data), Err(PakigeParseError::InvalidFormat)));
+
+        let fields = str_to_table_with_mode (data, ParseMode::SkipComments).unwrap();
+        assert_eq!(fields.get("build-depends").map(String::as_str), Some("a,\n b,\n c"));
+        assert_eq!(fields.comments().count(), 0);
+
+        let fields = str_to_table_with_mode (data, ParseMode::KeepComments).unwrap();
+        let expected = [
+            (0, 0, "# before"),
+            (2, 0, "# inside, after its first line"),
+            (2, 1, "# inside, after b"),
+            (2, 2, "# between"),
+            (3, 0, "# last")
+        ];
+        assert_eq!(fields.comments().collect::<Vec<_>>(), expected);
+
+        // The streaming reader places them the same way
+        let stanza = StanzaReader::with_mode (data.as_bytes(), ParseMode::KeepComments).next().unwrap().unwrap();
+        assert_eq!(stanza.comments().collect::<Vec<_>>(), expected);
+        assert_eq!(stanza.to_fields().comments().collect::<Vec<_>>(), expected);
+    }
 }
-*/
\ No newline at end of file
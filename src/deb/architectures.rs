@@ -1,420 +1,627 @@
-// pub enum Arch
-// {
-//     uclibc-linux-armel
-//     uclibc-linux-i386
-//     uclibc-linux-ia64
-//     uclibc-linux-alpha
-//     uclibc-linux-amd64
-//     uclibc-linux-armeb
-//     uclibc-linux-arm
-//     uclibc-linux-arm64
-//     uclibc-linux-avr32
-//     uclibc-linux-hppa
-//     uclibc-linux-m32r
-//     uclibc-linux-m68k
-//     uclibc-linux-mips
-//     uclibc-linux-mipsel
-//     uclibc-linux-mips64
-//     uclibc-linux-mips64el
-//     uclibc-linux-nios2
-//     uclibc-linux-or1k
-//     uclibc-linux-powerpc
-//     uclibc-linux-powerpcel
-//     uclibc-linux-ppc64
-//     uclibc-linux-ppc64el
-//     uclibc-linux-s390
-//     uclibc-linux-s390x
-//     uclibc-linux-sh3
-//     uclibc-linux-sh3eb
-//     uclibc-linux-sh4
-//     uclibc-linux-sh4eb
-//     uclibc-linux-sparc
-//     uclibc-linux-sparc64
-//     musl-linux-armhf
-//     musl-linux-i386
-//     musl-linux-ia64
-//     musl-linux-alpha
-//     musl-linux-amd64
-//     musl-linux-armeb
-//     musl-linux-arm
-//     musl-linux-arm64
-//     musl-linux-avr32
-//     musl-linux-hppa
-//     musl-linux-m32r
-//     musl-linux-m68k
-//     musl-linux-mips
-//     musl-linux-mipsel
-//     musl-linux-mips64
-//     musl-linux-mips64el
-//     musl-linux-nios2
-//     musl-linux-or1k
-//     musl-linux-powerpc
-//     musl-linux-powerpcel
-//     musl-linux-ppc64
-//     musl-linux-ppc64el
-//     musl-linux-s390
-//     musl-linux-s390x
-//     musl-linux-sh3
-//     musl-linux-sh3eb
-//     musl-linux-sh4
-//     musl-linux-sh4eb
-//     musl-linux-sparc
-//     musl-linux-sparc64
-//     armhf
-//     armel
-//     mipsn32
-//     mipsn32el
-//     mips64
-//     mips64el
-//     powerpcspe
-//     x32
-//     i386
-//     ia64
-//     alpha
-//     amd64
-//     armeb
-//     arm
-//     arm64
-//     avr32
-//     hppa
-//     m32r
-//     m68k
-//     mips
-//     mipsel
-//     nios2
-//     or1k
-//     powerpc
-//     powerpcel
-//     ppc64
-//     ppc64el
-//     s390
-//     s390x
-//     sh3
-//     sh3eb
-//     sh4
-//     sh4eb
-//     sparc
-//     sparc64
-//     kfreebsd-armhf
-//     kfreebsd-i386
-//     kfreebsd-ia64
-//     kfreebsd-alpha
-//     kfreebsd-amd64
-//     kfreebsd-armeb
-//     kfreebsd-arm
-//     kfreebsd-arm64
-//     kfreebsd-avr32
-//     kfreebsd-hppa
-//     kfreebsd-m32r
-//     kfreebsd-m68k
-//     kfreebsd-mips
-//     kfreebsd-mipsel
-//     kfreebsd-mips64
-//     kfreebsd-mips64el
-//     kfreebsd-nios2
-//     kfreebsd-or1k
-//     kfreebsd-powerpc
-//     kfreebsd-powerpcel
-//     kfreebsd-ppc64
-//     kfreebsd-ppc64el
-//     kfreebsd-s390
-//     kfreebsd-s390x
-//     kfreebsd-sh3
-//     kfreebsd-sh3eb
-//     kfreebsd-sh4
-//     kfreebsd-sh4eb
-//     kfreebsd-sparc
-//     kfreebsd-sparc64
-//     knetbsd-i386
-//     knetbsd-ia64
-//     knetbsd-alpha
-//     knetbsd-amd64
-//     knetbsd-armeb
-//     knetbsd-arm
-//     knetbsd-arm64
-//     knetbsd-avr32
-//     knetbsd-hppa
-//     knetbsd-m32r
-//     knetbsd-m68k
-//     knetbsd-mips
-//     knetbsd-mipsel
-//     knetbsd-mips64
-//     knetbsd-mips64el
-//     knetbsd-nios2
-//     knetbsd-or1k
-//     knetbsd-powerpc
-//     knetbsd-powerpcel
-//     knetbsd-ppc64
-//     knetbsd-ppc64el
-//     knetbsd-s390
-//     knetbsd-s390x
-//     knetbsd-sh3
-//     knetbsd-sh3eb
-//     knetbsd-sh4
-//     knetbsd-sh4eb
-//     knetbsd-sparc
-//     knetbsd-sparc64
-//     kopensolaris-i386
-//     kopensolaris-ia64
-//     kopensolaris-alpha
-//     kopensolaris-amd64
-//     kopensolaris-armeb
-//     kopensolaris-arm
-//     kopensolaris-arm64
-//     kopensolaris-avr32
-//     kopensolaris-hppa
-//     kopensolaris-m32r
-//     kopensolaris-m68k
-//     kopensolaris-mips
-//     kopensolaris-mipsel
-//     kopensolaris-mips64
-//     kopensolaris-mips64el
-//     kopensolaris-nios2
-//     kopensolaris-or1k
-//     kopensolaris-powerpc
-//     kopensolaris-powerpcel
-//     kopensolaris-ppc64
-//     kopensolaris-ppc64el
-//     kopensolaris-s390
-//     kopensolaris-s390x
-//     kopensolaris-sh3
-//     kopensolaris-sh3eb
-//     kopensolaris-sh4
-//     kopensolaris-sh4eb
-//     kopensolaris-sparc
-//     kopensolaris-sparc64
-//     hurd-i386
-//     hurd-ia64
-//     hurd-alpha
-//     hurd-amd64
-//     hurd-armeb
-//     hurd-arm
-//     hurd-arm64
-//     hurd-avr32
-//     hurd-hppa
-//     hurd-m32r
-//     hurd-m68k
-//     hurd-mips
-//     hurd-mipsel
-//     hurd-mips64
-//     hurd-mips64el
-//     hurd-nios2
-//     hurd-or1k
-//     hurd-powerpc
-//     hurd-powerpcel
-//     hurd-ppc64
-//     hurd-ppc64el
-//     hurd-s390
-//     hurd-s390x
-//     hurd-sh3
-//     hurd-sh3eb
-//     hurd-sh4
-//     hurd-sh4eb
-//     hurd-sparc
-//     hurd-sparc64
-//     darwin-i386
-//     darwin-ia64
-//     darwin-alpha
-//     darwin-amd64
-//     darwin-armeb
-//     darwin-arm
-//     darwin-arm64
-//     darwin-avr32
-//     darwin-hppa
-//     darwin-m32r
-//     darwin-m68k
-//     darwin-mips
-//     darwin-mipsel
-//     darwin-mips64
-//     darwin-mips64el
-//     darwin-nios2
-//     darwin-or1k
-//     darwin-powerpc
-//     darwin-powerpcel
-//     darwin-ppc64
-//     darwin-ppc64el
-//     darwin-s390
-//     darwin-s390x
-//     darwin-sh3
-//     darwin-sh3eb
-//     darwin-sh4
-//     darwin-sh4eb
-//     darwin-sparc
-//     darwin-sparc64
-//     dragonflybsd-i386
-//     dragonflybsd-ia64
-//     dragonflybsd-alpha
-//     dragonflybsd-amd64
-//     dragonflybsd-armeb
-//     dragonflybsd-arm
-//     dragonflybsd-arm64
-//     dragonflybsd-avr32
-//     dragonflybsd-hppa
-//     dragonflybsd-m32r
-//     dragonflybsd-m68k
-//     dragonflybsd-mips
-//     dragonflybsd-mipsel
-//     dragonflybsd-mips64
-//     dragonflybsd-mips64el
-//     dragonflybsd-nios2
-//     dragonflybsd-or1k
-//     dragonflybsd-powerpc
-//     dragonflybsd-powerpcel
-//     dragonflybsd-ppc64
-//     dragonflybsd-ppc64el
-//     dragonflybsd-s390
-//     dragonflybsd-s390x
-//     dragonflybsd-sh3
-//     dragonflybsd-sh3eb
-//     dragonflybsd-sh4
-//     dragonflybsd-sh4eb
-//     dragonflybsd-sparc
-//     dragonflybsd-sparc64
-//     freebsd-i386
-//     freebsd-ia64
-//     freebsd-alpha
-//     freebsd-amd64
-//     freebsd-armeb
-//     freebsd-arm
-//     freebsd-arm64
-//     freebsd-avr32
-//     freebsd-hppa
-//     freebsd-m32r
-//     freebsd-m68k
-//     freebsd-mips
-//     freebsd-mipsel
-//     freebsd-mips64
-//     freebsd-mips64el
-//     freebsd-nios2
-//     freebsd-or1k
-//     freebsd-powerpc
-//     freebsd-powerpcel
-//     freebsd-ppc64
-//     freebsd-ppc64el
-//     freebsd-s390
-//     freebsd-s390x
-//     freebsd-sh3
-//     freebsd-sh3eb
-//     freebsd-sh4
-//     freebsd-sh4eb
-//     freebsd-sparc
-//     freebsd-sparc64
-//     netbsd-i386
-//     netbsd-ia64
-//     netbsd-alpha
-//     netbsd-amd64
-//     netbsd-armeb
-//     netbsd-arm
-//     netbsd-arm64
-//     netbsd-avr32
-//     netbsd-hppa
-//     netbsd-m32r
-//     netbsd-m68k
-//     netbsd-mips
-//     netbsd-mipsel
-//     netbsd-mips64
-//     netbsd-mips64el
-//     netbsd-nios2
-//     netbsd-or1k
-//     netbsd-powerpc
-//     netbsd-powerpcel
-//     netbsd-ppc64
-//     netbsd-ppc64el
-//     netbsd-s390
-//     netbsd-s390x
-//     netbsd-sh3
-//     netbsd-sh3eb
-//     netbsd-sh4
-//     netbsd-sh4eb
-//     netbsd-sparc
-//     netbsd-sparc64
-//     openbsd-i386
-//     openbsd-ia64
-//     openbsd-alpha
-//     openbsd-amd64
-//     openbsd-armeb
-//     openbsd-arm
-//     openbsd-arm64
-//     openbsd-avr32
-//     openbsd-hppa
-//     openbsd-m32r
-//     openbsd-m68k
-//     openbsd-mips
-//     openbsd-mipsel
-//     openbsd-mips64
-//     openbsd-mips64el
-//     openbsd-nios2
-//     openbsd-or1k
-//     openbsd-powerpc
-//     openbsd-powerpcel
-//     openbsd-ppc64
-//     openbsd-ppc64el
-//     openbsd-s390
-//     openbsd-s390x
-//     openbsd-sh3
-//     openbsd-sh3eb
-//     openbsd-sh4
-//     openbsd-sh4eb
-//     openbsd-sparc
-//     openbsd-sparc64
-//     solaris-i386
-//     solaris-ia64
-//     solaris-alpha
-//     solaris-amd64
-//     solaris-armeb
-//     solaris-arm
-//     solaris-arm64
-//     solaris-avr32
-//     solaris-hppa
-//     solaris-m32r
-//     solaris-m68k
-//     solaris-mips
-//     solaris-mipsel
-//     solaris-mips64
-//     solaris-mips64el
-//     solaris-nios2
-//     solaris-or1k
-//     solaris-powerpc
-//     solaris-powerpcel
-//     solaris-ppc64
-//     solaris-ppc64el
-//     solaris-s390
-//     solaris-s390x
-//     solaris-sh3
-//     solaris-sh3eb
-//     solaris-sh4
-//     solaris-sh4eb
-//     solaris-sparc
-//     solaris-sparc64
-//     uclinux-armel
-//     uclinux-i386
-//     uclinux-ia64
-//     uclinux-alpha
-//     uclinux-amd64
-//     uclinux-armeb
-//     uclinux-arm
-//     uclinux-arm64
-//     uclinux-avr32
-//     uclinux-hppa
-//     uclinux-m32r
-//     uclinux-m68k
-//     uclinux-mips
-//     uclinux-mipsel
-//     uclinux-mips64
-//     uclinux-mips64el
-//     uclinux-nios2
-//     uclinux-or1k
-//     uclinux-powerpc
-//     uclinux-powerpcel
-//     uclinux-ppc64
-//     uclinux-ppc64el
-//     uclinux-s390
-//     uclinux-s390x
-//     uclinux-sh3
-//     uclinux-sh3eb
-//     uclinux-sh4
-//     uclinux-sh4eb
-//     uclinux-sparc
-//     uclinux-sparc64
-//     mint-m68k
-// }
\ No newline at end of file
+use crate::PakigeParseError;
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+// https://wiki.debian.org/Multiarch/Tuples
+// dpkg decomposes every architecture name into an `abi-libc-os-cpu` tuple, e.g.
+//     amd64             -> base-gnu-linux-amd64
+//     armhf             -> eabihf-gnu-linux-arm
+//     musl-linux-arm64  -> base-musl-linux-arm64
+// Wildcards such as `linux-any` or `any-arm` are matched component-wise against that tuple.
+// The tables below mirror dpkg's data/cputable, data/ostable and data/tupletable, which also
+// give the GNU triplet and multiarch tuple for each architecture. The bits and endianness
+// come from data/cputable, with data/abitable overriding the bits for ABIs like x32.
+
+/* cputable: (Debian CPU, GNU CPU, GNU CPU regex, bits, endianness) */
+static CPU_TABLE: &[(&str, &str, &str, u8, Endianness)] = &[
+    ("i386", "i686", r"(i[34567]86|pentium)", 32, Endianness::Little),
+    ("ia64", "ia64", r"ia64", 64, Endianness::Little),
+    ("alpha", "alpha", r"alpha.*", 64, Endianness::Little),
+    ("amd64", "x86_64", r"(amd64|x86_64)", 64, Endianness::Little),
+    ("arc", "arc", r"arc", 32, Endianness::Little),
+    ("armeb", "armeb", r"arm.*b", 32, Endianness::Big),
+    ("arm", "arm", r"arm.*", 32, Endianness::Little),
+    ("arm64", "aarch64", r"aarch64", 64, Endianness::Little),
+    ("avr32", "avr32", r"avr32", 32, Endianness::Big),
+    ("hppa", "hppa", r"hppa.*", 32, Endianness::Big),
+    ("loong64", "loongarch64", r"loongarch64", 64, Endianness::Little),
+    ("m32r", "m32r", r"m32r", 32, Endianness::Big),
+    ("m68k", "m68k", r"m68k", 32, Endianness::Big),
+    ("mips", "mips", r"mips(eb)?", 32, Endianness::Big),
+    ("mipsel", "mipsel", r"mipsel", 32, Endianness::Little),
+    ("mipsr6", "mipsisa32r6", r"mipsisa32r6", 32, Endianness::Big),
+    ("mipsr6el", "mipsisa32r6el", r"mipsisa32r6el", 32, Endianness::Little),
+    ("mips64", "mips64", r"mips64", 64, Endianness::Big),
+    ("mips64el", "mips64el", r"mips64el", 64, Endianness::Little),
+    ("mips64r6", "mipsisa64r6", r"mipsisa64r6", 64, Endianness::Big),
+    ("mips64r6el", "mipsisa64r6el", r"mipsisa64r6el", 64, Endianness::Little),
+    ("nios2", "nios2", r"nios2", 32, Endianness::Little),
+    ("or1k", "or1k", r"or1k", 32, Endianness::Big),
+    ("powerpc", "powerpc", r"(powerpc|ppc)", 32, Endianness::Big),
+    ("powerpcel", "powerpcle", r"powerpcle", 32, Endianness::Little),
+    ("ppc64", "powerpc64", r"(powerpc|ppc)64", 64, Endianness::Big),
+    ("ppc64el", "powerpc64le", r"powerpc64le", 64, Endianness::Little),
+    ("riscv64", "riscv64", r"riscv64", 64, Endianness::Little),
+    ("s390", "s390", r"s390", 32, Endianness::Big),
+    ("s390x", "s390x", r"s390x", 64, Endianness::Big),
+    ("sh3", "sh3", r"sh3", 32, Endianness::Little),
+    ("sh3eb", "sh3eb", r"sh3eb", 32, Endianness::Big),
+    ("sh4", "sh4", r"sh4", 32, Endianness::Little),
+    ("sh4eb", "sh4eb", r"sh4eb", 32, Endianness::Big),
+    ("sparc", "sparc", r"sparc", 32, Endianness::Big),
+    ("sparc64", "sparc64", r"sparc64", 64, Endianness::Big)
+];
+
+/* ostable: (Debian `abi-libc-os`, GNU system, GNU system regex) */
+// Order matters: the regexes are tried in turn, so more specific systems come first.
+static OS_TABLE: &[(&str, &str, &str)] = &[
+    ("eabi-uclibc-linux", "linux-uclibceabi", r"linux[^-]*-uclibceabi"),
+    ("base-uclibc-linux", "linux-uclibc", r"linux[^-]*-uclibc"),
+    ("eabihf-musl-linux", "linux-musleabihf", r"linux[^-]*-musleabihf"),
+    ("base-musl-linux", "linux-musl", r"linux[^-]*-musl"),
+    ("eabihf-gnu-linux", "linux-gnueabihf", r"linux[^-]*-gnueabihf"),
+    ("eabi-gnu-linux", "linux-gnueabi", r"linux[^-]*-gnueabi"),
+    ("abin32-gnu-linux", "linux-gnuabin32", r"linux[^-]*-gnuabin32"),
+    ("abi64-gnu-linux", "linux-gnuabi64", r"linux[^-]*-gnuabi64"),
+    ("spe-gnu-linux", "linux-gnuspe", r"linux[^-]*-gnuspe"),
+    ("x32-gnu-linux", "linux-gnux32", r"linux[^-]*-gnux32"),
+    ("base-gnu-linux", "linux-gnu", r"linux[^-]*(-gnu.*)?"),
+    ("eabihf-gnu-kfreebsd", "kfreebsd-gnueabihf", r"kfreebsd[^-]*-gnueabihf"),
+    ("base-gnu-kfreebsd", "kfreebsd-gnu", r"kfreebsd[^-]*(-gnu.*)?"),
+    ("base-gnu-knetbsd", "knetbsd-gnu", r"knetbsd[^-]*(-gnu.*)?"),
+    ("base-gnu-kopensolaris", "kopensolaris-gnu", r"kopensolaris[^-]*(-gnu.*)?"),
+    ("base-gnu-hurd", "gnu", r"gnu[^-]*"),
+    ("base-bsd-darwin", "darwin", r"darwin[^-]*"),
+    ("base-bsd-dragonflybsd", "dragonflybsd", r"dragonfly[^-]*"),
+    ("base-bsd-freebsd", "freebsd", r"freebsd[^-]*"),
+    ("base-bsd-netbsd", "netbsd", r"netbsd[^-]*"),
+    ("base-bsd-openbsd", "openbsd", r"openbsd[^-]*"),
+    ("base-sysv-aix", "aix", r"aix[^-]*"),
+    ("base-sysv-solaris", "solaris", r"solaris[^-]*"),
+    ("eabi-uclibc-uclinux", "uclinux-uclibceabi", r"uclinux[^-]*-uclibceabi"),
+    ("base-uclibc-uclinux", "uclinux-uclibc", r"uclinux[^-]*(-uclibc.*)?"),
+    ("base-tos-mint", "mint", r"mint[^-]*")
+];
+
+/* abitable: (Debian ABI, bits), overriding the CPU's native word size */
+static ABI_TABLE: &[(&str, u8)] = &[
+    ("abin32", 32),
+    ("abi64", 64),
+    ("x32", 32)
+];
+
+/* tupletable: (Debian tuple, Debian architecture), where `<cpu>` expands to every CPU */
+// Order matters: specific entries must come before the `<cpu>` templates that would also match them.
+static TUPLE_TABLE: &[(&str, &str)] = &[
+    ("eabi-uclibc-linux-arm", "uclibc-linux-armel"),
+    ("base-uclibc-linux-<cpu>", "uclibc-linux-<cpu>"),
+    ("eabihf-musl-linux-arm", "musl-linux-armhf"),
+    ("base-musl-linux-<cpu>", "musl-linux-<cpu>"),
+    ("x32-gnu-linux-amd64", "x32"),
+    ("eabihf-gnu-linux-arm", "armhf"),
+    ("eabi-gnu-linux-arm", "armel"),
+    ("abin32-gnu-linux-mips64r6el", "mipsn32r6el"),
+    ("abin32-gnu-linux-mips64r6", "mipsn32r6"),
+    ("abin32-gnu-linux-mips64el", "mipsn32el"),
+    ("abin32-gnu-linux-mips64", "mipsn32"),
+    ("abi64-gnu-linux-mips64r6el", "mips64r6el"),
+    ("abi64-gnu-linux-mips64r6", "mips64r6"),
+    ("abi64-gnu-linux-mips64el", "mips64el"),
+    ("abi64-gnu-linux-mips64", "mips64"),
+    ("spe-gnu-linux-powerpc", "powerpcspe"),
+    ("base-gnu-linux-<cpu>", "<cpu>"),
+    ("eabihf-gnu-kfreebsd-arm", "kfreebsd-armhf"),
+    ("base-gnu-kfreebsd-<cpu>", "kfreebsd-<cpu>"),
+    ("base-gnu-knetbsd-<cpu>", "knetbsd-<cpu>"),
+    ("base-gnu-kopensolaris-<cpu>", "kopensolaris-<cpu>"),
+    ("base-gnu-hurd-<cpu>", "hurd-<cpu>"),
+    ("base-bsd-darwin-<cpu>", "darwin-<cpu>"),
+    ("base-bsd-dragonflybsd-<cpu>", "dragonflybsd-<cpu>"),
+    ("base-bsd-freebsd-<cpu>", "freebsd-<cpu>"),
+    ("base-bsd-netbsd-<cpu>", "netbsd-<cpu>"),
+    ("base-bsd-openbsd-<cpu>", "openbsd-<cpu>"),
+    ("base-sysv-aix-<cpu>", "aix-<cpu>"),
+    ("base-sysv-solaris-<cpu>", "solaris-<cpu>"),
+    ("eabi-uclibc-uclinux-arm", "uclinux-armel"),
+    ("base-uclibc-uclinux-<cpu>", "uclinux-<cpu>"),
+    ("base-tos-mint-m68k", "mint-m68k")
+];
+
+// The GNU CPU and system regexes of the tables, anchored and compiled once, in table order
+fn cpu_rules () -> &'static [Regex]
+{
+    static RULES: OnceLock<Vec<Regex>> = OnceLock::new();
+    return RULES.get_or_init(|| CPU_TABLE.iter().map(|entry| Regex::new(&format!("^(?:{})$", entry.2)).unwrap()).collect());
+}
+
+fn os_rules () -> &'static [Regex]
+{
+    static RULES: OnceLock<Vec<Regex>> = OnceLock::new();
+    return RULES.get_or_init(|| OS_TABLE.iter().map(|entry| Regex::new(&format!("^(?:{})$", entry.2)).unwrap()).collect());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endianness
+{
+    Little,
+    Big
+}
+
+impl fmt::Display for Endianness
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            Endianness::Little => write!(f, "little"),
+            Endianness::Big => write!(f, "big")
+        }
+    }
+}
+
+// A concrete (non-wildcard) Debian architecture. Its parts always come from the tables, so it
+// can only be built by parsing a name or a tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Arch
+{
+    abi: &'static str,
+    libc: &'static str,
+    os: &'static str,
+    cpu: &'static str
+}
+
+impl Arch
+{
+    /* e.g. `eabihf` for armhf, `base` for most architectures */
+    pub fn abi (&self) -> &'static str
+    {
+        return self.abi;
+    }
+
+    pub fn libc (&self) -> &'static str
+    {
+        return self.libc;
+    }
+
+    pub fn os (&self) -> &'static str
+    {
+        return self.os;
+    }
+
+    /* The Debian CPU name, e.g. `arm` for armhf */
+    pub fn cpu (&self) -> &'static str
+    {
+        return self.cpu;
+    }
+
+    fn from_tuple (os_tuple: &str, cpu: &str) -> Option<Arch>
+    {
+        let os_tuple = OS_TABLE.iter().find(|entry| entry.0 == os_tuple)?.0;
+        let cpu = CPU_TABLE.iter().find(|entry| entry.0 == cpu)?.0;
+
+        let mut parts = os_tuple.splitn(3, '-');
+        return Some(Arch {
+            abi: parts.next()?,
+            libc: parts.next()?,
+            os: parts.next()?,
+            cpu
+        });
+    }
+
+    /* Every architecture name dpkg knows about, in tupletable order */
+    pub fn all () -> Vec<Arch>
+    {
+        let mut arches: Vec<Arch> = Vec::new();
+
+        for (_, template) in TUPLE_TABLE
+        {
+            let names: Vec<String> = if template.contains("<cpu>")
+            {
+                CPU_TABLE.iter().map(|cpu| template.replace("<cpu>", cpu.0)).collect()
+            }
+            else
+            {
+                vec![template.to_string()]
+            };
+
+            // As in dpkg, the first tupletable entry for a name wins (e.g. `mips64` is abi64)
+            for name in names
+            {
+                if let Ok(arch) = Arch::from_str (&name)
+                {
+                    if !arches.contains(&arch)
+                    {
+                        arches.push(arch);
+                    }
+                }
+            }
+        }
+
+        return arches;
+    }
+
+    /* The `abi-libc-os-cpu` tuple, e.g. `eabihf-gnu-linux-arm` */
+    pub fn tuple (&self) -> String
+    {
+        return format!("{}-{}-{}-{}", self.abi, self.libc, self.os, self.cpu);
+    }
+
+    fn cpu_entry (&self) -> &'static (&'static str, &'static str, &'static str, u8, Endianness)
+    {
+        // An Arch can only be built from table entries, so the lookups cannot fail
+        return CPU_TABLE.iter().find(|entry| entry.0 == self.cpu).unwrap();
+    }
+
+    fn os_entry (&self) -> &'static (&'static str, &'static str, &'static str)
+    {
+        let os_tuple = format!("{}-{}-{}", self.abi, self.libc, self.os);
+        return OS_TABLE.iter().find(|entry| entry.0 == os_tuple).unwrap();
+    }
+
+    /* DEB_HOST_GNU_CPU, e.g. `x86_64` */
+    pub fn gnu_cpu (&self) -> &'static str
+    {
+        return self.cpu_entry().1;
+    }
+
+    /* DEB_HOST_GNU_SYSTEM, e.g. `linux-gnueabihf` */
+    pub fn gnu_system (&self) -> &'static str
+    {
+        return self.os_entry().1;
+    }
+
+    /* DEB_HOST_GNU_TYPE, e.g. `x86_64-linux-gnu` */
+    pub fn gnu_triplet (&self) -> String
+    {
+        return format!("{}-{}", self.gnu_cpu(), self.gnu_system());
+    }
+
+    /* DEB_HOST_MULTIARCH, e.g. `i386-linux-gnu`, which differs from the GNU triplet on i386 */
+    pub fn multiarch (&self) -> String
+    {
+        let triplet = self.gnu_triplet();
+
+        // i386 through i686
+        let is_x86 = |cpu: &str| cpu.len() == 4 && cpu.starts_with('i') && cpu.ends_with("86") && (b'3'..=b'6').contains(&cpu.as_bytes()[1]);
+
+        return match triplet.split_once('-')
+        {
+            Some((cpu, system)) if is_x86 (cpu) => format!("i386-{}", system),
+            _ => triplet
+        };
+    }
+
+    /* DEB_HOST_ARCH_BITS, e.g. 32 on x32 even though the CPU is amd64 */
+    pub fn bits (&self) -> u8
+    {
+        return match ABI_TABLE.iter().find(|entry| entry.0 == self.abi)
+        {
+            Some(entry) => entry.1,
+            None => self.cpu_entry().3
+        };
+    }
+
+    /* DEB_HOST_ARCH_ENDIAN */
+    pub fn endianness (&self) -> Endianness
+    {
+        return self.cpu_entry().4;
+    }
+
+    // Mirrors dpkg's gnutriplet_to_debarch(): the CPU and system halves are matched against the
+    // table regexes in order, then the resulting tuple must map back to an architecture name.
+    pub fn from_gnu_triplet (triplet: &str) -> Result<Arch, PakigeParseError>
+    {
+        let (gnu_cpu, gnu_system) = triplet.split_once('-').ok_or(PakigeParseError::InvalidValue)?;
+
+        let (cpu, _) = CPU_TABLE.iter().zip(cpu_rules())
+            .find(|(_, rule)| rule.is_match(gnu_cpu))
+            .ok_or(PakigeParseError::InvalidValue)?;
+        let (os_tuple, _) = OS_TABLE.iter().zip(os_rules())
+            .find(|(_, rule)| rule.is_match(gnu_system))
+            .ok_or(PakigeParseError::InvalidValue)?;
+
+        let arch = Arch::from_tuple (os_tuple.0, cpu.0).ok_or(PakigeParseError::InvalidValue)?;
+
+        // Tuples like `abin32-gnu-linux-arm` have no architecture name
+        return match Arch::from_str (&arch.to_string())
+        {
+            Ok(named) if named == arch => Ok(arch),
+            _ => Err(PakigeParseError::InvalidValue)
+        };
+    }
+
+    // Mirrors dpkg's debarch_is(): `any` matches everything, and wildcards like `linux-any`,
+    // `any-amd64` or `musl-any-any` match component-wise against the architecture's tuple.
+    pub fn matches (&self, wildcard: &str) -> bool
+    {
+        if wildcard == "any"
+        {
+            return true;
+        }
+
+        let pattern = match wildcard_to_tuple (wildcard)
+        {
+            Some(pattern) => pattern,
+            None => return false
+        };
+
+        let tuple = [self.abi, self.libc, self.os, self.cpu];
+        return pattern.iter().zip(tuple.iter()).all(|(want, have)| *want == "any" || want == have);
+    }
+}
+
+impl fmt::Display for Arch
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let tuple = self.tuple();
+
+        for (template, name) in TUPLE_TABLE
+        {
+            if *template == tuple
+            {
+                return write!(f, "{}", name);
+            }
+            if let Some(prefix) = template.strip_suffix("<cpu>")
+            {
+                if tuple.strip_prefix(prefix) == Some(self.cpu)
+                {
+                    return write!(f, "{}", name.replace("<cpu>", self.cpu));
+                }
+            }
+        }
+
+        // Valid tuples that tupletable gives no name, such as `eabi-gnu-linux-armeb`, are shown as is
+        return write!(f, "{}", tuple);
+    }
+}
+
+impl FromStr for Arch
+{
+    type Err = PakigeParseError;
+
+    fn from_str (name: &str) -> Result<Self, Self::Err>
+    {
+        // dpkg still accepts the legacy `linux-<cpu>` spelling
+        let name = name.strip_prefix("linux-").unwrap_or(name);
+
+        for (tuple, template) in TUPLE_TABLE
+        {
+            let (os_tuple, cpu) = tuple.rsplit_once('-').unwrap();
+
+            let cpu = if cpu == "<cpu>"
+            {
+                let (prefix, suffix) = template.split_once("<cpu>").unwrap();
+                match name.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(suffix))
+                {
+                    Some(cpu) => cpu,
+                    None => continue
+                }
+            }
+            else if name == *template
+            {
+                cpu
+            }
+            else
+            {
+                continue;
+            };
+
+            if let Some(arch) = Arch::from_tuple (os_tuple, cpu)
+            {
+                return Ok(arch);
+            }
+        }
+
+        return Err(PakigeParseError::InvalidValue);
+    }
+}
+
+// Mirrors dpkg's debwildcard_to_debtuple(): missing leading components are filled with `any`
+fn wildcard_to_tuple (wildcard: &str) -> Option<[&str; 4]>
+{
+    let parts: Vec<&str> = wildcard.splitn(4, '-').collect();
+
+    if !parts.contains(&"any")
+    {
+        let arch = Arch::from_str (wildcard).ok()?;
+        return Some([arch.abi, arch.libc, arch.os, arch.cpu]);
+    }
+
+    let mut tuple = ["any"; 4];
+    for (slot, part) in tuple.iter_mut().skip(4 - parts.len()).zip(parts)
+    {
+        *slot = part;
+    }
+
+    return Some(tuple);
+}
+
+// Mirrors dpkg's debarch_is(): works on names, so `all` and unknown architectures can only
+// match themselves (or `any`).
+pub fn arch_is (arch: &str, wildcard: &str) -> bool
+{
+    if arch == wildcard || wildcard == "any"
+    {
+        return true;
+    }
+
+    return match Arch::from_str (arch)
+    {
+        Ok(arch) => arch.matches (wildcard),
+        Err(_) => false
+    };
+}
+
+// Architecture names are hyphen-separated parts of lower case letters and digits. Names that
+// are not in the tables are still valid: dpkg only needs to know an architecture to build for it.
+pub fn is_arch_name (name: &str) -> bool
+{
+    return name.split('-')
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+}
+
+pub fn is_wildcard (name: &str) -> bool
+{
+    return name.split('-').any(|part| part == "any");
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn arch (name: &str) -> Arch
+    {
+        return Arch::from_str (name).unwrap();
+    }
+
+    #[test]
+    fn tuples ()
+    {
+        let cases = [
+            ("amd64", "base-gnu-linux-amd64"),
+            ("i386", "base-gnu-linux-i386"),
+            ("armhf", "eabihf-gnu-linux-arm"),
+            ("armel", "eabi-gnu-linux-arm"),
+            ("arc", "base-gnu-linux-arc"),
+            ("x32", "x32-gnu-linux-amd64"),
+            ("mips64el", "abi64-gnu-linux-mips64el"),
+            ("mipsn32", "abin32-gnu-linux-mips64"),
+            ("powerpcspe", "spe-gnu-linux-powerpc"),
+            ("musl-linux-arm64", "base-musl-linux-arm64"),
+            ("musl-linux-armhf", "eabihf-musl-linux-arm"),
+            ("hurd-i386", "base-gnu-hurd-i386"),
+            ("kfreebsd-amd64", "base-gnu-kfreebsd-amd64"),
+            ("uclinux-armel", "eabi-uclibc-uclinux-arm"),
+            ("mint-m68k", "base-tos-mint-m68k")
+        ];
+
+        for (name, tuple) in cases
+        {
+            assert_eq!(arch (name).tuple(), tuple, "{}", name);
+            assert_eq!(arch (name).to_string(), name);
+        }
+    }
+
+    #[test]
+    fn names ()
+    {
+        // The legacy `linux-` prefix names the same architecture
+        assert_eq!(arch ("linux-amd64"), arch ("amd64"));
+        assert_eq!(arch ("linux-amd64").to_string(), "amd64");
+
+        assert!(Arch::from_str ("all").is_err());
+        assert!(Arch::from_str ("any").is_err());
+        assert!(Arch::from_str ("linux-any").is_err());
+        assert!(Arch::from_str ("amd65").is_err());
+
+        for arch in Arch::all()
+        {
+            assert_eq!(Arch::from_str (&arch.to_string()).unwrap(), arch);
+        }
+    }
+
+    #[test]
+    fn wildcards ()
+    {
+        assert!(arch ("amd64").matches ("any"));
+        assert!(arch ("armhf").matches ("linux-any"));
+        assert!(arch ("armhf").matches ("any-arm"));
+        assert!(arch ("armhf").matches ("eabihf-any-any-any"));
+        assert!(arch ("armhf").matches ("gnu-any-any"));
+        assert!(!arch ("armhf").matches ("any-amd64"));
+        assert!(!arch ("hurd-i386").matches ("linux-any"));
+        assert!(arch ("hurd-i386").matches ("any-i386"));
+        assert!(arch ("musl-linux-amd64").matches ("musl-any-any"));
+        assert!(!arch ("amd64").matches ("musl-any-any"));
+        assert!(arch ("amd64").matches ("amd64"));
+        assert!(!arch ("amd64").matches ("i386"));
+
+        assert!(arch_is ("all", "all"));
+        assert!(arch_is ("all", "any"));
+        assert!(!arch_is ("all", "linux-any"));
+        assert!(arch_is ("newarch", "newarch"));
+        assert!(!arch_is ("newarch", "linux-any"));
+
+        assert!(is_wildcard ("linux-any"));
+        assert!(!is_wildcard ("amd64"));
+    }
+
+    #[test]
+    fn parts ()
+    {
+        let armhf = arch ("armhf");
+        assert_eq!((armhf.abi(), armhf.libc(), armhf.os(), armhf.cpu()), ("eabihf", "gnu", "linux", "arm"));
+        assert!(Arch::from_str ("nope").is_err());
+    }
+
+    #[test]
+    fn arch_names ()
+    {
+        assert!(is_arch_name ("amd64"));
+        assert!(is_arch_name ("newarch64"));
+        assert!(is_arch_name ("linux-any"));
+        assert!(!is_arch_name (""));
+        assert!(!is_arch_name ("AMD64"));
+        assert!(!is_arch_name ("amd64 i386"));
+        for name in ["-", "-amd64", "amd64-", "linux--any"]
+        {
+            assert!(!is_arch_name (name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn gnu_triplets ()
+    {
+        let cases = [
+            ("amd64", "x86_64-linux-gnu", "x86_64-linux-gnu"),
+            ("i386", "i686-linux-gnu", "i386-linux-gnu"),
+            ("armhf", "arm-linux-gnueabihf", "arm-linux-gnueabihf"),
+            ("arm64", "aarch64-linux-gnu", "aarch64-linux-gnu"),
+            ("x32", "x86_64-linux-gnux32", "x86_64-linux-gnux32"),
+            ("mipsn32el", "mips64el-linux-gnuabin32", "mips64el-linux-gnuabin32"),
+            ("hurd-i386", "i686-gnu", "i386-gnu"),
+            ("musl-linux-amd64", "x86_64-linux-musl", "x86_64-linux-musl")
+        ];
+
+        for (name, triplet, multiarch) in cases
+        {
+            assert_eq!(arch (name).gnu_triplet(), triplet);
+            assert_eq!(arch (name).multiarch(), multiarch);
+            assert_eq!(Arch::from_gnu_triplet (triplet).unwrap(), arch (name));
+        }
+
+        // Every architecture round-trips through its triplet
+        for arch in Arch::all()
+        {
+            assert_eq!(Arch::from_gnu_triplet (&arch.gnu_triplet()).unwrap(), arch, "{}", arch);
+        }
+    }
+
+    #[test]
+    fn gnu_triplet_spellings ()
+    {
+        assert_eq!(Arch::from_gnu_triplet ("i586-linux-gnu").unwrap(), arch ("i386"));
+        assert!(Arch::from_gnu_triplet ("i486-pc-linux-gnu").is_err()); // As in dpkg, vendors are not stripped
+        assert_eq!(Arch::from_gnu_triplet ("powerpc64le-linux-gnu").unwrap(), arch ("ppc64el"));
+        assert!(Arch::from_gnu_triplet ("x86_64").is_err());
+        assert!(Arch::from_gnu_triplet ("vax-linux-gnu").is_err());
+
+        // A valid tuple without an architecture name
+        assert!(Arch::from_gnu_triplet ("armeb-linux-gnueabi").is_err());
+        let armeb = Arch { abi: "eabi", libc: "gnu", os: "linux", cpu: "armeb" };
+        assert_eq!(armeb.to_string(), "eabi-gnu-linux-armeb");
+    }
+
+    #[test]
+    fn bits_and_endianness ()
+    {
+        assert_eq!((arch ("amd64").bits(), arch ("amd64").endianness()), (64, Endianness::Little));
+        assert_eq!((arch ("x32").bits(), arch ("x32").endianness()), (32, Endianness::Little));
+        assert_eq!((arch ("mipsn32").bits(), arch ("mipsn32").endianness()), (32, Endianness::Big));
+        assert_eq!((arch ("s390x").bits(), arch ("s390x").endianness()), (64, Endianness::Big));
+        assert_eq!((arch ("arc").bits(), arch ("arc").endianness()), (32, Endianness::Little));
+    }
+}
@@ -0,0 +1,720 @@
+use crate::PakigeParseError;
+use crate::compression::{decoder, Compression, DecompressError};
+use super::tar::{EntryKind, TarReader};
+use super::{str_to_table, BinaryDeb, Conffiles, Md5Sums, Shlibs, StanzaError, Symbols, Triggers};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::str::FromStr;
+
+// A binary package is an ar archive holding, in order, `debian-binary` (the format version),
+// `control.tar[.gz|.xz|.zst]` and `data.tar[.gz|.xz|.zst|.bz2|.lzma]`, as deb(5) describes.
+// Members whose names start with `_` may appear anywhere after `debian-binary` and are skipped,
+// as dpkg-deb does. The control archive is small and is read up front; the payload is only read,
+// and decompressed as it goes, when its entries are listed.
+
+static AR_MAGIC: &[u8] = b"!<arch>\n";
+const AR_HEADER: usize = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveError
+{
+    NotAnArchive,
+    InvalidMember,
+    UnsupportedVersion(String),
+    MissingMember(&'static str),
+    UnexpectedMember(String),
+    UnsupportedCompression(String),
+    Decompress(String, DecompressError),
+    InvalidTar,
+    MissingControlFile(String)
+}
+
+impl fmt::Display for ArchiveError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            ArchiveError::NotAnArchive => write!(f, "file is not an ar archive"),
+            ArchiveError::InvalidMember => write!(f, "archive member header is invalid or truncated"),
+            ArchiveError::UnsupportedVersion(version) => write!(f, "unsupported package format version '{}'", version),
+            ArchiveError::MissingMember(name) => write!(f, "archive has no '{}' member", name),
+            ArchiveError::UnexpectedMember(name) => write!(f, "unexpected archive member '{}'", name),
+            ArchiveError::UnsupportedCompression(name) => write!(f, "member '{}' uses an unsupported compression", name),
+            ArchiveError::Decompress(name, error) => write!(f, "member '{}' could not be decompressed: {}", name, error),
+            ArchiveError::InvalidTar => write!(f, "tar archive is invalid or truncated"),
+            ArchiveError::MissingControlFile(name) => write!(f, "control archive has no '{}' file", name)
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<ArchiveError> for PakigeParseError
+{
+    fn from (error: ArchiveError) -> PakigeParseError
+    {
+        return PakigeParseError::InvalidArchive(error);
+    }
+}
+
+/* Reads the members of an ar archive, header by header, seeking over the data not needed */
+struct ArReader<R: Read + Seek>
+{
+    reader: R,
+    length: u64,
+    next: u64 // Offset of the next member header
+}
+
+impl<R: Read + Seek> ArReader<R>
+{
+    fn new (mut reader: R) -> Result<Self, PakigeParseError>
+    {
+        let length = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        let mut magic = [0u8; 8];
+        if reader.read_exact(&mut magic).is_err() || magic != AR_MAGIC
+        {
+            return Err(ArchiveError::NotAnArchive.into());
+        }
+        return Ok(ArReader { reader, length, next: AR_MAGIC.len() as u64 });
+    }
+
+    /* The next member's (name, offset of its data, size) */
+    fn next_member (&mut self) -> Result<Option<(String, u64, u64)>, PakigeParseError>
+    {
+        if self.next >= self.length
+        {
+            return Ok(None);
+        }
+        if self.next + AR_HEADER as u64 > self.length
+        {
+            return Err(ArchiveError::InvalidMember.into());
+        }
+
+        let mut header = [0u8; AR_HEADER];
+        self.reader.seek(SeekFrom::Start(self.next))?;
+        self.reader.read_exact(&mut header)?;
+        if &header[58..60] != b"`\n"
+        {
+            return Err(ArchiveError::InvalidMember.into());
+        }
+
+        // GNU ar ends names with a slash
+        let name = String::from_utf8_lossy(&header[..16]);
+        let name = name.trim_end_matches(' ').trim_end_matches('/').to_string();
+        let size: u64 = std::str::from_utf8(&header[48..58]).ok()
+            .and_then(|size| size.trim_end_matches(' ').parse().ok())
+            .ok_or(ArchiveError::InvalidMember)?;
+
+        let start = self.next + AR_HEADER as u64;
+        let end = start.checked_add(size).filter(|end| *end <= self.length).ok_or(ArchiveError::InvalidMember)?;
+
+        // Members are padded to an even offset
+        self.next = end + size % 2;
+        return Ok(Some((name, start, size)));
+    }
+
+    /* Reads a member's data whole */
+    fn read (&mut self, offset: u64, size: u64) -> Result<Vec<u8>, PakigeParseError>
+    {
+        let mut data = Vec::new();
+        self.reader.seek(SeekFrom::Start(offset))?;
+        (&mut self.reader).take(size).read_to_end(&mut data)?;
+        if data.len() as u64 != size
+        {
+            return Err(ArchiveError::InvalidMember.into());
+        }
+        return Ok(data);
+    }
+}
+
+/* `control.tar.xz` -> its compression, given the expected `control.tar` stem */
+fn member_compression (name: &str, stem: &str) -> Option<Result<Compression, ArchiveError>>
+{
+    let extension = name.strip_prefix(stem)?;
+    let extension = match extension.strip_prefix('.')
+    {
+        Some(extension) => extension,
+        None if extension.is_empty() => "",
+        None => return None
+    };
+    return Some(Compression::from_extension (extension).ok_or(ArchiveError::UnsupportedCompression(name.to_string())));
+}
+
+// Errors of the reader under a decoder come out of it like errors in the data. They are marked
+// on the way in, so that a failing disk is not reported as a corrupt package.
+#[derive(Debug)]
+struct SourceError(io::Error);
+
+impl fmt::Display for SourceError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        return self.0.fmt(f);
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+struct Source<R>(R);
+
+fn source_error (error: io::Error) -> io::Error
+{
+    return io::Error::new(error.kind(), SourceError(error));
+}
+
+impl<R: Read> Read for Source<R>
+{
+    fn read (&mut self, buffer: &mut [u8]) -> io::Result<usize>
+    {
+        return self.0.read(buffer).map_err(source_error);
+    }
+}
+
+impl<R: BufRead> BufRead for Source<R>
+{
+    fn fill_buf (&mut self) -> io::Result<&[u8]>
+    {
+        return self.0.fill_buf().map_err(source_error);
+    }
+
+    fn consume (&mut self, amount: usize)
+    {
+        self.0.consume(amount);
+    }
+}
+
+// Decompressed data of an archive member. Decompression errors are reported as the member's,
+// and errors reading the archive itself are passed on as they are.
+struct MemberReader<'a>
+{
+    name: String,
+    reader: Box<dyn Read + 'a>
+}
+
+impl Read for MemberReader<'_>
+{
+    fn read (&mut self, buffer: &mut [u8]) -> io::Result<usize>
+    {
+        return self.reader.read(buffer).map_err(|error| {
+            let kind = error.kind();
+            return match error.into_inner().map(|inner| inner.downcast::<SourceError>())
+            {
+                Some(Ok(source)) => source.0,
+                _ => io::Error::new(kind, ArchiveError::Decompress(self.name.clone(), DecompressError::from(io::Error::from(kind))))
+            };
+        });
+    }
+}
+
+/* Decompresses `control.tar.*` or `data.tar.*`, given as `stem`, while it is read */
+fn member_reader<'a, R: BufRead + 'a> (name: &str, stem: &str, mut reader: R) -> Result<MemberReader<'a>, PakigeParseError>
+{
+    let compression = match member_compression (name, stem)
+    {
+        Some(compression) => compression?,
+        None => return Err(ArchiveError::UnexpectedMember(name.to_string()).into())
+    };
+    // Read ahead here, so that an error reading the header is not taken for a bad header
+    reader.fill_buf()?;
+    let reader = decoder (Source(reader), compression).map_err(|error| ArchiveError::Decompress(name.to_string(), error))?;
+    return Ok(MemberReader { name: name.to_string(), reader });
+}
+
+/* Where the still compressed payload is: in memory, or at an offset in a file */
+enum Payload<'a>
+{
+    Bytes(&'a [u8]),
+    File(File, u64, u64) // (the package file as opened, offset, size)
+}
+
+// A range of a file, read through a handle of its own. It seeks before every read, so that
+// several readers can share the file without disturbing one another.
+struct FileRange
+{
+    file: File,
+    position: u64,
+    end: u64
+}
+
+impl Read for FileRange
+{
+    fn read (&mut self, buffer: &mut [u8]) -> io::Result<usize>
+    {
+        let length = buffer.len().min((self.end - self.position) as usize);
+        if length == 0
+        {
+            return Ok(0);
+        }
+        self.file.seek(SeekFrom::Start(self.position))?;
+        let read = self.file.read(&mut buffer[..length])?;
+        self.position += read as u64;
+        return Ok(read);
+    }
+}
+
+pub struct DebArchive<'a>
+{
+    version: String,
+    control_files: Vec<(String, Vec<u8>)>,
+    data_name: String,
+    payload: Payload<'a>
+}
+
+// The entries of a package's payload, which can be listed and read without extracting anything
+// to disk. The payload is decompressed as the entries are read.
+pub type DataArchive<'a> = TarReader<Box<dyn Read + 'a>>;
+
+/* The parts of a package found by reading its ar members */
+struct Members
+{
+    version: String,
+    control_files: Vec<(String, Vec<u8>)>,
+    data_name: String,
+    data: (u64, u64) // (offset, size) of the payload member
+}
+
+fn read_members<R: Read + Seek> (reader: R) -> Result<Members, PakigeParseError>
+{
+    let mut ar = ArReader::new (reader)?;
+
+    let (name, offset, size) = ar.next_member()?.ok_or(ArchiveError::MissingMember("debian-binary"))?;
+    if name != "debian-binary"
+    {
+        return Err(ArchiveError::MissingMember("debian-binary").into());
+    }
+    // Only the major version matters; dpkg accepts any 2.x
+    let version = String::from_utf8_lossy(&ar.read (offset, size)?).trim_end_matches('\n').to_string();
+    if !version.starts_with("2.") || version.contains('\n')
+    {
+        return Err(ArchiveError::UnsupportedVersion(version).into());
+    }
+
+    let mut next_member = || -> Result<Option<(String, u64, u64)>, PakigeParseError> {
+        loop
+        {
+            match ar.next_member()?
+            {
+                Some((name, _, _)) if name.starts_with('_') => continue,
+                member => return Ok(member)
+            }
+        }
+    };
+
+    let (control_name, control_offset, control_size) = next_member()?.ok_or(ArchiveError::MissingMember("control.tar"))?;
+    if member_compression (&control_name, "control.tar").is_none()
+    {
+        return Err(ArchiveError::UnexpectedMember(control_name).into());
+    }
+
+    let (data_name, data_offset, data_size) = next_member()?.ok_or(ArchiveError::MissingMember("data.tar"))?;
+    if member_compression (&data_name, "data.tar").is_none()
+    {
+        return Err(ArchiveError::UnexpectedMember(data_name).into());
+    }
+    if let Some((name, _, _)) = next_member()?
+    {
+        return Err(ArchiveError::UnexpectedMember(name).into());
+    }
+
+    let control = ar.read (control_offset, control_size)?;
+    let mut tar = TarReader::new (member_reader (&control_name, "control.tar", control.as_slice())?);
+    let mut control_files = Vec::new();
+    while let Some(entry) = tar.next_entry()?
+    {
+        if entry.kind == EntryKind::File
+        {
+            let mut data = Vec::new();
+            tar.contents().read_to_end(&mut data)?;
+            control_files.push((entry.path, data));
+        }
+    }
+
+    return Ok(Members { version, control_files, data_name, data: (data_offset, data_size) });
+}
+
+impl DebArchive<'static>
+{
+    // Reads the control archive of a package file; the payload stays on disk until data() is
+    // called. The file is kept open, so the payload is read from the same file even if the path
+    // is replaced in the meantime.
+    pub fn open (path: &Path) -> Result<Self, PakigeParseError>
+    {
+        let file = File::open(path)?;
+        let members = read_members (BufReader::new(&file))?;
+        let (offset, size) = members.data;
+        return Ok(DebArchive {
+            version: members.version,
+            control_files: members.control_files,
+            data_name: members.data_name,
+            payload: Payload::File(file, offset, size)
+        });
+    }
+}
+
+impl<'a> DebArchive<'a>
+{
+    pub fn from_bytes (data: &'a [u8]) -> Result<Self, PakigeParseError>
+    {
+        let members = read_members (Cursor::new(data))?;
+        let (offset, size) = members.data;
+        return Ok(DebArchive {
+            version: members.version,
+            control_files: members.control_files,
+            data_name: members.data_name,
+            payload: Payload::Bytes(&data[offset as usize..(offset + size) as usize])
+        });
+    }
+
+    /* The contents of `debian-binary`, without the newline (e.g. `2.0`) */
+    pub fn format_version (&self) -> &str
+    {
+        return &self.version;
+    }
+
+    /* The names of the files in the control archive (`control`, `md5sums`, `postinst`, ...) */
+    pub fn control_files (&self) -> Vec<&str>
+    {
+        return self.control_files.iter().map(|(name, _)| name.as_str()).collect();
+    }
+
+    pub fn control_file (&self, name: &str) -> Option<&[u8]>
+    {
+        return self.control_files.iter().find(|(other, _)| other == name).map(|(_, data)| data.as_slice());
+    }
+
+    /* The name of the payload member, e.g. `data.tar.xz` */
+    pub fn data_member (&self) -> &str
+    {
+        return &self.data_name;
+    }
+
+    // Opens the payload for reading. Compressions that the package format allows but that are
+    // not supported here (bzip2, lzma) are only reported at this point, so the control archive of
+    // such packages can still be read.
+    pub fn data (&self) -> Result<DataArchive<'a>, PakigeParseError>
+    {
+        let reader = match &self.payload
+        {
+            Payload::Bytes(data) => member_reader (&self.data_name, "data.tar", *data)?,
+            Payload::File(file, offset, size) =>
+            {
+                let range = FileRange { file: file.try_clone()?, position: *offset, end: offset + size };
+                member_reader (&self.data_name, "data.tar", BufReader::with_capacity(64 * 1024, range))?
+            }
+        };
+        return Ok(TarReader::new (Box::new(reader)));
+    }
+
+    pub fn binary_deb (&self) -> Result<BinaryDeb, PakigeParseError>
+    {
+        let control = self.control_file("control").ok_or(ArchiveError::MissingControlFile(String::from("control")))?;
+        let control = std::str::from_utf8(control).map_err(|_| PakigeParseError::InvalidFormat)?;
+        return BinaryDeb::from_fields (str_to_table (control)?);
+    }
+
+    // Parses an optional control file. Text that is not UTF-8 is reported at the line where the
+    // invalid bytes are.
+    fn parse_control_file<T: FromStr<Err = StanzaError>> (&self, name: &str) -> Result<Option<T>, StanzaError>
+    {
+        let data = match self.control_file(name)
+        {
+            Some(data) => data,
+            None => return Ok(None)
+        };
+        let text = std::str::from_utf8(data).map_err(|error| StanzaError {
+            line: data[..error.valid_up_to()].iter().filter(|b| **b == b'\n').count() + 1,
+            error: PakigeParseError::InvalidFormat
+        })?;
+        return T::from_str (text).map(Some);
+    }
+
+    pub fn md5sums (&self) -> Result<Option<Md5Sums>, StanzaError>
+    {
+        return self.parse_control_file("md5sums");
+    }
+
+    pub fn conffiles (&self) -> Result<Option<Conffiles>, StanzaError>
+    {
+        return self.parse_control_file("conffiles");
+    }
+
+    pub fn triggers (&self) -> Result<Option<Triggers>, StanzaError>
+    {
+        return self.parse_control_file("triggers");
+    }
+
+    pub fn shlibs (&self) -> Result<Option<Shlibs>, StanzaError>
+    {
+        return self.parse_control_file("shlibs");
+    }
+
+    pub fn symbols (&self) -> Result<Option<Symbols>, StanzaError>
+    {
+        return self.parse_control_file("symbols");
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::deb::TarEntry;
+    use std::fs;
+    use crate::testing::temp_dir;
+    use std::path::PathBuf;
+
+    // Built by tests/data/make-fixtures.sh: the same package with each payload compression, plus
+    // uncompressed payloads written as pax and as ustar instead of GNU tar
+    static FIXTURES: [(&str, &[u8]); 6] = [
+        ("data.tar.gz", include_bytes!("../../tests/data/hello_gzip.deb")),
+        ("data.tar.xz", include_bytes!("../../tests/data/hello_xz.deb")),
+        ("data.tar.zst", include_bytes!("../../tests/data/hello_zstd.deb")),
+        ("data.tar", include_bytes!("../../tests/data/hello_none.deb")),
+        ("data.tar", include_bytes!("../../tests/data/hello_pax.deb")),
+        ("data.tar", include_bytes!("../../tests/data/hello_ustar.deb"))
+    ];
+    static LONG_PATH: &str = "usr/share/doc/hello/examples/a-directory-name-long-enough-that-the-path/does-not-fit-in-a-tar-header.txt";
+    static HELLO: &[u8] = b"#!/bin/sh\necho \"Hello, world!\"\n";
+
+    fn entries (archive: &DebArchive) -> Vec<TarEntry>
+    {
+        return archive.data().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    }
+
+    fn fixture_path (name: &str) -> PathBuf
+    {
+        return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data").join(name);
+    }
+
+    #[test]
+    fn control ()
+    {
+        for (data_name, bytes) in FIXTURES
+        {
+            let archive = DebArchive::from_bytes (bytes).unwrap();
+            assert_eq!(archive.format_version(), "2.0");
+            assert_eq!(archive.data_member(), data_name);
+            for name in ["control", "md5sums", "conffiles"]
+            {
+                assert!(archive.control_files().contains(&name));
+            }
+            assert_eq!(archive.control_file("conffiles"), Some(&b"/etc/hello.conf\n"[..]));
+            assert!(archive.control_file("postinst").is_none());
+
+            let deb = archive.binary_deb().unwrap();
+            assert_eq!(deb.package, "hello");
+            assert_eq!(deb.version.to_string(), "1.0");
+            assert_eq!(deb.architecture, "all");
+
+            let md5sums = archive.md5sums().unwrap().unwrap();
+            assert_eq!(md5sums.get("usr/bin/hello"), Some("d9ee44d59390c7097f20a0ec1c449048"));
+            assert!(archive.conffiles().unwrap().unwrap().get("etc/hello.conf").is_some());
+            assert!(archive.triggers().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn payload ()
+    {
+        for (_, bytes) in FIXTURES
+        {
+            let entries = entries (&DebArchive::from_bytes (bytes).unwrap());
+            let entry = |path: &str| entries.iter().find(|entry| entry.path == path).unwrap();
+
+            let root = entry ("");
+            assert_eq!(root.kind, EntryKind::Directory);
+            assert_eq!(entry ("usr/bin").kind, EntryKind::Directory);
+
+            let hello = entry ("usr/bin/hello");
+            assert_eq!(hello.kind, EntryKind::File);
+            assert_eq!(hello.mode, 0o755);
+            assert_eq!(hello.size, HELLO.len() as u64);
+            assert_eq!(hello.mtime, 1700000000);
+            assert_eq!((hello.uid, hello.gid), (0, 0));
+            assert_eq!((hello.user.as_str(), hello.group.as_str()), ("root", "root"));
+            assert_eq!(hello.link_target, None);
+
+            let again = entry ("usr/bin/hello-again");
+            assert_eq!(again.kind, EntryKind::HardLink);
+            assert_eq!(again.link_target.as_deref(), Some("usr/bin/hello"));
+            let hi = entry ("usr/bin/hi");
+            assert_eq!(hi.kind, EntryKind::Symlink);
+            assert_eq!(hi.link_target.as_deref(), Some("hello"));
+
+            let conf = entry ("etc/hello.conf");
+            assert_eq!((conf.kind, conf.mode, conf.size), (EntryKind::File, 0o644, 15));
+            let long = entry (LONG_PATH);
+            assert_eq!((long.kind, long.size), (EntryKind::File, 13));
+        }
+    }
+
+    #[test]
+    fn contents ()
+    {
+        for (_, bytes) in FIXTURES
+        {
+            let archive = DebArchive::from_bytes (bytes).unwrap();
+            let mut data = archive.data().unwrap();
+            let mut found = Vec::new();
+            while let Some(entry) = data.next_entry().unwrap()
+            {
+                let mut contents = Vec::new();
+                match entry.path.as_str()
+                {
+                    "usr/bin/hello" => { data.contents().read_to_end(&mut contents).unwrap(); },
+                    "etc/hello.conf" => { data.contents().read_to_end(&mut contents).unwrap(); },
+                    // Partly read, to leave the rest to be skipped
+                    path if path == LONG_PATH => { contents.resize(2, 0); data.contents().read_exact(&mut contents).unwrap(); },
+                    _ => continue
+                }
+                found.push((entry.path, contents));
+            }
+            assert_eq!(found.len(), 3);
+            assert!(found.contains(&(String::from("usr/bin/hello"), HELLO.to_vec())));
+            assert!(found.contains(&(String::from("etc/hello.conf"), b"greeting=Hello\n".to_vec())));
+            assert!(found.contains(&(String::from(LONG_PATH), b"A ".to_vec())));
+
+            // Reading stops at the end of the entry
+            let mut data = archive.data().unwrap();
+            while data.next_entry().unwrap().is_some_and(|entry| entry.path != "usr/bin/hello") {}
+            let mut contents = Vec::new();
+            data.contents().read_to_end(&mut contents).unwrap();
+            assert_eq!(data.contents().read(&mut [0; 16]).unwrap(), 0);
+            assert_eq!(contents, HELLO);
+        }
+    }
+
+    #[test]
+    fn open ()
+    {
+        for name in ["hello_gzip.deb", "hello_xz.deb", "hello_zstd.deb", "hello_none.deb", "hello_pax.deb", "hello_ustar.deb"]
+        {
+            let path = fixture_path (name);
+            let archive = DebArchive::open (&path).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            let expected: Vec<String> = entries (&DebArchive::from_bytes (&bytes).unwrap()).into_iter().map(|entry| entry.path).collect();
+            let found: Vec<String> = entries (&archive).into_iter().map(|entry| entry.path).collect();
+            assert_eq!(found, expected);
+            assert_eq!(archive.binary_deb().unwrap().package, "hello");
+        }
+        assert!(matches!(DebArchive::open (&fixture_path ("missing.deb")), Err(PakigeParseError::IoError(_))));
+    }
+
+    #[test]
+    fn open_file ()
+    {
+        let dir = temp_dir ("open-file");
+        let path = dir.join("hello.deb");
+        fs::write(&path, FIXTURES[1].1).unwrap();
+        let archive = DebArchive::open (&path).unwrap();
+        let expected = entries (&archive);
+
+        // Readers of the same file do not get in each other's way
+        let (mut first, mut second) = (archive.data().unwrap(), archive.data().unwrap());
+        for entry in &expected
+        {
+            assert_eq!(first.next_entry().unwrap().unwrap().path, entry.path);
+            assert_eq!(second.next_entry().unwrap().unwrap().path, entry.path);
+        }
+        assert!(first.next_entry().unwrap().is_none() && second.next_entry().unwrap().is_none());
+
+        // The payload still comes from the file that was opened once the path is replaced
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, b"!<arch>\n").unwrap();
+        let found: Vec<String> = entries (&archive).into_iter().map(|entry| entry.path).collect();
+        assert_eq!(found, expected.into_iter().map(|entry| entry.path).collect::<Vec<_>>());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /* Hands out `data`, then fails as a disk would */
+    struct Failing<'a>(&'a [u8]);
+
+    impl Read for Failing<'_>
+    {
+        fn read (&mut self, buffer: &mut [u8]) -> io::Result<usize>
+        {
+            if self.0.is_empty()
+            {
+                return Err(io::Error::other("input/output error"));
+            }
+            return self.0.read(buffer);
+        }
+    }
+
+    #[test]
+    fn io_errors ()
+    {
+        // Errors reading the archive are not taken for corrupt data, whatever the compression
+        for (name, bytes) in FIXTURES
+        {
+            let (offset, size) = payload_member (bytes);
+            let member = &bytes[offset..offset + (size / 2).min(1024)];
+            let reader = member_reader (name, "data.tar", BufReader::with_capacity(64, Failing(member))).unwrap();
+            let found = TarReader::new (reader).collect::<Result<Vec<_>, _>>();
+            assert!(matches!(found, Err(PakigeParseError::IoError(ref error)) if error.kind() == io::ErrorKind::Other), "{}", name);
+
+            let found = member_reader (name, "data.tar", BufReader::new(Failing(&[])));
+            assert!(matches!(found, Err(PakigeParseError::IoError(_))), "{}", name);
+        }
+    }
+
+    #[test]
+    fn archive_errors ()
+    {
+        let bytes = FIXTURES[0].1;
+        let error = |data: &[u8]| match DebArchive::from_bytes (data)
+        {
+            Err(PakigeParseError::InvalidArchive(error)) => error,
+            Err(error) => panic!("{:?}", error),
+            Ok(_) => panic!("archive was accepted")
+        };
+
+        assert_eq!(error (b"not an archive"), ArchiveError::NotAnArchive);
+        assert_eq!(error (AR_MAGIC), ArchiveError::MissingMember("debian-binary"));
+        assert_eq!(error (&bytes[..AR_MAGIC.len() + 30]), ArchiveError::InvalidMember);
+        // A member cut short
+        assert_eq!(error (&bytes[..bytes.len() - 100]), ArchiveError::InvalidMember);
+
+        let mut version = bytes.to_vec();
+        let start = AR_MAGIC.len() + AR_HEADER;
+        version[start..start + 3].copy_from_slice(b"3.0");
+        assert_eq!(error (&version), ArchiveError::UnsupportedVersion(String::from("3.0")));
+    }
+
+    /* The offset and size of the payload member */
+    fn payload_member (bytes: &[u8]) -> (usize, usize)
+    {
+        let members = read_members (Cursor::new(bytes)).unwrap();
+        return (members.data.0 as usize, members.data.1 as usize);
+    }
+
+    #[test]
+    fn payload_errors ()
+    {
+        // Corrupt compressed data is only noticed once the payload is read
+        let mut xz = FIXTURES[1].1.to_vec();
+        let (offset, size) = payload_member (&xz);
+        xz[offset + size / 2] ^= 0xff;
+        let archive = DebArchive::from_bytes (&xz).unwrap();
+        let error = archive.data().and_then(|data| data.collect::<Result<Vec<_>, _>>());
+        assert!(matches!(error, Err(PakigeParseError::InvalidArchive(ArchiveError::Decompress(name, _))) if name == "data.tar.xz"));
+
+        // A tar header with a bad checksum
+        let mut tar = FIXTURES[3].1.to_vec();
+        let (offset, _) = payload_member (&tar);
+        tar[offset + 10] ^= 0xff;
+        let archive = DebArchive::from_bytes (&tar).unwrap();
+        let error = archive.data().and_then(|data| data.collect::<Result<Vec<_>, _>>());
+        assert!(matches!(error, Err(PakigeParseError::InvalidArchive(ArchiveError::InvalidTar))));
+
+        // A payload that ends in the middle of an entry, both when listing and when reading it
+        let member = &FIXTURES[3].1[payload_member (FIXTURES[3].1).0..];
+        let found = TarReader::new (&member[..512 * 3 + 100]).collect::<Result<Vec<_>, _>>();
+        assert!(matches!(found, Err(PakigeParseError::InvalidArchive(ArchiveError::InvalidTar))));
+    }
+}
@@ -0,0 +1,382 @@
+use crate::PakigeParseError;
+use super::{DateTime, StanzaError};
+use deb_version7::DebVersion;
+use regex::Regex;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+// https://www.debian.org/doc/debian-policy/ch-source.html#debian-changelog-debian-changelog
+// Entries are listed newest first, each made of a header line, the change details (indented
+// lines, usually `  * item` bullets) and a trailer line:
+//
+//   package (version) distribution(s); urgency=urgency
+//
+//     * change details
+//
+//    -- maintainer name <email address>  date
+//
+// Parsing stops at the markers dpkg-parsechangelog stops at: an `Old Changelog:` line, or the
+// Emacs/Vim local variables block at the end of the file.
+
+static HEADER_RULES: &str = r"(?i)^(\w[-+0-9a-z.]*) \(([^() \t]+)\)((?:\s+[-+0-9a-z.]+)+);\s*(.*?)\s*$";
+static TRAILER_RULES: &str = r"^ -- (.*<.*>)  ?(\S.*?)\s*$";
+static CLOSES_RULES: &str = r"(?i)closes:\s*((?:bug)?#?\s?\d+(?:,\s*(?:bug)?#?\s?\d+)*)";
+
+fn header_rules () -> &'static Regex
+{
+    static RULES: OnceLock<Regex> = OnceLock::new();
+    return RULES.get_or_init(|| Regex::new(HEADER_RULES).unwrap());
+}
+
+fn trailer_rules () -> &'static Regex
+{
+    static RULES: OnceLock<Regex> = OnceLock::new();
+    return RULES.get_or_init(|| Regex::new(TRAILER_RULES).unwrap());
+}
+
+fn closes_rules () -> &'static Regex
+{
+    static RULES: OnceLock<Regex> = OnceLock::new();
+    return RULES.get_or_init(|| Regex::new(CLOSES_RULES).unwrap());
+}
+
+#[derive(Debug, Clone)]
+pub struct ChangelogEntry
+{
+    pub package: String,
+    pub version: DebVersion,
+    pub distributions: Vec<String>,
+    pub urgency: Option<String>,
+    pub keywords: Vec<(String, String)>, // Keyword arguments of the header other than urgency, e.g. binary-only=yes
+    pub changes: Vec<String>, // The change detail lines as written, without leading or trailing blank lines
+    pub maintainer: String, // `name <email>`
+    pub date: DateTime,
+    pub line: usize // The line of the header (1-based)
+}
+
+impl ChangelogEntry
+{
+    // The change details split into items: a line starting with `*`, `-` or `+` starts a new
+    // item, and more indented lines continue it. Group headings such as `[ Name ]` are left out.
+    pub fn items (&self) -> Vec<String>
+    {
+        let mut items: Vec<String> = Vec::new();
+        let mut continuing = false;
+
+        for line in &self.changes
+        {
+            let text = line.trim();
+            if text.is_empty() || (text.starts_with('[') && text.ends_with(']'))
+            {
+                continuing = false;
+                continue;
+            }
+
+            match text.strip_prefix(['*', '-', '+'])
+            {
+                Some(item) => items.push(item.trim().to_string()),
+                None if continuing =>
+                {
+                    // Continuing is only set once an item was pushed
+                    let item = items.last_mut().unwrap();
+                    item.push(' ');
+                    item.push_str(text);
+                },
+                None => items.push(text.to_string())
+            }
+            continuing = true;
+        }
+
+        return items;
+    }
+
+    /* Bug numbers from `Closes: #123, #456` in the change details */
+    pub fn closes (&self) -> Vec<u32>
+    {
+        let mut bugs = Vec::new();
+        let text = self.changes.join("\n");
+
+        // The list is taken from its capture group, as the case-insensitive match of `closes:`
+        // need not be 7 bytes long (`cloſeſ:`)
+        for closes in closes_rules().captures_iter(&text)
+        {
+            for number in closes[1].split(|c: char| !c.is_ascii_digit()).filter(|number| !number.is_empty())
+            {
+                if let Ok(bug) = number.parse::<u32>()
+                {
+                    if !bugs.contains(&bug)
+                    {
+                        bugs.push(bug);
+                    }
+                }
+            }
+        }
+
+        return bugs;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Changelog
+{
+    pub entries: Vec<ChangelogEntry> // Newest first, as in the file
+}
+
+impl Changelog
+{
+    /* The newest entry */
+    pub fn latest (&self) -> Option<&ChangelogEntry>
+    {
+        return self.entries.first();
+    }
+
+    // Entries with a version newer than `version`, newest first: what changed since a version
+    // that is installed
+    pub fn newer_than (&self, version: &DebVersion) -> Vec<&ChangelogEntry>
+    {
+        return self.entries.iter().filter(|entry| entry.version > *version).collect();
+    }
+}
+
+/* Splits `urgency=medium, binary-only=yes` into (keyword, value) pairs, keywords lowercased */
+fn parse_keywords (data: &str) -> Result<Vec<(String, String)>, PakigeParseError>
+{
+    let mut keywords: Vec<(String, String)> = Vec::new();
+
+    for keyword in data.split(',').map(|keyword| keyword.trim()).filter(|keyword| !keyword.is_empty())
+    {
+        let (name, value) = keyword.split_once('=').ok_or(PakigeParseError::InvalidFormat)?;
+        let name = name.trim().to_lowercase();
+
+        if keywords.iter().any(|(other, _)| *other == name)
+        {
+            return Err(PakigeParseError::DuplicateField);
+        }
+        keywords.push((name, value.trim().to_string()));
+    }
+
+    return Ok(keywords);
+}
+
+fn is_end_marker (line: &str) -> bool
+{
+    return line.starts_with("Old Changelog:")
+        || line.starts_with("Local variables:")
+        || line.starts_with("local variables:")
+        || line.starts_with("vim:")
+        || line.starts_with("# vim:");
+}
+
+/* Details of an entry whose header has been read but not yet its trailer */
+struct PartialEntry
+{
+    package: String,
+    version: DebVersion,
+    distributions: Vec<String>,
+    urgency: Option<String>,
+    keywords: Vec<(String, String)>,
+    changes: Vec<String>,
+    line: usize
+}
+
+// Errors give the line they were found on. A header with no trailer before the next header
+// (or the end of the file) is reported at the header's line.
+impl FromStr for Changelog
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut entries = Vec::new();
+        let mut current: Option<PartialEntry> = None;
+
+        for (index, line) in data.lines().enumerate()
+        {
+            let number = index + 1;
+            let error = |error| StanzaError { line: number, error };
+            let line = line.trim_end_matches('\r');
+
+            if let Some(entry) = current.as_mut()
+            {
+                if line.starts_with(" --")
+                {
+                    let captures = trailer_rules().captures(line).ok_or(error(PakigeParseError::InvalidFormat))?;
+                    let date = DateTime::from_str (&captures[2]).map_err(error)?;
+                    let entry = current.take().unwrap();
+
+                    let mut changes = entry.changes;
+                    while changes.last().is_some_and(|line| line.trim().is_empty())
+                    {
+                        changes.pop();
+                    }
+
+                    entries.push(ChangelogEntry {
+                        package: entry.package,
+                        version: entry.version,
+                        distributions: entry.distributions,
+                        urgency: entry.urgency,
+                        keywords: entry.keywords,
+                        changes,
+                        maintainer: captures[1].trim().to_string(),
+                        date,
+                        line: entry.line
+                    });
+                }
+                else if line.trim().is_empty() || line.starts_with([' ', '\t'])
+                {
+                    // Leading blank lines are not part of the changes
+                    if !(entry.changes.is_empty() && line.trim().is_empty())
+                    {
+                        entry.changes.push(line.to_string());
+                    }
+                }
+                else
+                {
+                    // Most likely the next header, so the trailer is missing
+                    return Err(StanzaError { line: entry.line, error: PakigeParseError::InvalidFormat });
+                }
+                continue;
+            }
+
+            if line.trim().is_empty()
+            {
+                continue;
+            }
+            if is_end_marker (line)
+            {
+                break;
+            }
+
+            let captures = header_rules().captures(line).ok_or(error(PakigeParseError::InvalidFormat))?;
+            let version = DebVersion::from_str (&captures[2]).map_err(|_| error(PakigeParseError::InvalidValue))?;
+            let mut keywords = parse_keywords (&captures[4]).map_err(error)?;
+            let urgency = keywords.iter().position(|(name, _)| name == "urgency").map(|index| keywords.remove(index).1);
+
+            current = Some(PartialEntry {
+                package: captures[1].to_string(),
+                version,
+                distributions: captures[3].split_whitespace().map(String::from).collect(),
+                urgency,
+                keywords,
+                changes: Vec::new(),
+                line: number
+            });
+        }
+
+        if let Some(entry) = current
+        {
+            return Err(StanzaError { line: entry.line, error: PakigeParseError::InvalidFormat });
+        }
+        if entries.is_empty()
+        {
+            return Err(StanzaError { line: 1, error: PakigeParseError::EmptyInput });
+        }
+
+        return Ok(Changelog { entries });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static CHANGELOG: &str = "\
+hello (2.10-3) unstable; urgency=medium
+
+  [ Santiago Vila ]
+  * Fix a crash on empty input.
+    Closes: #1000001, #1000002.
+  * Update Standards-Version. closes: Bug#1000003
+
+ -- Santiago Vila <sanvila@debian.org>  Sun, 04 Jun 2023 12:34:56 +0200
+
+hello (2.10-2+b1) unstable experimental; urgency=low, binary-only=yes
+
+  * Binary-only non-maintainer upload for amd64; no source changes.
+
+ -- amd64 Build Daemon <buildd_amd64@debian.org>  Sat, 03 Jun 2023 08:00:00 +0000
+
+hello (2.10-2) unstable; urgency=low
+
+  * Initial release.
+
+ -- Santiago Vila <sanvila@debian.org>  Fri, 02 Jun 2023 10:00:00 +0200
+
+Old Changelog:
+This is not parsed.
+";
+
+    #[test]
+    fn changelog ()
+    {
+        let changelog = Changelog::from_str (CHANGELOG).unwrap();
+        assert_eq!(changelog.entries.len(), 3);
+
+        let latest = changelog.latest().unwrap();
+        assert_eq!(latest.package, "hello");
+        assert_eq!(latest.version.to_string(), "2.10-3");
+        assert_eq!(latest.distributions, ["unstable"]);
+        assert_eq!(latest.urgency.as_deref(), Some("medium"));
+        assert_eq!(latest.maintainer, "Santiago Vila <sanvila@debian.org>");
+        assert_eq!(latest.date.to_string(), "Sun, 04 Jun 2023 12:34:56 +0200");
+        assert_eq!(latest.line, 1);
+        assert_eq!(latest.changes.len(), 4);
+        assert_eq!(latest.items(), [
+            "Fix a crash on empty input. Closes: #1000001, #1000002.",
+            "Update Standards-Version. closes: Bug#1000003"
+        ]);
+
+        let binnmu = &changelog.entries[1];
+        assert_eq!(binnmu.line, 10);
+        assert_eq!(binnmu.distributions, ["unstable", "experimental"]);
+        assert_eq!(binnmu.urgency.as_deref(), Some("low"));
+        assert_eq!(binnmu.keywords, [(String::from("binary-only"), String::from("yes"))]);
+
+        let installed = DebVersion::from_str ("2.10-2").unwrap();
+        let newer: Vec<String> = changelog.newer_than(&installed).iter().map(|entry| entry.version.to_string()).collect();
+        assert_eq!(newer, ["2.10-3", "2.10-2+b1"]);
+    }
+
+    #[test]
+    fn closes ()
+    {
+        let changelog = Changelog::from_str (CHANGELOG).unwrap();
+        assert_eq!(changelog.entries[0].closes(), [1000001, 1000002, 1000003]);
+        assert!(changelog.entries[1].closes().is_empty());
+
+        let closes = |line: &str| Changelog::from_str (&format!(
+            "hello (1.0) unstable; urgency=low\n\n  * {}\n\n -- A <a@example.org>  Fri, 02 Jun 2023 10:00:00 +0200\n", line))
+            .unwrap().entries[0].closes();
+        assert_eq!(closes ("Closes: #1, #2"), [1, 2]);
+        assert_eq!(closes ("closes: Bug#3"), [3]);
+        assert_eq!(closes ("CLOSES:#4,bug#5, #4"), [4, 5]);
+        assert_eq!(closes ("(cloſeſ: #123)"), [123]);
+        assert!(closes ("Closes #6, and see #7").is_empty());
+    }
+
+    #[test]
+    fn errors ()
+    {
+        let error = Changelog::from_str ("").unwrap_err();
+        assert!(matches!(error.error, PakigeParseError::EmptyInput));
+
+        // A missing trailer is reported at the entry's header
+        let data = CHANGELOG.replace(" -- amd64 Build Daemon <buildd_amd64@debian.org>  Sat, 03 Jun 2023 08:00:00 +0000\n", "");
+        let error = Changelog::from_str (&data).unwrap_err();
+        assert_eq!(error.line, 10);
+        assert!(matches!(error.error, PakigeParseError::InvalidFormat));
+
+        let error = Changelog::from_str (&CHANGELOG.replace("Sat, 03 Jun 2023", "Sat, 32 Jun 2023")).unwrap_err();
+        assert_eq!(error.line, 14);
+        assert!(matches!(error.error, PakigeParseError::InvalidValue));
+
+        let error = Changelog::from_str (&CHANGELOG.replace("urgency=low, binary-only=yes", "urgency=low, urgency=high")).unwrap_err();
+        assert_eq!(error.line, 10);
+        assert!(matches!(error.error, PakigeParseError::DuplicateField));
+
+        let error = Changelog::from_str (&CHANGELOG.replace("hello (2.10-2)", "hello 2.10-2")).unwrap_err();
+        assert_eq!(error.line, 16);
+        assert!(matches!(error.error, PakigeParseError::InvalidFormat));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::PakigeParseError;
+use super::{str_to_table, parse_clearsigned, DateTime, Fields, FileChecksum, SourceRef};
+use super::setters::{set_format, set_date, set_source, set_binary_list, set_upload_architectures,
+                     set_version, set_distribution, set_urgency, set_maintainer, set_changed_by,
+                     set_closes, set_changes, set_changes_files, set_checksums_sha1, set_checksums_sha256};
+use deb_version7::DebVersion;
+use std::fmt;
+use std::str::FromStr;
+
+// https://manpages.debian.org/deb-changes.5
+// A .changes file describes an upload: the changelog entries it closes, and the files (source
+// and/or binary packages) to put in the archive, with their checksums. It is usually clearsigned.
+
+/* One line of the Files field of a .changes */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangesFile
+{
+    pub md5sum: String,
+    pub size: u64,
+    pub section: String, // Section, or `byhand`, or `-`
+    pub priority: String,
+    pub filename: String
+}
+
+#[derive(Debug, Clone)]
+pub struct Changes
+{
+    pub format: String, /* Mandatory */
+    pub date: DateTime, /* Mandatory */
+    pub source: SourceRef, /* Mandatory */
+    pub binary: Vec<String>,
+    pub architecture: Vec<String>, /* Mandatory */
+    pub version: DebVersion, /* Mandatory */
+    pub distribution: Vec<String>, /* Mandatory */
+    pub urgency: Option<String>, /* Recommended */
+    pub maintainer: String, /* Mandatory */
+    pub changed_by: Option<String>,
+    pub closes: Vec<u32>,
+    pub changes: String, /* Mandatory */
+    pub files: Vec<ChangesFile>, /* Mandatory */
+    pub checksums_sha1: Vec<FileChecksum>,
+    pub checksums_sha256: Vec<FileChecksum>, /* Mandatory since Format 1.8, but older uploads lack it */
+    pub signature: Option<String>, // The armored OpenPGP signature, if the file was clearsigned
+    pub all_fields: Fields
+}
+
+/* A disagreement between the Files table and a Checksums-* table */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChecksumTableError
+{
+    MissingFile(String, String), // (table, filename): in Files, but not in the table
+    ExtraFile(String, String), // (table, filename): in the table, but not in Files
+    SizeMismatch(String, String, u64, u64) // (table, filename, size in Files, size in the table)
+}
+
+impl fmt::Display for ChecksumTableError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            ChecksumTableError::MissingFile(table, filename) => write!(f, "File '{}' is listed in Files but not in {}.", filename, table),
+            ChecksumTableError::ExtraFile(table, filename) => write!(f, "File '{}' is listed in {} but not in Files.", filename, table),
+            ChecksumTableError::SizeMismatch(table, filename, expected, found) =>
+                write!(f, "File '{}' has size {} in Files but {} in {}.", filename, expected, found, table)
+        }
+    }
+}
+
+impl std::error::Error for ChecksumTableError {}
+
+impl Changes
+{
+    fn from_fields (fields: Fields, signature: Option<String>) -> Result<Self, PakigeParseError>
+    {
+        return Ok(Changes {
+            format: set_format (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            date: set_date (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            source: set_source (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            binary: set_binary_list (&fields)?.unwrap_or_default(),
+            architecture: set_upload_architectures (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            version: set_version (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            distribution: set_distribution (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            urgency: set_urgency (&fields)?, /* Recommended */
+            maintainer: set_maintainer (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            changed_by: set_changed_by (&fields)?,
+            closes: set_closes (&fields)?.unwrap_or_default(),
+            changes: set_changes (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            files: set_changes_files (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            checksums_sha1: set_checksums_sha1 (&fields)?.unwrap_or_default(),
+            checksums_sha256: set_checksums_sha256 (&fields)?.unwrap_or_default(),
+            signature,
+            all_fields: fields
+        });
+    }
+
+    /* Whether the .changes came wrapped in an OpenPGP signature (which is not verified here) */
+    pub fn is_signed (&self) -> bool
+    {
+        return self.signature.is_some();
+    }
+
+    // Checks that each Checksums-* table lists the same files as Files, with the same sizes.
+    // Tables that are absent are not checked. Returns every disagreement found, in table order.
+    pub fn verify_checksum_tables (&self) -> Vec<ChecksumTableError>
+    {
+        let mut errors = Vec::new();
+
+        for (table, checksums) in [("Checksums-Sha1", &self.checksums_sha1), ("Checksums-Sha256", &self.checksums_sha256)]
+        {
+            if checksums.is_empty()
+            {
+                continue;
+            }
+
+            for file in &self.files
+            {
+                match checksums.iter().find(|other| other.filename == file.filename)
+                {
+                    Some(other) if other.size != file.size =>
+                        errors.push(ChecksumTableError::SizeMismatch(table.to_string(), file.filename.clone(), file.size, other.size)),
+                    Some(_) => (),
+                    None => errors.push(ChecksumTableError::MissingFile(table.to_string(), file.filename.clone()))
+                }
+            }
+
+            for other in checksums
+            {
+                if !self.files.iter().any(|file| file.filename == other.filename)
+                {
+                    errors.push(ChecksumTableError::ExtraFile(table.to_string(), other.filename.clone()));
+                }
+            }
+        }
+
+        return errors;
+    }
+}
+
+// Accepts the file either clearsigned or bare
+impl FromStr for Changes
+{
+    type Err = PakigeParseError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let signed = parse_clearsigned (data)?;
+        let fields = str_to_table (&signed.body)?;
+        return Changes::from_fields (fields, signed.signature);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static CHANGES: &str = "\
+Format: 1.8
+Date: Sun, 04 Jun 2023 12:34:56 +0200 (CEST)
+Source: hello
+Binary: hello
+Architecture: source amd64
+Version: 2.10-3
+Distribution: unstable
+Urgency: medium (fixes a crash)
+Maintainer: Santiago Vila <sanvila@debian.org>
+Changed-By: Santiago Vila <sanvila@debian.org>
+Closes: 1000001 1000002
+Changes:
+ hello (2.10-3) unstable; urgency=medium
+ .
+   * Fix a crash. Closes: #1000001, #1000002.
+Checksums-Sha1:
+ dc894908c0eac029a31f01e32b7f0df596c7399c 1851 hello_2.10-3.dsc
+ a6eb4d9d7f99ca47abe56f3220597663cf37ca4a 53080 hello_2.10-3_amd64.deb
+Checksums-Sha256:
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 1851 hello_2.10-3.dsc
+ 81d93757457f988523814ae0009837ae893f38d3fe123f2c37896f118b4c7804 53080 hello_2.10-3_amd64.deb
+Files:
+ 025f253325b46929cd34f2a7c3c55e7c 1851 devel optional hello_2.10-3.dsc
+ 6e9552c9bd8e61c8f277c21220160234 53080 devel optional hello_2.10-3_amd64.deb
+";
+
+    #[test]
+    fn changes ()
+    {
+        let changes = Changes::from_str (CHANGES).unwrap();
+        assert!(!changes.is_signed());
+        assert_eq!(changes.date.to_string(), "Sun, 04 Jun 2023 12:34:56 +0200");
+        assert_eq!(changes.source.name, "hello");
+        assert!(changes.source.version.is_none());
+        assert_eq!(changes.architecture, ["source", "amd64"]);
+        assert_eq!(changes.version.to_string(), "2.10-3");
+        assert_eq!(changes.distribution, ["unstable"]);
+        assert_eq!(changes.urgency.as_deref(), Some("medium (fixes a crash)"));
+        assert_eq!(changes.closes, [1000001, 1000002]);
+        assert_eq!(changes.changes, "hello (2.10-3) unstable; urgency=medium\n\n  * Fix a crash. Closes: #1000001, #1000002.");
+        assert_eq!(changes.files[1], ChangesFile {
+            md5sum: String::from("6e9552c9bd8e61c8f277c21220160234"),
+            size: 53080,
+            section: String::from("devel"),
+            priority: String::from("optional"),
+            filename: String::from("hello_2.10-3_amd64.deb")
+        });
+        assert!(changes.verify_checksum_tables().is_empty());
+    }
+
+    #[test]
+    fn changes_errors ()
+    {
+        let error = Changes::from_str (&CHANGES.replace("Date: Sun, 04 Jun 2023 12:34:56 +0200 (CEST)\n", "")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::MissingMandatoryField));
+
+        let error = Changes::from_str (&CHANGES.replace("+0200 (CEST)", "CEST")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+
+        let error = Changes::from_str (&CHANGES.replace("Closes: 1000001", "Closes: #1000001")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+
+        let error = Changes::from_str (&CHANGES.replace(" devel optional hello_2.10-3.dsc", " devel hello_2.10-3.dsc")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+    }
+
+    #[test]
+    fn checksum_tables ()
+    {
+        let data = CHANGES
+            .replace(" a6eb4d9d7f99ca47abe56f3220597663cf37ca4a 53080 hello_2.10-3_amd64.deb\n", "")
+            .replace(" 81d93757457f988523814ae0009837ae893f38d3fe123f2c37896f118b4c7804 53080 ",
+                     " 81d93757457f988523814ae0009837ae893f38d3fe123f2c37896f118b4c7804 53081 ")
+            .replace("Files:\n", "Files:\n 0123456789abcdef0123456789abcdef 10 byhand - hello.txt\n");
+        let changes = Changes::from_str (&data).unwrap();
+        assert_eq!(changes.verify_checksum_tables(), [
+            ChecksumTableError::MissingFile(String::from("Checksums-Sha1"), String::from("hello.txt")),
+            ChecksumTableError::MissingFile(String::from("Checksums-Sha1"), String::from("hello_2.10-3_amd64.deb")),
+            ChecksumTableError::MissingFile(String::from("Checksums-Sha256"), String::from("hello.txt")),
+            ChecksumTableError::SizeMismatch(String::from("Checksums-Sha256"), String::from("hello_2.10-3_amd64.deb"), 53080, 53081)
+        ]);
+
+        let data = CHANGES.replace(" 6e9552c9bd8e61c8f277c21220160234 53080 devel optional hello_2.10-3_amd64.deb\n", "");
+        let changes = Changes::from_str (&data).unwrap();
+        assert_eq!(changes.verify_checksum_tables(), [
+            ChecksumTableError::ExtraFile(String::from("Checksums-Sha1"), String::from("hello_2.10-3_amd64.deb")),
+            ChecksumTableError::ExtraFile(String::from("Checksums-Sha256"), String::from("hello_2.10-3_amd64.deb"))
+        ]);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::PakigeParseError;
+
+// https://www.rfc-editor.org/rfc/rfc4880#section-7
+// Files such as .dsc, .changes and InRelease are commonly wrapped in an OpenPGP cleartext
+// signature. The signed text sits between the armor headers and the signature block, with any
+// line that starts with `-` escaped as `- -`. Signatures are not checked here; the armored
+// signature is handed back so that it can be verified with gpgv or sqv.
+
+static SIGNED_MESSAGE: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+static BEGIN_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----";
+static END_SIGNATURE: &str = "-----END PGP SIGNATURE-----";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClearSigned
+{
+    pub body: String, // The signed text, dash-unescaped
+    pub signature: Option<String> // The armored signature block, None if the input was not signed
+}
+
+// Splits a possibly clearsigned document into its body and signature. Input that does not start
+// with the signed message header is returned as it is, unsigned.
+pub fn parse_clearsigned (data: &str) -> Result<ClearSigned, PakigeParseError>
+{
+    let mut lines = data.split_inclusive('\n').skip_while(|line| line.trim().is_empty()).peekable();
+
+    match lines.peek()
+    {
+        Some(line) if line.trim_end() == SIGNED_MESSAGE => { lines.next(); },
+        _ => return Ok(ClearSigned { body: data.to_string(), signature: None })
+    }
+
+    // Armor headers, such as `Hash: SHA512`, up to a blank line
+    loop
+    {
+        match lines.next()
+        {
+            Some(line) if line.trim().is_empty() => break,
+            Some(line) if line.contains(':') => continue,
+            _ => return Err(PakigeParseError::InvalidFormat)
+        }
+    }
+
+    let mut body = String::new();
+    loop
+    {
+        let line = match lines.next()
+        {
+            Some(line) => line,
+            None => return Err(PakigeParseError::InvalidFormat)
+        };
+        if line.trim_end() == BEGIN_SIGNATURE
+        {
+            let mut signature = String::from(line);
+            for line in lines.by_ref()
+            {
+                signature.push_str(line);
+                if line.trim_end() == END_SIGNATURE
+                {
+                    break;
+                }
+            }
+            if !signature.trim_end().ends_with(END_SIGNATURE)
+            {
+                return Err(PakigeParseError::InvalidFormat);
+            }
+
+            // Nothing but whitespace may follow the signature
+            if lines.any(|line| !line.trim().is_empty())
+            {
+                return Err(PakigeParseError::InvalidFormat);
+            }
+
+            return Ok(ClearSigned { body, signature: Some(signature) });
+        }
+
+        match line.strip_prefix("- ")
+        {
+            Some(unescaped) => body.push_str(unescaped),
+            None if line.starts_with('-') => return Err(PakigeParseError::InvalidFormat),
+            None => body.push_str(line)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static SIGNATURE: &str = "\
+-----BEGIN PGP SIGNATURE-----
+
+iQIzBAEBCgAdFiEEAAAAAAAAAAAAAAAAAAAAAAAAAAAFAmQAAAAACgkQAAAAAAAA
+=AAAA
+-----END PGP SIGNATURE-----
+";
+
+    #[test]
+    fn signed ()
+    {
+        let data = format!("-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\nSource: hello\n- -----not a header\n{}", SIGNATURE);
+        let signed = parse_clearsigned (&data).unwrap();
+        assert_eq!(signed.body, "Source: hello\n-----not a header\n");
+        assert_eq!(signed.signature.as_deref(), Some(SIGNATURE));
+
+        // Leading blank lines, CRLF line endings and trailing whitespace are accepted
+        let data = format!("\n-----BEGIN PGP SIGNED MESSAGE-----\r\nHash: SHA256\r\n\r\nSource: hello\r\n{}\n\n", SIGNATURE);
+        let signed = parse_clearsigned (&data).unwrap();
+        assert_eq!(signed.body, "Source: hello\r\n");
+        assert!(signed.signature.is_some());
+    }
+
+    #[test]
+    fn unsigned ()
+    {
+        let data = "Source: hello\nVersion: 2.10-3\n";
+        assert_eq!(parse_clearsigned (data).unwrap(), ClearSigned { body: data.to_string(), signature: None });
+    }
+
+    #[test]
+    fn errors ()
+    {
+        let cases = [
+            // No blank line after the armor headers
+            format!("-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\nSource: hello\n{}", SIGNATURE),
+            // No signature
+            String::from("-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\nSource: hello\n"),
+            // Unterminated signature
+            String::from("-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\nSource: hello\n-----BEGIN PGP SIGNATURE-----\n\niQIz\n"),
+            // A dash that is not escaped
+            format!("-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\n-Source: hello\n{}", SIGNATURE),
+            // Text after the signature
+            format!("-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\nSource: hello\n{}Version: 1\n", SIGNATURE)
+        ];
+        for data in cases
+        {
+            assert!(matches!(parse_clearsigned (&data), Err(PakigeParseError::InvalidFormat)), "{}", data);
+        }
+    }
+}
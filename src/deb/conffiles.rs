@@ -0,0 +1,125 @@
+use crate::PakigeParseError;
+use super::StanzaError;
+use std::str::FromStr;
+
+// https://manpages.debian.org/deb-conffiles.5
+// One absolute path per line, optionally preceded by flags. The only flag dpkg knows is
+// `remove-on-upgrade`, for a conffile that the new version no longer ships and that should be
+// removed (after being backed up if modified) on upgrade.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conffile
+{
+    pub path: String, // Absolute, e.g. `/etc/hello/hello.conf`
+    pub remove_on_upgrade: bool
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Conffiles
+{
+    pub files: Vec<Conffile>
+}
+
+impl Conffiles
+{
+    /* The conffile at `path`, given with or without its leading slash */
+    pub fn get (&self, path: &str) -> Option<&Conffile>
+    {
+        let path = path.trim_start_matches('/');
+        return self.files.iter().find(|file| file.path.trim_start_matches('/') == path);
+    }
+
+    /* The conffiles the package still ships, leaving out those marked remove-on-upgrade */
+    pub fn shipped (&self) -> impl Iterator<Item = &Conffile>
+    {
+        return self.files.iter().filter(|file| !file.remove_on_upgrade);
+    }
+}
+
+impl FromStr for Conffiles
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut files: Vec<Conffile> = Vec::new();
+
+        for (index, line) in data.lines().enumerate()
+        {
+            let error = |error| StanzaError { line: index + 1, error };
+            let line = line.trim();
+            if line.is_empty()
+            {
+                continue;
+            }
+
+            // Flags come first; the path is whatever follows them, and may contain spaces
+            let mut remove_on_upgrade = false;
+            let mut path = line;
+            while !path.starts_with('/')
+            {
+                let (flag, rest) = path.split_once([' ', '\t']).ok_or(error(PakigeParseError::InvalidFormat))?;
+                match flag
+                {
+                    "remove-on-upgrade" => remove_on_upgrade = true,
+                    _ => return Err(error(PakigeParseError::InvalidValue))
+                }
+                path = rest.trim_start();
+            }
+
+            if files.iter().any(|file| file.path == path)
+            {
+                return Err(error(PakigeParseError::DuplicateField));
+            }
+            files.push(Conffile { path: path.to_string(), remove_on_upgrade });
+        }
+
+        return Ok(Conffiles { files });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn conffiles ()
+    {
+        let conffiles = Conffiles::from_str ("\
+/etc/hello/hello.conf
+
+  /etc/hello/with spaces.conf
+remove-on-upgrade /etc/hello/old.conf
+").unwrap();
+        assert_eq!(conffiles.files, vec![
+            Conffile { path: String::from("/etc/hello/hello.conf"), remove_on_upgrade: false },
+            Conffile { path: String::from("/etc/hello/with spaces.conf"), remove_on_upgrade: false },
+            Conffile { path: String::from("/etc/hello/old.conf"), remove_on_upgrade: true }
+        ]);
+        assert!(conffiles.get("etc/hello/hello.conf").is_some());
+        assert!(conffiles.get("/etc/hello/old.conf").unwrap().remove_on_upgrade);
+        assert!(conffiles.get("/etc/hello/other.conf").is_none());
+
+        let shipped: Vec<&str> = conffiles.shipped().map(|file| file.path.as_str()).collect();
+        assert_eq!(shipped, ["/etc/hello/hello.conf", "/etc/hello/with spaces.conf"]);
+
+        assert!(Conffiles::from_str ("").unwrap().files.is_empty());
+    }
+
+    #[test]
+    fn conffiles_errors ()
+    {
+        let error = Conffiles::from_str ("/etc/a.conf\netc/b.conf\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, PakigeParseError::InvalidFormat));
+
+        let error = Conffiles::from_str ("keep-on-upgrade /etc/a.conf\n").unwrap_err();
+        assert_eq!(error.line, 1);
+        assert!(matches!(error.error, PakigeParseError::InvalidValue));
+
+        let error = Conffiles::from_str ("/etc/a.conf\n\nremove-on-upgrade /etc/a.conf\n").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert!(matches!(error.error, PakigeParseError::DuplicateField));
+    }
+}
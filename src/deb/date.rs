@@ -0,0 +1,280 @@
+use crate::PakigeParseError;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+// https://www.rfc-editor.org/rfc/rfc2822#section-3.3
+// Dates in .changes files, changelog trailers and Release files are written as in mail headers:
+// `Sun, 04 Jun 2023 12:34:56 +0200`. Release files use `UTC` as the zone rather than `+0000`.
+
+static WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+static MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// A point in time together with the zone offset it was written in. Dates compare by the instant
+// they denote, so the same time written in two zones is equal.
+#[derive(Debug, Clone, Copy)]
+pub struct DateTime
+{
+    pub year: i32,
+    pub month: u8, // 1-12
+    pub day: u8, // 1-31
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub offset: i32 // Minutes east of UTC
+}
+
+fn is_leap_year (year: i32) -> bool
+{
+    return (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+}
+
+fn days_in_month (year: i32, month: u8) -> u8
+{
+    return match month
+    {
+        2 if is_leap_year (year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    };
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar (Howard Hinnant's
+// days_from_civil)
+fn days_from_civil (year: i32, month: u8, day: u8) -> i64
+{
+    let year = if month <= 2 { year as i64 - 1 } else { year as i64 };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    return era * 146097 + day_of_era - 719468;
+}
+
+impl DateTime
+{
+    /* Seconds since the Unix epoch */
+    pub fn timestamp (&self) -> i64
+    {
+        let days = days_from_civil (self.year, self.month, self.day);
+        let seconds = days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        return seconds - self.offset as i64 * 60;
+    }
+
+    /* Day of the week, 0 being Monday */
+    pub fn weekday (&self) -> u8
+    {
+        // 1970-01-01 was a Thursday
+        return (days_from_civil (self.year, self.month, self.day) + 3).rem_euclid(7) as u8;
+    }
+}
+
+impl PartialEq for DateTime
+{
+    fn eq (&self, other: &Self) -> bool
+    {
+        return self.timestamp() == other.timestamp();
+    }
+}
+
+impl Eq for DateTime {}
+
+impl PartialOrd for DateTime
+{
+    fn partial_cmp (&self, other: &Self) -> Option<Ordering>
+    {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for DateTime
+{
+    fn cmp (&self, other: &Self) -> Ordering
+    {
+        return self.timestamp().cmp(&other.timestamp());
+    }
+}
+
+fn parse_number<T: FromStr> (data: &str, min_digits: usize, max_digits: usize) -> Result<T, PakigeParseError>
+{
+    if data.len() < min_digits || data.len() > max_digits || !data.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return data.parse::<T>().map_err(|_| PakigeParseError::InvalidValue);
+}
+
+// `[Day,] DD Mon YYYY HH:MM[:SS] zone [(comment)]`. The day of the week is checked to be a day
+// name, but not against the date, as old changelogs often get it wrong. A trailing comment, as in
+// `+0200 (CEST)`, is what date(1) and some mailers add after the zone, and is ignored.
+impl FromStr for DateTime
+{
+    type Err = PakigeParseError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut data = data.trim();
+
+        if let Some(rest) = data.strip_suffix(')')
+        {
+            let (rest, _comment) = rest.rsplit_once('(').ok_or(PakigeParseError::InvalidValue)?;
+            data = rest;
+        }
+
+        if let Some((weekday, rest)) = data.split_once(',')
+        {
+            if !WEEKDAYS.contains(&weekday.trim())
+            {
+                return Err(PakigeParseError::InvalidValue);
+            }
+            data = rest;
+        }
+
+        let parts: Vec<&str> = data.split_whitespace().collect();
+        let [day, month, year, time, zone] = parts.as_slice() else { return Err(PakigeParseError::InvalidValue) };
+
+        let day: u8 = parse_number (day, 1, 2)?;
+        let month = match MONTHS.iter().position(|name| name == month)
+        {
+            Some(index) => index as u8 + 1,
+            None => return Err(PakigeParseError::InvalidValue)
+        };
+        let year: i32 = parse_number (year, 4, 4)?;
+
+        let time: Vec<&str> = time.split(':').collect();
+        let (hour, minute, second) = match time.as_slice()
+        {
+            [hour, minute] => (parse_number (hour, 2, 2)?, parse_number (minute, 2, 2)?, 0),
+            [hour, minute, second] => (parse_number (hour, 2, 2)?, parse_number (minute, 2, 2)?, parse_number (second, 2, 2)?),
+            _ => return Err(PakigeParseError::InvalidValue)
+        };
+
+        let offset = match *zone
+        {
+            "UT" | "UTC" | "GMT" | "Z" => 0,
+            _ =>
+            {
+                let (sign, digits) = match zone.split_at_checked(1)
+                {
+                    Some(("+", digits)) => (1, digits),
+                    Some(("-", digits)) => (-1, digits),
+                    _ => return Err(PakigeParseError::InvalidValue)
+                };
+                let zone: u32 = parse_number (digits, 4, 4)?;
+                let (hours, minutes) = ((zone / 100) as i32, (zone % 100) as i32);
+                if hours > 23 || minutes > 59
+                {
+                    return Err(PakigeParseError::InvalidValue);
+                }
+                sign * (hours * 60 + minutes)
+            }
+        };
+
+        // A leap second is allowed
+        if day == 0 || day > days_in_month (year, month) || hour > 23 || minute > 59 || second > 60
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+
+        return Ok(DateTime { year, month, day, hour, minute, second, offset });
+    }
+}
+
+/* As date -R writes it: `Sun, 04 Jun 2023 12:34:56 +0200` */
+impl fmt::Display for DateTime
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        write!(f, "{}, {:02} {} {:04} {:02}:{:02}:{:02} {}{:02}{:02}",
+            WEEKDAYS[self.weekday() as usize], self.day, MONTHS[self.month as usize - 1], self.year,
+            self.hour, self.minute, self.second, sign, self.offset.abs() / 60, self.offset.abs() % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn dates ()
+    {
+        let date = DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 +0200").unwrap();
+        assert_eq!((date.year, date.month, date.day), (2023, 6, 4));
+        assert_eq!((date.hour, date.minute, date.second, date.offset), (12, 34, 56, 120));
+        assert_eq!(date.timestamp(), 1685874896);
+        assert_eq!(date.weekday(), 6);
+        assert_eq!(date.to_string(), "Sun, 04 Jun 2023 12:34:56 +0200");
+
+        // Release files use UTC, and some dates leave out the day of the week or the seconds
+        let date = DateTime::from_str ("Sat, 10 Jun 2023 09:12:45 UTC").unwrap();
+        assert_eq!(date.offset, 0);
+        assert_eq!(date.to_string(), "Sat, 10 Jun 2023 09:12:45 +0000");
+        let date = DateTime::from_str ("4 Jun 2023 12:34 -0530").unwrap();
+        assert_eq!((date.day, date.second, date.offset), (4, 0, -330));
+        assert_eq!(date.to_string(), "Sun, 04 Jun 2023 12:34:00 -0530");
+
+        // The day of the week is not checked against the date
+        assert!(DateTime::from_str ("Mon, 04 Jun 2023 12:34:56 +0200").is_ok());
+        assert!(DateTime::from_str ("Sat, 29 Feb 2020 00:00:00 +0000").is_ok());
+        assert_eq!(DateTime::from_str ("Sat, 29 Feb 2020 00:00:00 -2359").unwrap().offset, -1439);
+        assert!(DateTime::from_str ("Sat, 30 Jun 2012 23:59:60 +0000").is_ok());
+    }
+
+    #[test]
+    fn zone_comments ()
+    {
+        let date = DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 +0200 (CEST)").unwrap();
+        assert_eq!(date.offset, 120);
+        assert_eq!(date, DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 +0200").unwrap());
+        assert!(DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 +0200 (Central European Summer Time)").is_ok());
+        assert!(DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 +0200(CEST)").is_ok());
+
+        // A comment does not stand in for the zone
+        assert!(DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 (CEST)").is_err());
+        assert!(DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 +0200 CEST)").is_err());
+    }
+
+    #[test]
+    fn ordering ()
+    {
+        let utc = DateTime::from_str ("Sun, 04 Jun 2023 10:34:56 +0000").unwrap();
+        let cest = DateTime::from_str ("Sun, 04 Jun 2023 12:34:56 +0200").unwrap();
+        let later = DateTime::from_str ("Sun, 04 Jun 2023 11:00:00 +0000").unwrap();
+        assert_eq!(utc, cest);
+        assert!(cest < later);
+        assert_eq!(DateTime::from_str ("Thu, 01 Jan 1970 00:00:00 +0000").unwrap().timestamp(), 0);
+        assert_eq!(DateTime::from_str ("Wed, 31 Dec 1969 23:59:59 +0000").unwrap().timestamp(), -1);
+    }
+
+    #[test]
+    fn invalid_dates ()
+    {
+        let cases = [
+            "",
+            "Sunday, 04 Jun 2023 12:34:56 +0200",
+            "Sun, 04 June 2023 12:34:56 +0200",
+            "Sun, 04 Jun 23 12:34:56 +0200",
+            "Sun, 04 Jun 2023 12:34:56",
+            "Sun, 04 Jun 2023 12:34:56 CEST",
+            "Sun, 04 Jun 2023 12:34:56 +02",
+            "Sun, 04 Jun 2023 12:34:56 +0260",
+            "Sun, 04 Jun 2023 12:34:56 +9999",
+            "Sun, 04 Jun 2023 12:34:56 -2400",
+            "Sun, 04 Jun 2023 24:00:00 +0200",
+            "Sun, 04 Jun 2023 12:60:00 +0200",
+            "Sun, 04 Jun 2023 1:34:56 +0200",
+            "Sun, 31 Jun 2023 12:34:56 +0200",
+            "Sun, 29 Feb 2023 12:34:56 +0200",
+            "Sun, 29 Feb 1900 12:34:56 +0200",
+            "Sun, 00 Jun 2023 12:34:56 +0200"
+        ];
+        for data in cases
+        {
+            assert!(matches!(DateTime::from_str (data), Err(PakigeParseError::InvalidValue)), "{}", data);
+        }
+    }
+}
@@ -0,0 +1,543 @@
+use crate::PakigeParseError;
+use super::StanzaError;
+use std::io::BufRead;
+use std::ops::Range;
+
+pub mod lossless;
+
+// https://man7.org/linux/man-pages/man5/deb822.5.html
+// A streaming alternative to str_to_table() for large files such as Packages or Contents indices.
+// Lines are read straight into one reusable buffer per stanza, and fields are recorded as byte
+// ranges into it, so looking a field up hands out a borrowed slice instead of a new String.
+// Tokenizing is done by hand rather than with regexes.
+
+// Binary DEBIAN/control files and index files do not allow comments, but source control files
+// such as debian/control do: lines starting with `#` are ignored, wherever they appear.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ParseMode
+{
+    #[default]
+    Strict, // Comment lines are an error
+    SkipComments, // Comment lines are dropped
+    KeepComments // Comment lines are kept alongside the fields, see Fields::comments()
+}
+
+// The fields of a stanza, in file order and spelled as in the file. Field names are not
+// case-sensitive, so lookups ignore case. Stanzas only hold a few dozen fields, so a linear
+// scan is cheaper than hashing here.
+#[derive(Debug, Default, Clone)]
+pub struct Fields
+{
+    entries: Vec<(String, String)>,
+    comments: Vec<(usize, usize, String)> // (position, see comments(), comment line)
+}
+
+impl Fields
+{
+    pub fn new () -> Self
+    {
+        return Fields::default();
+    }
+
+    fn position (&self, key: &str) -> Option<usize>
+    {
+        return self.entries.iter().position(|(name, _)| name.eq_ignore_ascii_case(key));
+    }
+
+    pub fn get (&self, key: &str) -> Option<&String>
+    {
+        return self.position (key).map(|index| &self.entries[index].1);
+    }
+
+    pub fn contains_key (&self, key: &str) -> bool
+    {
+        return self.position (key).is_some();
+    }
+
+    // Replaces the value of an existing field in place, keeping its position and spelling,
+    // or appends a new field. Returns the old value, if any.
+    pub fn insert (&mut self, key: String, value: String) -> Option<String>
+    {
+        return match self.position (&key)
+        {
+            Some(index) => Some(std::mem::replace(&mut self.entries[index].1, value)),
+            None =>
+            {
+                self.entries.push((key, value));
+                None
+            }
+        };
+    }
+
+    // Comments that came after the removed field, or between its continuation lines, move to
+    // the end of the field before it, so that they stay ahead of the fields that followed
+    pub fn remove (&mut self, key: &str) -> Option<String>
+    {
+        let index = self.position (key)?;
+        let (_, value) = self.entries.remove(index);
+
+        let lines = match index
+        {
+            0 => 0,
+            _ => self.entries[index - 1].1.matches('\n').count()
+        };
+        for (fields, continuation_lines, _) in &mut self.comments
+        {
+            if *fields == index + 1
+            {
+                *fields = index;
+                *continuation_lines = lines;
+            }
+            else if *fields > index + 1
+            {
+                *fields -= 1;
+            }
+        }
+        return Some(value);
+    }
+
+    /* (name, value) pairs in file order */
+    pub fn iter (&self) -> impl Iterator<Item = (&str, &str)>
+    {
+        return self.entries.iter().map(|(name, value)| (name.as_str(), value.as_str()));
+    }
+
+    pub fn keys (&self) -> impl Iterator<Item = &str>
+    {
+        return self.entries.iter().map(|(name, _)| name.as_str());
+    }
+
+    pub fn len (&self) -> usize
+    {
+        return self.entries.len();
+    }
+
+    pub fn is_empty (&self) -> bool
+    {
+        return self.entries.is_empty();
+    }
+
+    pub(crate) fn push_comment (&mut self, mode: ParseMode, fields: usize, continuation_lines: usize, line: &str)
+    {
+        if mode == ParseMode::KeepComments
+        {
+            self.comments.push((fields, continuation_lines, line.to_string()));
+        }
+    }
+
+    // Comment lines kept by ParseMode::KeepComments, in file order. Each comes with the number of
+    // fields that started before it and the number of continuation lines of the last of those that
+    // came before it. So (0, 0) is before the first field, and a comment between the continuation
+    // lines of a field has fewer continuation lines before it than the field has.
+    pub fn comments (&self) -> impl Iterator<Item = (usize, usize, &str)>
+    {
+        return self.comments.iter().map(|(fields, lines, line)| (*fields, *lines, line.as_str()));
+    }
+}
+
+/* One deb822 stanza, with its fields kept in file order */
+#[derive(Debug, Default, Clone)]
+pub struct Stanza
+{
+    text: String,
+    fields: Vec<(Range<usize>, Range<usize>)>, // (key, value) byte ranges into `text`
+    comments: Vec<(usize, usize, String)>, // (position, see Fields::comments(), comment line)
+    line: usize
+}
+
+impl Stanza
+{
+    pub fn new () -> Self
+    {
+        return Stanza::default();
+    }
+
+    fn clear (&mut self)
+    {
+        self.text.clear();
+        self.fields.clear();
+        self.comments.clear();
+        self.line = 0;
+    }
+
+    /* The line the stanza starts on (1-based) */
+    pub fn line (&self) -> usize
+    {
+        return self.line;
+    }
+
+    /* The raw text of the stanza */
+    pub fn as_str (&self) -> &str
+    {
+        return &self.text;
+    }
+
+    pub fn len (&self) -> usize
+    {
+        return self.fields.len();
+    }
+
+    pub fn is_empty (&self) -> bool
+    {
+        return self.fields.is_empty();
+    }
+
+    // Field names are not case-sensitive. Multi-line values are returned as they appear in the
+    // file, continuation lines keeping their leading space, exactly as str_to_table() stores them.
+    pub fn get (&self, key: &str) -> Option<&str>
+    {
+        return self.fields()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value);
+    }
+
+    /* (name, value) pairs in file order, with the names spelled as in the file */
+    pub fn fields (&self) -> impl Iterator<Item = (&str, &str)>
+    {
+        return self.fields.iter().map(|(key, value)| (&self.text[key.clone()], &self.text[value.clone()]));
+    }
+
+    /* Comment lines, if read with ParseMode::KeepComments; see Fields::comments() */
+    pub fn comments (&self) -> impl Iterator<Item = (usize, usize, &str)>
+    {
+        return self.comments.iter().map(|(fields, lines, line)| (*fields, *lines, line.as_str()));
+    }
+
+    pub(crate) fn to_fields (&self) -> Fields
+    {
+        let mut fields = Fields::new();
+        for (key, value) in self.fields()
+        {
+            fields.insert(key.to_string(), value.to_string());
+        }
+        for (position, lines, comment) in self.comments()
+        {
+            fields.comments.push((position, lines, comment.to_string()));
+        }
+        return fields;
+    }
+}
+
+pub struct StanzaReader<R>
+{
+    reader: R,
+    mode: ParseMode,
+    line: usize, // lines consumed so far
+    failed: bool
+}
+
+impl<R: BufRead> StanzaReader<R>
+{
+    /* A reader in ParseMode::Strict, for binary control files and index files */
+    pub fn new (reader: R) -> Self
+    {
+        return StanzaReader::with_mode (reader, ParseMode::Strict);
+    }
+
+    pub fn with_mode (reader: R, mode: ParseMode) -> Self
+    {
+        return StanzaReader { reader, mode, line: 0, failed: false };
+    }
+
+    // Reads the next stanza into `stanza`, reusing its buffers. Returns false at end of input.
+    // A malformed stanza is skipped up to the next blank line before its error is returned, so
+    // reading can carry on with the stanza after it.
+    pub fn read_stanza (&mut self, stanza: &mut Stanza) -> Result<bool, StanzaError>
+    {
+        stanza.clear();
+        let mut error: Option<StanzaError> = None;
+
+        loop
+        {
+            let start = stanza.text.len();
+            let read = match self.reader.read_line(&mut stanza.text)
+            {
+                Ok(read) => read,
+                Err(e) => return Err(StanzaError { line: self.line + 1, error: PakigeParseError::from(e) })
+            };
+            if read == 0
+            {
+                break;
+            }
+            self.line += 1;
+
+            let content = stanza.text[start..].trim_end_matches(['\n', '\r']);
+            let end = start + content.len();
+
+            // Blank lines separate stanzas; any before the first field are skipped
+            if content.trim().is_empty()
+            {
+                stanza.text.truncate(start);
+                if stanza.fields.is_empty() && error.is_none()
+                {
+                    continue;
+                }
+                break;
+            }
+
+            if error.is_some()
+            {
+                stanza.text.truncate(start);
+                continue;
+            }
+
+            // Comments may appear anywhere, even between continuation lines
+            if self.mode != ParseMode::Strict && content.starts_with('#')
+            {
+                // Taken out of the text, so that field values stay contiguous
+                if self.mode == ParseMode::KeepComments
+                {
+                    let lines = stanza.fields.last().map_or(0, |(_, value)| stanza.text[value.clone()].matches('\n').count());
+                    stanza.comments.push((stanza.fields.len(), lines, content.to_string()));
+                }
+                stanza.text.truncate(start);
+                continue;
+            }
+
+            if stanza.fields.is_empty()
+            {
+                stanza.line = self.line;
+            }
+
+            // Continuation lines extend the value of the previous field
+            if content.starts_with([' ', '\t'])
+            {
+                match stanza.fields.last_mut()
+                {
+                    Some((_, value)) => value.end = end,
+                    None => error = Some(StanzaError { line: self.line, error: PakigeParseError::InvalidFormat })
+                }
+                continue;
+            }
+
+            let (key, value) = match split_field_line (content)
+            {
+                Some((key, value)) => (start + key.start..start + key.end, start + value.start..end),
+                None =>
+                {
+                    error = Some(StanzaError { line: self.line, error: PakigeParseError::InvalidFormat });
+                    continue;
+                }
+            };
+
+            let name = &stanza.text[key.clone()];
+            if stanza.fields.iter().any(|(other, _)| stanza.text[other.clone()].eq_ignore_ascii_case(name))
+            {
+                error = Some(StanzaError { line: self.line, error: PakigeParseError::DuplicateField });
+                continue;
+            }
+
+            stanza.fields.push((key, value));
+        }
+
+        if let Some(error) = error
+        {
+            return Err(error);
+        }
+
+        return Ok(!stanza.fields.is_empty());
+    }
+}
+
+impl<R: BufRead> Iterator for StanzaReader<R>
+{
+    type Item = Result<Stanza, StanzaError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        // An I/O error may leave the reader in an unknown state, so stop there
+        if self.failed
+        {
+            return None;
+        }
+
+        let mut stanza = Stanza::new();
+        return match self.read_stanza (&mut stanza)
+        {
+            Ok(true) => Some(Ok(stanza)),
+            Ok(false) => None,
+            Err(error) =>
+            {
+                self.failed = matches!(error.error, PakigeParseError::IoError(_));
+                Some(Err(error))
+            }
+        };
+    }
+}
+
+// Splits `Name: value` into the byte ranges of the name and of the value (which has its leading
+// whitespace skipped). The field name is composed of US-ASCII characters excluding control
+// characters, space, and colon, and must not begin with `#` or `-`.
+pub(crate) fn split_field_line (line: &str) -> Option<(Range<usize>, Range<usize>)>
+{
+    let bytes = line.as_bytes();
+    let is_name_byte = |b: u8| (b'!'..=b'9').contains(&b) || (b';'..=b'~').contains(&b);
+
+    let key_end = bytes.iter().position(|b| !is_name_byte(*b)).unwrap_or(bytes.len());
+    if key_end == 0 || bytes[0] == b'#' || bytes[0] == b'-'
+    {
+        return None;
+    }
+
+    let mut colon = key_end;
+    while colon < bytes.len() && (bytes[colon] == b' ' || bytes[colon] == b'\t')
+    {
+        colon += 1;
+    }
+    if colon == bytes.len() || bytes[colon] != b':'
+    {
+        return None;
+    }
+
+    let mut value_start = colon + 1;
+    while value_start < bytes.len() && (bytes[value_start] == b' ' || bytes[value_start] == b'\t')
+    {
+        value_start += 1;
+    }
+
+    return Some((0..key_end, value_start..bytes.len()));
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::io::{self, BufReader, Read};
+
+    fn read_all (data: &str, mode: ParseMode) -> Vec<Result<Stanza, StanzaError>>
+    {
+        return StanzaReader::with_mode (data.as_bytes(), mode).collect();
+    }
+
+    #[test]
+    fn stanzas ()
+    {
+        let data = "\n\nPackage: foo\nDescription: short\n long line\n .\n  verbatim\n\n\n\nPackage: bar\nVersion:1.0\n";
+        let stanzas: Vec<Stanza> = read_all (data, ParseMode::Strict).into_iter().map(Result::unwrap).collect();
+        assert_eq!(stanzas.len(), 2);
+
+        assert_eq!(stanzas[0].line(), 3);
+        assert_eq!(stanzas[0].len(), 2);
+        assert_eq!(stanzas[0].get("description"), Some("short\n long line\n .\n  verbatim"));
+        assert_eq!(stanzas[1].line(), 11);
+        assert_eq!(stanzas[1].get("Version"), Some("1.0"));
+        assert_eq!(stanzas[1].fields().collect::<Vec<_>>(), [("Package", "bar"), ("Version", "1.0")]);
+    }
+
+    #[test]
+    fn same_values_as_str_to_table ()
+    {
+        // Continuation lines may start with a tab as well as a space
+        for data in ["Package: foo\nDepends: a,\n b\nDescription: x\n y\n .\n z\n", "Package: foo\nDepends: a,\n\tb\nDescription: x\n\ty\n\t.\n z\n"]
+        {
+            let stanza = StanzaReader::new (data.as_bytes()).next().unwrap().unwrap();
+            let fields = crate::deb::str_to_table (data).unwrap();
+            assert_eq!(stanza.to_fields().iter().collect::<Vec<_>>(), fields.iter().collect::<Vec<_>>());
+        }
+        let fields = crate::deb::str_to_table ("Depends: a,\n\tb\n").unwrap();
+        assert_eq!(fields.get("Depends").map(String::as_str), Some("a,\n\tb"));
+    }
+
+    #[test]
+    fn errors_skip_to_next_stanza ()
+    {
+        let data = "Package: a\nnot a field\nVersion: 1\n\n continuation first\n\nPackage: b\npackage: c\n\nPackage: d\n";
+        let results = read_all (data, ParseMode::Strict);
+        assert_eq!(results.len(), 4);
+
+        let error = results[0].as_ref().unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, PakigeParseError::InvalidFormat));
+        assert_eq!(results[1].as_ref().unwrap_err().line, 5);
+        let error = results[2].as_ref().unwrap_err();
+        assert_eq!(error.line, 8);
+        assert!(matches!(error.error, PakigeParseError::DuplicateField));
+        assert_eq!(results[3].as_ref().unwrap().get("package"), Some("d"));
+    }
+
+    #[test]
+    fn comments ()
+    {
+        let data = "# leading\nSource: foo\nBuild-Depends: a,\n# b,\n c\n";
+
+        let error = read_all (data, ParseMode::Strict).remove(0).unwrap_err();
+        assert_eq!(error.line, 1);
+
+        let stanza = read_all (data, ParseMode::SkipComments).remove(0).unwrap();
+        assert_eq!(stanza.get("Build-Depends"), Some("a,\n c"));
+        assert_eq!(stanza.comments().count(), 0);
+        assert_eq!(stanza.line(), 2);
+
+        let stanza = read_all (data, ParseMode::KeepComments).remove(0).unwrap();
+        assert_eq!(stanza.get("Build-Depends"), Some("a,\n c"));
+        assert_eq!(stanza.comments().collect::<Vec<_>>(), [(0, 0, "# leading"), (2, 0, "# b,")]);
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader
+    {
+        fn read (&mut self, _buffer: &mut [u8]) -> io::Result<usize>
+        {
+            return Err(io::Error::other("disk on fire"));
+        }
+    }
+
+    #[test]
+    fn io_errors_stop_reading ()
+    {
+        let mut reader = StanzaReader::new (BufReader::new("Package: a\n".as_bytes().chain(FailingReader)));
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, PakigeParseError::IoError(_)));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn fields_ignore_case ()
+    {
+        let mut fields = Fields::new();
+        assert_eq!(fields.insert(String::from("Package"), String::from("foo")), None);
+        assert_eq!(fields.insert(String::from("Version"), String::from("1")), None);
+        assert_eq!(fields.insert(String::from("Multi-Arch"), String::from("same")), None);
+
+        assert_eq!(fields.get("package").map(String::as_str), Some("foo"));
+        assert_eq!(fields.get("PACKAGE").map(String::as_str), Some("foo"));
+        assert!(fields.contains_key("multi-arch"));
+        assert!(!fields.contains_key("Multi"));
+
+        // Replacing keeps the position and the original spelling
+        assert_eq!(fields.insert(String::from("VERSION"), String::from("2")), Some(String::from("1")));
+        assert_eq!(fields.iter().collect::<Vec<_>>(), [("Package", "foo"), ("Version", "2"), ("Multi-Arch", "same")]);
+
+        assert_eq!(fields.remove("package"), Some(String::from("foo")));
+        assert_eq!(fields.keys().collect::<Vec<_>>(), ["Version", "Multi-Arch"]);
+        assert_eq!(fields.len(), 2);
+    }
+
+    #[test]
+    fn remove_keeps_comments_in_place ()
+    {
+        let data = "# top\nSource: foo\nBuild-Depends: a,\n# inside\n b\n# before section\nSection: misc\nPriority: optional\n# end\n";
+        let mut fields = crate::deb::str_to_table_with_mode (data, ParseMode::KeepComments).unwrap();
+        assert_eq!(fields.comments().collect::<Vec<_>>(), [(0, 0, "# top"), (2, 0, "# inside"), (2, 1, "# before section"), (4, 0, "# end")]);
+
+        fields.remove("build-depends");
+        assert_eq!(fields.comments().collect::<Vec<_>>(), [(0, 0, "# top"), (1, 0, "# inside"), (1, 0, "# before section"), (3, 0, "# end")]);
+
+        // The first field has nothing before it
+        fields.remove("Source");
+        assert_eq!(fields.comments().collect::<Vec<_>>(), [(0, 0, "# top"), (0, 0, "# inside"), (0, 0, "# before section"), (2, 0, "# end")]);
+        assert_eq!(fields.keys().collect::<Vec<_>>(), ["Section", "Priority"]);
+    }
+
+    #[test]
+    fn fields_duplicates ()
+    {
+        assert!(matches!(crate::deb::str_to_table ("Package: a\npackage: b\n"), Err(PakigeParseError::DuplicateField)));
+
+        let fields = crate::deb::str_to_table ("package: a\nVERSION: 1\n").unwrap();
+        assert_eq!(fields.get("Package").map(String::as_str), Some("a"));
+        assert_eq!(fields.keys().collect::<Vec<_>>(), ["package", "VERSION"]);
+    }
+}
@@ -0,0 +1,623 @@
+use crate::PakigeParseError;
+use crate::deb::StanzaError;
+use crate::deb::writer::write_field;
+use super::split_field_line;
+use std::fmt;
+use std::str::FromStr;
+
+// A lossless syntax tree for deb822 files such as debian/control. Every line of the input is
+// kept verbatim (comments, blank lines, continuation indentation, trailing whitespace and line
+// endings), so printing an unmodified Document gives back exactly the input. Edits replace only
+// the lines of the field they touch.
+
+/* A field and its raw lines: the `Name: value` line, then any continuation (or comment) lines */
+#[derive(Debug, Clone)]
+pub struct FieldNode
+{
+    name: String,
+    lines: Vec<String>
+}
+
+#[derive(Debug, Clone)]
+pub enum Entry
+{
+    Comment(String),
+    Field(FieldNode)
+}
+
+/* A run of non-blank lines */
+#[derive(Debug, Clone, Default)]
+pub struct Paragraph
+{
+    entries: Vec<Entry>
+}
+
+#[derive(Debug, Clone)]
+pub enum Item
+{
+    Blank(String),
+    Paragraph(Paragraph)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Document
+{
+    items: Vec<Item>
+}
+
+fn is_comment (line: &str) -> bool
+{
+    return line.starts_with('#');
+}
+
+fn is_blank (line: &str) -> bool
+{
+    return line.trim().is_empty();
+}
+
+impl FieldNode
+{
+    // Renders `name: value` as fresh lines, folded the same way BinaryDeb is written out and
+    // ending in `newline`
+    fn render (name: &str, value: &str, newline: &str) -> FieldNode
+    {
+        let mut text = String::new();
+        // Writing into a String cannot fail
+        write_field (&mut text, name, value).unwrap();
+
+        return FieldNode {
+            name: name.to_string(),
+            lines: text.lines().map(|line| format!("{}{}", line, newline)).collect()
+        };
+    }
+
+    /* The field name, spelled as in the file */
+    pub fn name (&self) -> &str
+    {
+        return &self.name;
+    }
+
+    // The value as str_to_table() would store it: continuation lines are joined with a newline
+    // and keep their leading space, and comment lines are left out.
+    pub fn value (&self) -> String
+    {
+        let mut value = String::new();
+
+        for (index, line) in self.lines.iter().enumerate()
+        {
+            let line = line.trim_end_matches(['\n', '\r']);
+            if index == 0
+            {
+                // The first line always splits, or it would not have become a field
+                let (_, range) = split_field_line (line).unwrap();
+                value.push_str(&line[range]);
+            }
+            else if !is_comment (line)
+            {
+                value.push('\n');
+                value.push_str(line);
+            }
+        }
+
+        return value;
+    }
+
+    fn ends_with_newline (&self) -> bool
+    {
+        return self.lines.last().is_some_and(|line| line.ends_with('\n'));
+    }
+
+    // Replaces the value lines, keeping everything else as it was: the name and separator up to
+    // the value (`Name:`, `Name :` or `Name:\t`), the line ending, comment lines (which stay after
+    // the same number of continuation lines as before) and a missing newline at the end of the file.
+    fn replace_value (&mut self, value: &str)
+    {
+        let newline = if self.lines[0].ends_with("\r\n") { "\r\n" } else { "\n" };
+        let rendered = FieldNode::render (&self.name, value, newline);
+        let (first, continuation) = rendered.lines.split_first().unwrap();
+        let first = first.trim_end_matches(['\n', '\r']);
+        let (_, range) = split_field_line (first).unwrap();
+        let first_value = &first[range];
+
+        let old_first = self.lines[0].trim_end_matches(['\n', '\r']);
+        let (_, range) = split_field_line (old_first).unwrap();
+        let mut prefix = old_first[..range.start].to_string();
+        if first_value.is_empty()
+        {
+            prefix.truncate(prefix.trim_end().len());
+        }
+        else if range.is_empty() && !prefix.ends_with([' ', '\t'])
+        {
+            // There was no value on the first line to take the spacing from
+            prefix.push(' ');
+        }
+
+        let ends_with_newline = self.ends_with_newline();
+
+        let mut comments = Vec::new();
+        let mut continuation_lines = 0;
+        for line in &self.lines[1..]
+        {
+            if is_comment (line)
+            {
+                comments.push((continuation_lines, line.clone()));
+            }
+            else
+            {
+                continuation_lines += 1;
+            }
+        }
+        let mut comments = comments.into_iter().peekable();
+
+        let mut lines = vec![format!("{}{}{}", prefix, first_value, newline)];
+        for (index, line) in continuation.iter().enumerate()
+        {
+            while let Some((_, comment)) = comments.next_if(|(position, _)| *position <= index)
+            {
+                lines.push(comment);
+            }
+            lines.push(line.clone());
+        }
+        lines.extend(comments.map(|(_, comment)| comment));
+
+        if !ends_with_newline
+        {
+            if let Some(line) = lines.last_mut()
+            {
+                line.truncate(line.trim_end_matches(['\n', '\r']).len());
+            }
+        }
+        self.lines = lines;
+    }
+}
+
+impl Paragraph
+{
+    fn fields (&self) -> impl Iterator<Item = &FieldNode>
+    {
+        return self.entries.iter().filter_map(|entry| match entry
+        {
+            Entry::Field(field) => Some(field),
+            Entry::Comment(_) => None
+        });
+    }
+
+    fn position (&self, name: &str) -> Option<usize>
+    {
+        return self.entries.iter().position(|entry| match entry
+        {
+            Entry::Field(field) => field.name.eq_ignore_ascii_case(name),
+            Entry::Comment(_) => false
+        });
+    }
+
+    pub fn entries (&self) -> &[Entry]
+    {
+        return &self.entries;
+    }
+
+    /* Field names in file order */
+    pub fn field_names (&self) -> Vec<&str>
+    {
+        return self.fields().map(|field| field.name()).collect();
+    }
+
+    pub fn get (&self, name: &str) -> Option<String>
+    {
+        return self.fields().find(|field| field.name.eq_ignore_ascii_case(name)).map(|field| field.value());
+    }
+
+    pub fn contains_key (&self, name: &str) -> bool
+    {
+        return self.position (name).is_some();
+    }
+
+    /* The line ending of the paragraph's first line, for the lines added to it */
+    fn newline (&self) -> &'static str
+    {
+        let first_line = match self.entries.first()
+        {
+            Some(Entry::Field(field)) => field.lines.first(),
+            Some(Entry::Comment(line)) => Some(line),
+            None => None
+        };
+        return if first_line.is_some_and(|line| line.ends_with("\r\n")) { "\r\n" } else { "\n" };
+    }
+
+    // Makes sure the paragraph's last line is terminated before lines are added after it
+    fn terminate_last_line (&mut self)
+    {
+        let newline = self.newline();
+        let last_line = match self.entries.last_mut()
+        {
+            Some(Entry::Field(field)) => field.lines.last_mut(),
+            Some(Entry::Comment(line)) => Some(line),
+            None => None
+        };
+
+        if let Some(line) = last_line
+        {
+            if !line.ends_with('\n')
+            {
+                line.push_str(newline);
+            }
+        }
+    }
+
+    // Replaces the value of an existing field in place, keeping its name's spelling and its
+    // position, or appends the field to the end of the paragraph. Setting a field to the value it
+    // already has changes nothing.
+    pub fn set (&mut self, name: &str, value: &str)
+    {
+        match self.position (name)
+        {
+            Some(index) =>
+            {
+                let Entry::Field(field) = &mut self.entries[index] else { unreachable!() };
+                if field.value() != value
+                {
+                    field.replace_value (value);
+                }
+            },
+            None =>
+            {
+                self.terminate_last_line();
+                self.entries.push(Entry::Field(FieldNode::render (name, value, self.newline())));
+            }
+        }
+    }
+
+    // Inserts a new field directly after the field `after`. Returns false (and changes nothing)
+    // if `after` is not in the paragraph. If `name` is already present, it is moved.
+    pub fn insert_after (&mut self, after: &str, name: &str, value: &str) -> bool
+    {
+        if self.position (after).is_none()
+        {
+            return false;
+        }
+        if after.eq_ignore_ascii_case(name)
+        {
+            self.set (name, value);
+            return true;
+        }
+
+        self.remove (name);
+        let index = self.position (after).unwrap();
+
+        // `after` may have been the last line of the file
+        if index + 1 == self.entries.len()
+        {
+            self.terminate_last_line();
+        }
+
+        self.entries.insert(index + 1, Entry::Field(FieldNode::render (name, value, self.newline())));
+        return true;
+    }
+
+    /* Removes a field, along with its continuation lines. Returns its old value */
+    pub fn remove (&mut self, name: &str) -> Option<String>
+    {
+        let index = self.position (name)?;
+        return match self.entries.remove(index)
+        {
+            Entry::Field(field) => Some(field.value()),
+            Entry::Comment(_) => None
+        };
+    }
+}
+
+impl Document
+{
+    pub fn new () -> Self
+    {
+        return Document::default();
+    }
+
+    pub fn items (&self) -> &[Item]
+    {
+        return &self.items;
+    }
+
+    /* Paragraphs that hold at least one field; comment-only runs are skipped */
+    pub fn paragraphs (&self) -> impl Iterator<Item = &Paragraph>
+    {
+        return self.items.iter().filter_map(|item| match item
+        {
+            Item::Paragraph(paragraph) if paragraph.fields().next().is_some() => Some(paragraph),
+            _ => None
+        });
+    }
+
+    pub fn paragraphs_mut (&mut self) -> impl Iterator<Item = &mut Paragraph>
+    {
+        return self.items.iter_mut().filter_map(|item| match item
+        {
+            Item::Paragraph(paragraph) if paragraph.fields().next().is_some() => Some(paragraph),
+            _ => None
+        });
+    }
+
+    pub fn paragraph (&self, index: usize) -> Option<&Paragraph>
+    {
+        return self.paragraphs().nth(index);
+    }
+
+    pub fn paragraph_mut (&mut self, index: usize) -> Option<&mut Paragraph>
+    {
+        return self.paragraphs_mut().nth(index);
+    }
+}
+
+impl FromStr for Document
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut items: Vec<Item> = Vec::new();
+        let mut paragraph: Option<Paragraph> = None;
+
+        for (index, line) in data.split_inclusive('\n').enumerate()
+        {
+            let error = |error| StanzaError { line: index + 1, error };
+            let content = line.trim_end_matches(['\n', '\r']);
+
+            if is_blank (content)
+            {
+                if let Some(paragraph) = paragraph.take()
+                {
+                    items.push(Item::Paragraph(paragraph));
+                }
+                items.push(Item::Blank(line.to_string()));
+                continue;
+            }
+
+            let current = paragraph.get_or_insert_with(Paragraph::default);
+
+            if is_comment (content)
+            {
+                current.entries.push(Entry::Comment(line.to_string()));
+            }
+            else if content.starts_with([' ', '\t'])
+            {
+                // Comments followed by a continuation line interrupt a field, so they belong to it
+                let mut interrupting = Vec::new();
+                while let Some(Entry::Comment(_)) = current.entries.last()
+                {
+                    if let Some(Entry::Comment(comment)) = current.entries.pop()
+                    {
+                        interrupting.insert(0, comment);
+                    }
+                }
+
+                match current.entries.last_mut()
+                {
+                    Some(Entry::Field(field)) =>
+                    {
+                        field.lines.append(&mut interrupting);
+                        field.lines.push(line.to_string());
+                    },
+                    _ => return Err(error(PakigeParseError::InvalidFormat))
+                }
+            }
+            else
+            {
+                let (name, _) = split_field_line (content).ok_or(error(PakigeParseError::InvalidFormat))?;
+                let name = &content[name];
+
+                if current.contains_key(name)
+                {
+                    return Err(error(PakigeParseError::DuplicateField));
+                }
+                current.entries.push(Entry::Field(FieldNode {
+                    name: name.to_string(),
+                    lines: vec![line.to_string()]
+                }));
+            }
+        }
+
+        if let Some(paragraph) = paragraph
+        {
+            items.push(Item::Paragraph(paragraph));
+        }
+
+        return Ok(Document { items });
+    }
+}
+
+impl fmt::Display for Document
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        for item in &self.items
+        {
+            match item
+            {
+                Item::Blank(line) => write!(f, "{}", line)?,
+                Item::Paragraph(paragraph) =>
+                {
+                    for entry in &paragraph.entries
+                    {
+                        match entry
+                        {
+                            Entry::Comment(line) => write!(f, "{}", line)?,
+                            Entry::Field(field) =>
+                            {
+                                for line in &field.lines
+                                {
+                                    write!(f, "{}", line)?;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static CONTROL: &str = "\
+# Maintained in git
+Source: hello
+Section:devel
+Priority :  optional\t
+Build-Depends: debhelper-compat (= 13),
+# libfoo-dev,
+               libbar-dev,
+ \tlibbaz-dev
+Standards-Version: 4.6.2
+
+
+Package: hello
+Architecture: any
+Description: example package
+ Some text.
+ .
+ More text.";
+
+    fn document (data: &str) -> Document
+    {
+        return Document::from_str (data).unwrap();
+    }
+
+    #[test]
+    fn unchanged_round_trip ()
+    {
+        assert_eq!(document (CONTROL).to_string(), CONTROL);
+
+        let crlf = CONTROL.replace('\n', "\r\n");
+        assert_eq!(document (&crlf).to_string(), crlf);
+
+        assert_eq!(document ("").to_string(), "");
+        assert_eq!(document ("\n\n# only a comment\n\n").to_string(), "\n\n# only a comment\n\n");
+    }
+
+    #[test]
+    fn values ()
+    {
+        let document = document (CONTROL);
+        let source = document.paragraph(0).unwrap();
+        assert_eq!(source.get("section").as_deref(), Some("devel"));
+        assert_eq!(source.get("Build-Depends").as_deref(),
+                   Some("debhelper-compat (= 13),\n               libbar-dev,\n \tlibbaz-dev"));
+        assert_eq!(source.field_names(), ["Source", "Section", "Priority", "Build-Depends", "Standards-Version"]);
+        assert_eq!(document.paragraphs().count(), 2);
+    }
+
+    #[test]
+    fn set_keeps_comments ()
+    {
+        let mut edited = document (CONTROL);
+        edited.paragraph_mut(0).unwrap().set ("Build-Depends", "debhelper-compat (= 13),\n libbar-dev (>= 2),\n libbaz-dev");
+
+        let expected = CONTROL.replace("\
+Build-Depends: debhelper-compat (= 13),
+# libfoo-dev,
+               libbar-dev,
+ \tlibbaz-dev
+", "\
+Build-Depends: debhelper-compat (= 13),
+# libfoo-dev,
+ libbar-dev (>= 2),
+ libbaz-dev
+");
+        assert_eq!(edited.to_string(), expected);
+    }
+
+    #[test]
+    fn set_keeps_crlf ()
+    {
+        let crlf = CONTROL.replace('\n', "\r\n");
+        let mut edited = document (&crlf);
+        edited.paragraph_mut(0).unwrap().set ("Standards-Version", "4.7.0");
+        edited.paragraph_mut(0).unwrap().set ("Build-Depends", "debhelper-compat (= 13),\n libbar-dev");
+
+        let expected = CONTROL
+            .replace("Standards-Version: 4.6.2", "Standards-Version: 4.7.0")
+            .replace("               libbar-dev,\n \tlibbaz-dev\n", " libbar-dev\n")
+            .replace('\n', "\r\n");
+        assert_eq!(edited.to_string(), expected);
+    }
+
+    #[test]
+    fn set_keeps_separator ()
+    {
+        let mut edited = document (CONTROL);
+        let source = edited.paragraph_mut(0).unwrap();
+        source.set ("section", "utils");
+        source.set ("PRIORITY", "extra");
+
+        let expected = CONTROL
+            .replace("Section:devel\n", "Section:utils\n")
+            .replace("Priority :  optional\t\n", "Priority :  extra\n");
+        assert_eq!(edited.to_string(), expected);
+    }
+
+    #[test]
+    fn set_same_value ()
+    {
+        let mut edited = document (CONTROL);
+        let value = edited.paragraph(0).unwrap().get("Build-Depends").unwrap();
+        edited.paragraph_mut(0).unwrap().set ("Build-Depends", &value);
+        // Values keep trailing whitespace, as with str_to_table()
+        edited.paragraph_mut(0).unwrap().set ("Priority", "optional\t");
+        assert_eq!(edited.to_string(), CONTROL);
+    }
+
+    #[test]
+    fn set_empty_first_line ()
+    {
+        let mut edited = document ("Source: hello\nBuild-Depends:\n debhelper-compat (= 13)\n");
+        edited.paragraph_mut(0).unwrap().set ("Build-Depends", "debhelper-compat (= 13), libfoo-dev");
+        assert_eq!(edited.to_string(), "Source: hello\nBuild-Depends: debhelper-compat (= 13), libfoo-dev\n");
+
+        edited.paragraph_mut(0).unwrap().set ("Build-Depends", "\n debhelper-compat (= 13)");
+        assert_eq!(edited.to_string(), "Source: hello\nBuild-Depends:\n debhelper-compat (= 13)\n");
+    }
+
+    #[test]
+    fn set_last_field_without_newline ()
+    {
+        let mut edited = document (CONTROL);
+        edited.paragraph_mut(1).unwrap().set ("Description", "example package\n Other text.");
+        assert!(edited.to_string().ends_with("Description: example package\n Other text."));
+    }
+
+    #[test]
+    fn add_and_remove_fields ()
+    {
+        let crlf = CONTROL.replace('\n', "\r\n");
+        let mut edited = document (&crlf);
+        let binary = edited.paragraph_mut(1).unwrap();
+        binary.set ("Multi-Arch", "foreign");
+        assert!(binary.insert_after ("Architecture", "Depends", "${misc:Depends}"));
+        assert!(!binary.insert_after ("Homepage", "X-Other", "value"));
+        assert_eq!(edited.paragraph_mut(0).unwrap().remove ("Section").as_deref(), Some("devel"));
+
+        let expected = CONTROL
+            .replace("Section:devel\n", "")
+            .replace("Architecture: any\n", "Architecture: any\nDepends: ${misc:Depends}\n")
+            .replace("\n More text.", "\n More text.\nMulti-Arch: foreign\n")
+            .replace('\n', "\r\n");
+        assert_eq!(edited.to_string(), expected);
+    }
+
+    #[test]
+    fn errors ()
+    {
+        let error = Document::from_str ("Source: a\nsource: b\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, PakigeParseError::DuplicateField));
+
+        let error = Document::from_str ("\n continuation\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, PakigeParseError::InvalidFormat));
+    }
+}
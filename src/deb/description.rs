@@ -0,0 +1,204 @@
+use crate::PakigeParseError;
+use std::str::FromStr;
+
+// https://www.debian.org/doc/debian-policy/ch-controlfields.html#description
+// The first line of the field is the synopsis. The continuation lines form the extended
+// description: a line of ` .` separates paragraphs, a line starting with a single space is text
+// that may be re-wrapped, and a line starting with two or more spaces is shown verbatim.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DescriptionLine
+{
+    Text(String), // Without its leading space
+    Verbatim(String) // Without the first of its leading spaces, so the indentation is kept
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Description
+{
+    pub synopsis: String,
+    pub paragraphs: Vec<Vec<DescriptionLine>>
+}
+
+impl Description
+{
+    // Plain text for display: the synopsis, then each paragraph after a blank line. Runs of text
+    // lines are joined into one line to be wrapped by the caller; verbatim lines are kept as is.
+    pub fn to_plain_text (&self) -> String
+    {
+        let mut text = self.synopsis.clone();
+
+        for paragraph in &self.paragraphs
+        {
+            text.push_str("\n\n");
+
+            let mut previous_was_text = false;
+            for (index, line) in paragraph.iter().enumerate()
+            {
+                match line
+                {
+                    DescriptionLine::Text(line) =>
+                    {
+                        if previous_was_text
+                        {
+                            text.push(' ');
+                        }
+                        else if index > 0
+                        {
+                            text.push('\n');
+                        }
+                        text.push_str(line.trim());
+                        previous_was_text = true;
+                    },
+                    DescriptionLine::Verbatim(line) =>
+                    {
+                        if index > 0
+                        {
+                            text.push('\n');
+                        }
+                        text.push_str(line);
+                        previous_was_text = false;
+                    }
+                }
+            }
+        }
+
+        return text;
+    }
+
+    // The field value as it is stored in a control file (and by str_to_table): continuation
+    // lines keep their leading space and paragraphs are separated by ` .`
+    pub fn to_control (&self) -> String
+    {
+        let mut value = self.synopsis.clone();
+
+        for (index, paragraph) in self.paragraphs.iter().enumerate()
+        {
+            if index > 0
+            {
+                value.push_str("\n .");
+            }
+
+            for line in paragraph
+            {
+                value.push_str("\n ");
+                match line
+                {
+                    DescriptionLine::Text(line) => value.push_str(line),
+                    DescriptionLine::Verbatim(line) => value.push_str(line)
+                }
+            }
+        }
+
+        return value;
+    }
+}
+
+// Parses the field value as stored by str_to_table, e.g. "synopsis\n text\n .\n  verbatim".
+// Whitespace at the end of lines (and around the synopsis) is dropped, and continuation lines
+// indented with a tab are written back by to_control() with a space, so only values that are
+// already in that form round-trip exactly. A trailing ` .` is kept as an empty last paragraph.
+impl FromStr for Description
+{
+    type Err = PakigeParseError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut lines = data.split('\n');
+        let synopsis = lines.next().unwrap_or("").trim().to_string();
+
+        let mut paragraphs: Vec<Vec<DescriptionLine>> = Vec::new();
+        let mut paragraph = Vec::new();
+
+        for line in lines
+        {
+            // Every continuation line starts with a space (or tab)
+            let line = match line.strip_prefix([' ', '\t'])
+            {
+                Some(line) => line.trim_end(),
+                None => return Err(PakigeParseError::InvalidFormat)
+            };
+
+            if line == "."
+            {
+                paragraphs.push(std::mem::take(&mut paragraph));
+            }
+            else if line.starts_with([' ', '\t'])
+            {
+                paragraph.push(DescriptionLine::Verbatim(line.to_string()));
+            }
+            else
+            {
+                paragraph.push(DescriptionLine::Text(line.to_string()));
+            }
+        }
+
+        // After a ` .`, the last paragraph is kept even when empty, so the value round-trips
+        if !paragraph.is_empty() || !paragraphs.is_empty()
+        {
+            paragraphs.push(paragraph);
+        }
+
+        return Ok(Description { synopsis, paragraphs });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn round_trip (value: &str) -> Description
+    {
+        let description = Description::from_str (value).unwrap();
+        assert_eq!(description.to_control(), value);
+        assert_eq!(Description::from_str (&description.to_control()).unwrap(), description);
+        return description;
+    }
+
+    #[test]
+    fn paragraphs ()
+    {
+        let description = round_trip ("short\n first paragraph,\n wrapped\n .\n second\n   verbatim\n  also verbatim");
+        assert_eq!(description.synopsis, "short");
+        assert_eq!(description.paragraphs, [
+            vec![DescriptionLine::Text(String::from("first paragraph,")), DescriptionLine::Text(String::from("wrapped"))],
+            vec![
+                DescriptionLine::Text(String::from("second")),
+                DescriptionLine::Verbatim(String::from("  verbatim")),
+                DescriptionLine::Verbatim(String::from(" also verbatim"))
+            ]
+        ]);
+        assert_eq!(description.to_plain_text(), "short\n\nfirst paragraph, wrapped\n\nsecond\n  verbatim\n also verbatim");
+    }
+
+    #[test]
+    fn empty_paragraphs ()
+    {
+        assert!(round_trip ("synopsis only").paragraphs.is_empty());
+        assert_eq!(round_trip ("short\n text\n .").paragraphs.len(), 2);
+        assert_eq!(round_trip ("short\n .\n text").paragraphs.len(), 2);
+        assert_eq!(round_trip ("short\n .").paragraphs, [Vec::new(), Vec::new()]);
+        assert_eq!(round_trip ("short\n a\n .\n .\n b").paragraphs.len(), 3);
+    }
+
+    #[test]
+    fn normalization ()
+    {
+        let description = Description::from_str (" short \n text  \n\t.\t\n\ttabbed\n   verbatim \n . ").unwrap();
+        assert_eq!(description.synopsis, "short");
+        assert_eq!(description.paragraphs, [
+            vec![DescriptionLine::Text(String::from("text"))],
+            vec![DescriptionLine::Text(String::from("tabbed")), DescriptionLine::Verbatim(String::from("  verbatim"))],
+            Vec::new()
+        ]);
+        assert_eq!(description.to_control(), "short\n text\n .\n tabbed\n   verbatim\n .");
+        assert_eq!(Description::from_str (&description.to_control()).unwrap(), description);
+    }
+
+    #[test]
+    fn errors ()
+    {
+        assert!(matches!(Description::from_str ("short\nnot indented"), Err(PakigeParseError::InvalidFormat)));
+    }
+}
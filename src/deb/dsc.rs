@@ -0,0 +1,213 @@
+use crate::PakigeParseError;
+use super::{str_to_table, parse_clearsigned, DependsPackageList, Fields, FileChecksum, PackageListEntry,
+            ProvidesPackageList};
+use super::setters::{set_format, set_source_name, set_package, set_binary_list, set_architecture_list, set_version,
+                     set_maintainer, set_uploaders, set_homepage, set_standards_version, set_vcs,
+                     set_testsuite, set_build_depends, set_build_depends_indep, set_build_depends_arch,
+                     set_build_conflicts, set_build_conflicts_indep, set_build_conflicts_arch,
+                     set_package_list, set_files, set_checksums_sha1, set_checksums_sha256};
+use deb_version7::DebVersion;
+use std::str::FromStr;
+
+// https://manpages.debian.org/dsc.5
+// A .dsc describes a source package: the source paragraph of debian/control, plus the version
+// and the files making up the package, with their checksums. It is usually clearsigned.
+
+#[derive(Debug, Clone)]
+pub struct Dsc
+{
+    pub format: String, /* Mandatory */
+    pub source: String, /* Mandatory */
+    pub binary: Vec<String>,
+    pub architecture: Vec<String>,
+    pub version: DebVersion, /* Mandatory */
+    pub maintainer: String, /* Mandatory */
+    pub uploaders: Vec<String>,
+    pub homepage: Option<String>,
+    pub standards_version: Option<String>, /* Recommended */
+    pub vcs: Vec<(String, String)>,
+    pub testsuite: Vec<String>,
+    pub build_depends: Option<DependsPackageList>,
+    pub build_depends_indep: Option<DependsPackageList>,
+    pub build_depends_arch: Option<DependsPackageList>,
+    pub build_conflicts: Option<ProvidesPackageList>,
+    pub build_conflicts_indep: Option<ProvidesPackageList>,
+    pub build_conflicts_arch: Option<ProvidesPackageList>,
+    pub package_list: Vec<PackageListEntry>,
+    pub files: Vec<FileChecksum>, /* Mandatory */
+    pub checksums_sha1: Vec<FileChecksum>, /* Recommended */
+    pub checksums_sha256: Vec<FileChecksum>, /* Recommended */
+    pub signature: Option<String>, // The armored OpenPGP signature, if the file was clearsigned
+    pub all_fields: Fields
+}
+
+impl Dsc
+{
+    fn from_fields (fields: Fields, signature: Option<String>) -> Result<Self, PakigeParseError>
+    {
+        let source = set_source_name (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?; /* Mandatory */
+        return Dsc::build (fields, source, signature);
+    }
+
+    // Stanzas of an APT Sources index name the source package in Package rather than Source
+    pub(super) fn from_index_fields (fields: Fields) -> Result<Self, PakigeParseError>
+    {
+        let source = set_package (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?; /* Mandatory */
+        return Dsc::build (fields, source, None);
+    }
+
+    fn build (fields: Fields, source: String, signature: Option<String>) -> Result<Self, PakigeParseError>
+    {
+        return Ok(Dsc {
+            format: set_format (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            source,
+            binary: set_binary_list (&fields)?.unwrap_or_default(),
+            architecture: set_architecture_list (&fields)?.unwrap_or_default(),
+            version: set_version (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            maintainer: set_maintainer (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            uploaders: set_uploaders (&fields)?.unwrap_or_default(),
+            homepage: set_homepage (&fields)?,
+            standards_version: set_standards_version (&fields)?, /* Recommended */
+            vcs: set_vcs (&fields)?,
+            testsuite: set_testsuite (&fields)?.unwrap_or_default(),
+            build_depends: set_build_depends (&fields)?,
+            build_depends_indep: set_build_depends_indep (&fields)?,
+            build_depends_arch: set_build_depends_arch (&fields)?,
+            build_conflicts: set_build_conflicts (&fields)?,
+            build_conflicts_indep: set_build_conflicts_indep (&fields)?,
+            build_conflicts_arch: set_build_conflicts_arch (&fields)?,
+            package_list: set_package_list (&fields)?.unwrap_or_default(),
+            files: set_files (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            checksums_sha1: set_checksums_sha1 (&fields)?.unwrap_or_default(), /* Recommended */
+            checksums_sha256: set_checksums_sha256 (&fields)?.unwrap_or_default(), /* Recommended */
+            signature,
+            all_fields: fields
+        });
+    }
+
+    /* Whether the .dsc came wrapped in an OpenPGP signature (which is not verified here) */
+    pub fn is_signed (&self) -> bool
+    {
+        return self.signature.is_some();
+    }
+}
+
+// Accepts the file either clearsigned or bare
+impl FromStr for Dsc
+{
+    type Err = PakigeParseError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let signed = parse_clearsigned (data)?;
+        let fields = str_to_table (&signed.body)?;
+        return Dsc::from_fields (fields, signed.signature);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static DSC: &str = "\
+-----BEGIN PGP SIGNED MESSAGE-----
+Hash: SHA512
+
+Format: 3.0 (quilt)
+Source: hello
+Binary: hello
+Architecture: any
+Version: 2.10-3
+Maintainer: Santiago Vila <sanvila@debian.org>
+Homepage: https://www.gnu.org/software/hello/
+Standards-Version: 4.6.2
+Vcs-Browser: https://salsa.debian.org/sanvila/hello
+Testsuite: autopkgtest
+Build-Depends: debhelper-compat (= 13)
+Package-List:
+ hello deb devel optional arch=any
+Checksums-Sha1:
+ dc894908c0eac029a31f01e32b7f0df596c7399c 725946 hello_2.10.orig.tar.gz
+ a6eb4d9d7f99ca47abe56f3220597663cf37ca4a 12688 hello_2.10-3.debian.tar.xz
+Checksums-Sha256:
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 725946 hello_2.10.orig.tar.gz
+ 81d93757457f988523814ae0009837ae893f38d3fe123f2c37896f118b4c7804 12688 hello_2.10-3.debian.tar.xz
+Files:
+ 025f253325b46929cd34f2a7c3c55e7c 725946 hello_2.10.orig.tar.gz
+ 6e9552c9bd8e61c8f277c21220160234 12688 hello_2.10-3.debian.tar.xz
+-----BEGIN PGP SIGNATURE-----
+
+iQIzBAEBCgAdFiEEAAAAAAAAAAAAAAAAAAAAAAAAAAAFAmQAAAAACgkQAAAAAAAA
+=AAAA
+-----END PGP SIGNATURE-----
+";
+
+    #[test]
+    fn signed_dsc ()
+    {
+        let dsc = Dsc::from_str (DSC).unwrap();
+        assert!(dsc.is_signed());
+        assert_eq!(dsc.format, "3.0 (quilt)");
+        assert_eq!(dsc.source, "hello");
+        assert_eq!(dsc.binary, ["hello"]);
+        assert_eq!(dsc.architecture, ["any"]);
+        assert_eq!(dsc.version.to_string(), "2.10-3");
+        assert_eq!(dsc.standards_version.as_deref(), Some("4.6.2"));
+        assert_eq!(dsc.vcs, [(String::from("Browser"), String::from("https://salsa.debian.org/sanvila/hello"))]);
+        assert_eq!(dsc.testsuite, ["autopkgtest"]);
+        assert_eq!(dsc.build_depends.as_ref().unwrap().0.len(), 1);
+
+        assert_eq!(dsc.package_list.len(), 1);
+        assert_eq!(dsc.package_list[0].package, "hello");
+        assert_eq!(dsc.package_list[0].architecture, ["any"]);
+
+        assert_eq!(dsc.files.len(), 2);
+        assert_eq!(dsc.files[1], FileChecksum {
+            checksum: String::from("6e9552c9bd8e61c8f277c21220160234"),
+            size: 12688,
+            filename: String::from("hello_2.10-3.debian.tar.xz")
+        });
+        assert_eq!(dsc.checksums_sha1.len(), 2);
+        assert_eq!(dsc.checksums_sha256[0].checksum, "14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f");
+        assert!(dsc.signature.as_ref().unwrap().starts_with("-----BEGIN PGP SIGNATURE-----\n"));
+    }
+
+    #[test]
+    fn unsigned_dsc ()
+    {
+        let start = DSC.find("Format:").unwrap();
+        let end = DSC.find("-----BEGIN PGP SIGNATURE-----").unwrap();
+        let dsc = Dsc::from_str (&DSC[start..end]).unwrap();
+        assert!(!dsc.is_signed());
+        assert_eq!(dsc.files.len(), 2);
+    }
+
+    #[test]
+    fn dsc_errors ()
+    {
+        let error = Dsc::from_str (&DSC.replace("Files:", "Checksums-Md5:")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::MissingMandatoryField));
+
+        let error = Dsc::from_str (&DSC.replace("Format: 3.0 (quilt)", "Format: 3.0 quilt")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+
+        // A digest of the wrong length for its table, then a file listed twice
+        let error = Dsc::from_str (&DSC.replace(" 6e9552c9bd8e61c8f277c21220160234 ", " 6e9552c9bd8e61c8 ")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+
+        let error = Dsc::from_str (&DSC.replace(" 12688 hello_2.10-3.debian.tar.xz\n-", " 12688 hello_2.10.orig.tar.gz\n-")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+
+        // Names in Binary and Package-List must be whole valid package names
+        for name in ["Foo", "Foo Bar", "x!!", "hello world", "h", "-hello"]
+        {
+            let error = Dsc::from_str (&DSC.replace("Binary: hello\n", &format!("Binary: {}\n", name))).unwrap_err();
+            assert!(matches!(error, PakigeParseError::InvalidValue), "{}", name);
+            let error = Dsc::from_str (&DSC.replace(" hello deb devel", &format!(" {} deb devel", name))).unwrap_err();
+            assert!(matches!(error, PakigeParseError::InvalidValue), "{}", name);
+        }
+        let dsc = Dsc::from_str (&DSC.replace("Binary: hello\n", "Binary: hello, hello-doc,\n libhello2.0\n")).unwrap();
+        assert_eq!(dsc.binary, ["hello", "hello-doc", "libhello2.0"]);
+    }
+}
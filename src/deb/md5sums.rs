@@ -0,0 +1,130 @@
+use crate::PakigeParseError;
+use super::StanzaError;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// The md5sums control file lists the MD5 digest of each file a package installs, one per line
+// in md5sum(1) output form: `<digest>  <path>`, with paths relative to the root directory.
+// Conffiles are usually included too, although dpkg tracks their digests separately.
+
+// Packages can ship tens of thousands of files, so lookups by path go through an index
+#[derive(Debug, Clone, Default)]
+pub struct Md5Sums
+{
+    entries: Vec<(String, String)>, // (path, lowercase hex digest), in file order
+    index: HashMap<String, usize> // path -> position in entries
+}
+
+impl Md5Sums
+{
+    /* (path, lowercase hex digest) pairs, in file order */
+    pub fn entries (&self) -> &[(String, String)]
+    {
+        return &self.entries;
+    }
+
+    /* The digest of a file, given with or without its leading slash */
+    pub fn get (&self, path: &str) -> Option<&str>
+    {
+        let path = path.trim_start_matches('/');
+        return self.index.get(path).map(|index| self.entries[*index].1.as_str());
+    }
+
+    pub fn len (&self) -> usize
+    {
+        return self.entries.len();
+    }
+
+    pub fn is_empty (&self) -> bool
+    {
+        return self.entries.is_empty();
+    }
+}
+
+// A package that installs no files has an empty md5sums, so empty input is not an error here.
+// md5sum's binary-mode marker (`<digest> *<path>`) is accepted as well.
+impl FromStr for Md5Sums
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut entries = Vec::new();
+        let mut positions = HashMap::new();
+
+        for (index, line) in data.lines().enumerate()
+        {
+            let error = |error| StanzaError { line: index + 1, error };
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty()
+            {
+                continue;
+            }
+
+            let (digest, path) = line.split_once(' ').ok_or(error(PakigeParseError::InvalidFormat))?;
+            let path = path.strip_prefix([' ', '*']).ok_or(error(PakigeParseError::InvalidFormat))?;
+            if digest.len() != 32 || !digest.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                return Err(error(PakigeParseError::InvalidValue));
+            }
+
+            let path = path.trim_start_matches("./").trim_start_matches('/');
+            if path.is_empty()
+            {
+                return Err(error(PakigeParseError::InvalidFormat));
+            }
+            if positions.insert(path.to_string(), entries.len()).is_some()
+            {
+                return Err(error(PakigeParseError::DuplicateField));
+            }
+
+            entries.push((path.to_string(), digest.to_ascii_lowercase()));
+        }
+
+        return Ok(Md5Sums { entries, index: positions });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing::assert_stanza_error;
+
+    #[test]
+    fn md5sums ()
+    {
+        let md5sums = Md5Sums::from_str ("\
+d9ee44d59390c7097f20a0ec1c449048  usr/bin/hello
+ADEB71A8269489A50DB4052AD89FD298 *./usr/share/doc/hello/a file with spaces\r
+
+0123456789abcdef0123456789abcdef  /etc/hello.conf
+").unwrap();
+        assert_eq!(md5sums.len(), 3);
+        assert_eq!(md5sums.entries()[0], (String::from("usr/bin/hello"), String::from("d9ee44d59390c7097f20a0ec1c449048")));
+        assert_eq!(md5sums.get("/usr/share/doc/hello/a file with spaces"), Some("adeb71a8269489a50db4052ad89fd298"));
+        assert_eq!(md5sums.get("etc/hello.conf"), Some("0123456789abcdef0123456789abcdef"));
+        assert_eq!(md5sums.get("usr/bin/hi"), None);
+
+        assert!(Md5Sums::from_str ("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn md5sums_errors ()
+    {
+        let line = "d9ee44d59390c7097f20a0ec1c449048  usr/bin/hello\n";
+        let cases = [
+            ("d9ee44d59390c7097f20a0ec1c449048", 1, PakigeParseError::InvalidFormat),
+            ("d9ee44d59390c7097f20a0ec1c449048 usr/bin/hello", 1, PakigeParseError::InvalidFormat),
+            ("d9ee44d59390c7097f20a0ec1c44904  usr/bin/hello", 1, PakigeParseError::InvalidValue),
+            ("z9ee44d59390c7097f20a0ec1c449048  usr/bin/hello", 1, PakigeParseError::InvalidValue),
+            ("d9ee44d59390c7097f20a0ec1c449048  ./", 1, PakigeParseError::InvalidFormat),
+            (&format!("{}\n./usr/bin/hi\n", line), 3, PakigeParseError::InvalidFormat),
+            (&format!("{}{}", line, line.replace("  ", "  /")), 2, PakigeParseError::DuplicateField)
+        ];
+        for (data, line, expected) in cases
+        {
+            assert_stanza_error (Md5Sums::from_str (data), data, line, expected);
+        }
+    }
+}
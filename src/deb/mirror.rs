@@ -0,0 +1,435 @@
+use crate::PakigeParseError;
+use crate::digest::{hash_reader, Sha256, Sha512};
+use super::{FileChecksum, Release};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Component, Path};
+
+// Checks the Packages and Sources indices of a mirrored distribution against its Release file.
+// Release lists each index under several compressions (Packages, Packages.gz, Packages.xz), and
+// mirrors commonly carry only some of them, so an index only counts as missing when none of its
+// listed variants is present. With Acquire-By-Hash, every variant that is present should also be
+// found under by-hash/SHA256/<hash> (and by-hash/SHA512/<hash> when a SHA512 table is given).
+// Mirrors keep superseded by-hash files around for a while, so by-hash files that Release no
+// longer lists are reported apart, and do not make the mirror fail verification.
+// Release files come from the network: an index path that would lead out of the mirror (absolute,
+// or through `..`) is an invalid value, and nothing is checked.
+
+static INDEX_NAMES: &[&str] = &["Packages", "Sources"];
+static INDEX_COMPRESSIONS: &[&str] = &["", ".gz", ".xz", ".bz2", ".zst"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileMismatch
+{
+    Size(u64, u64), // (size in Release, size on disk)
+    Checksum(String) // The algorithm whose digest did not match
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MirrorReport
+{
+    pub verified: Vec<String>, // Paths relative to the directory of the Release file
+    pub missing: Vec<String>,
+    pub mismatched: Vec<(String, FileMismatch)>,
+    pub extra: Vec<String>, // Index files on disk that Release does not list
+    pub stale_by_hash: Vec<String> // By-hash files on disk that Release does not list
+}
+
+impl MirrorReport
+{
+    /* Stale by-hash files are left out, as mirrors keep them on purpose */
+    pub fn is_ok (&self) -> bool
+    {
+        return self.missing.is_empty() && self.mismatched.is_empty() && self.extra.is_empty();
+    }
+}
+
+/* `Packages.xz` -> `Packages`, for the names of Packages and Sources indices only */
+fn index_base (name: &str) -> Option<&str>
+{
+    return INDEX_COMPRESSIONS.iter()
+        .filter_map(|extension| name.strip_suffix(extension))
+        .find(|base| INDEX_NAMES.contains(base));
+}
+
+/* `main/binary-amd64/Packages.xz` -> (`main/binary-amd64`, `Packages.xz`) */
+fn split_path (path: &str) -> (&str, &str)
+{
+    return path.rsplit_once('/').unwrap_or(("", path));
+}
+
+fn by_hash_path (path: &str, algorithm: &str, checksum: &str) -> String
+{
+    return match split_path (path)
+    {
+        ("", _) => format!("by-hash/{}/{}", algorithm, checksum),
+        (directory, _) => format!("{}/by-hash/{}/{}", directory, algorithm, checksum)
+    };
+}
+
+/* Paths that stay under the directory they are joined to */
+pub(super) fn is_relative_path (path: &str) -> bool
+{
+    return Path::new(path).components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+}
+
+/* path -> (SHA256 entry, SHA512 entry), for the Packages and Sources indices */
+type IndexEntries<'a> = HashMap<&'a str, (Option<&'a FileChecksum>, Option<&'a FileChecksum>)>;
+
+impl Release
+{
+    fn table (&self, algorithm: &str) -> Option<&[FileChecksum]>
+    {
+        return match algorithm
+        {
+            "MD5Sum" => Some(&self.md5sum),
+            "SHA1" => Some(&self.sha1),
+            "SHA256" => Some(&self.sha256),
+            "SHA512" => Some(&self.sha512),
+            _ => None
+        };
+    }
+
+    /* The Packages and Sources indices listed in the SHA256 and SHA512 tables */
+    fn index_entries (&self) -> IndexEntries<'_>
+    {
+        let mut entries: IndexEntries = HashMap::new();
+        for file in self.sha256.iter().filter(|file| index_base (split_path (&file.filename).1).is_some())
+        {
+            entries.entry(&file.filename).or_default().0.get_or_insert(file);
+        }
+        for file in self.sha512.iter().filter(|file| index_base (split_path (&file.filename).1).is_some())
+        {
+            entries.entry(&file.filename).or_default().1.get_or_insert(file);
+        }
+        return entries;
+    }
+
+    // Every copy a listed index should have on disk: its own path, plus its by-hash paths when
+    // the Release asks for them. Each copy comes with the algorithm and entry to check it against.
+    fn copies<'a> (&self, path: &str, sha256: Option<&'a FileChecksum>, sha512: Option<&'a FileChecksum>) -> Vec<(String, &'static str, &'a FileChecksum)>
+    {
+        let mut copies = Vec::new();
+
+        // The strongest digest listed for the file is used for its own path
+        match (sha512, sha256)
+        {
+            (Some(entry), _) => copies.push((path.to_string(), "SHA512", entry)),
+            (None, Some(entry)) => copies.push((path.to_string(), "SHA256", entry)),
+            (None, None) => return copies
+        }
+
+        if self.acquire_by_hash
+        {
+            if let Some(entry) = sha256
+            {
+                copies.push((by_hash_path (path, "SHA256", &entry.checksum), "SHA256", entry));
+            }
+            if let Some(entry) = sha512
+            {
+                copies.push((by_hash_path (path, "SHA512", &entry.checksum), "SHA512", entry));
+            }
+        }
+
+        return copies;
+    }
+
+    // Verifies the Packages and Sources indices (plain or compressed) listed in the SHA256 and
+    // SHA512 tables against the files under `root`, the directory holding the Release file
+    // (e.g. `mirror/dists/bookworm`). Files are checked by size, then by digest.
+    pub fn verify_indices (&self, root: &Path) -> Result<MirrorReport, PakigeParseError>
+    {
+        let mut report = MirrorReport::default();
+
+        let entries = self.index_entries ();
+        let indices: Vec<&str> = self.paths().into_iter().filter(|path| entries.contains_key(path)).collect();
+        if !indices.iter().all(|path| is_relative_path (path))
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+        let copies = |path: &str| {
+            let (sha256, sha512) = entries[path];
+            return self.copies (path, sha256, sha512);
+        };
+
+        // Group the compressions of each index: main/binary-amd64/Packages{,.gz,.xz}
+        let mut groups: Vec<Vec<&str>> = Vec::new();
+        let mut positions: HashMap<(&str, &str), usize> = HashMap::new();
+        for path in &indices
+        {
+            let (directory, name) = split_path (path);
+            let base = (directory, index_base (name).unwrap_or(name));
+            match positions.get(&base)
+            {
+                Some(position) => groups[*position].push(path),
+                None =>
+                {
+                    positions.insert(base, groups.len());
+                    groups.push(vec![path]);
+                }
+            }
+        }
+
+        for variants in &groups
+        {
+            let present: Vec<&str> = variants.iter()
+                .filter(|path| copies (path).iter().any(|(copy, _, _)| root.join(copy).is_file()))
+                .copied()
+                .collect();
+
+            if present.is_empty()
+            {
+                report.missing.extend(variants.iter().map(|path| path.to_string()));
+                continue;
+            }
+
+            for path in present
+            {
+                for (copy, algorithm, entry) in copies (path)
+                {
+                    let file = root.join(&copy);
+                    if !file.is_file()
+                    {
+                        report.missing.push(copy);
+                        continue;
+                    }
+
+                    let size = fs::metadata(&file)?.len();
+                    if size != entry.size
+                    {
+                        report.mismatched.push((copy, FileMismatch::Size(entry.size, size)));
+                        continue;
+                    }
+
+                    let reader = BufReader::new(File::open(&file)?);
+                    let (checksum, _) = match algorithm
+                    {
+                        "SHA512" => hash_reader (Sha512::new(), reader)?,
+                        _ => hash_reader (Sha256::new(), reader)?
+                    };
+                    if checksum != entry.checksum
+                    {
+                        report.mismatched.push((copy, FileMismatch::Checksum(algorithm.to_string())));
+                        continue;
+                    }
+
+                    report.verified.push(copy);
+                }
+            }
+        }
+
+        // Look for unlisted files in each directory that holds an index
+        let mut directories: Vec<&str> = Vec::new();
+        let mut seen = HashSet::new();
+        for path in &indices
+        {
+            let directory = split_path (path).0;
+            if seen.insert(directory)
+            {
+                directories.push(directory);
+            }
+        }
+        let listed: HashSet<&str> = self.paths().into_iter().collect();
+
+        // (algorithm, directory, checksum) of every listed file, to tell stale by-hash files
+        let mut known = HashSet::new();
+        for algorithm in ["MD5Sum", "SHA1", "SHA256", "SHA512"]
+        {
+            for entry in self.table (algorithm).unwrap_or(&[])
+            {
+                known.insert((algorithm, split_path (&entry.filename).0, entry.checksum.as_str()));
+            }
+        }
+
+        for directory in directories
+        {
+            let prefix = if directory.is_empty() { String::new() } else { format!("{}/", directory) };
+            let entries = match fs::read_dir(root.join(directory))
+            {
+                Ok(entries) => entries,
+                Err(_) => continue // Already reported as missing
+            };
+
+            // Sorted, as directory order is arbitrary
+            let mut names = Vec::new();
+            for entry in entries
+            {
+                let entry = entry?;
+                if entry.file_type()?.is_file()
+                {
+                    names.push(entry.file_name().to_string_lossy().into_owned());
+                }
+            }
+            names.sort();
+
+            for name in names
+            {
+                let path = format!("{}{}", prefix, name);
+                if index_base (&name).is_some() && !listed.contains(&path.as_str())
+                {
+                    report.extra.push(path);
+                }
+            }
+
+            let by_hash = match fs::read_dir(root.join(directory).join("by-hash"))
+            {
+                Ok(entries) => entries,
+                Err(_) => continue
+            };
+            let mut algorithms = Vec::new();
+            for algorithm in by_hash
+            {
+                let algorithm = algorithm?;
+                if algorithm.file_type()?.is_dir()
+                {
+                    algorithms.push(algorithm.file_name().to_string_lossy().into_owned());
+                }
+            }
+            algorithms.sort();
+
+            for algorithm_name in algorithms
+            {
+                let mut checksums = Vec::new();
+                for file in fs::read_dir(root.join(directory).join("by-hash").join(&algorithm_name))?
+                {
+                    let file = file?;
+                    if file.file_type()?.is_file()
+                    {
+                        checksums.push(file.file_name().to_string_lossy().into_owned());
+                    }
+                }
+                checksums.sort();
+
+                for checksum in checksums
+                {
+                    if !known.contains(&(algorithm_name.as_str(), directory, checksum.as_str()))
+                    {
+                        report.stale_by_hash.push(format!("{}by-hash/{}/{}", prefix, algorithm_name, checksum));
+                    }
+                }
+            }
+        }
+
+        return Ok(report);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::digest::{to_hex, Digest};
+    use crate::testing::{temp_dir, write};
+    use std::str::FromStr;
+
+    fn sha256 (data: &[u8]) -> String
+    {
+        let mut digest = Sha256::new();
+        digest.update(data);
+        return to_hex (&digest.finish());
+    }
+
+    static AMD64: &[u8] = b"Package: hello\n";
+    static ARM64: &[u8] = b"Package: hellp\n";
+
+    fn release (by_hash: bool) -> Release
+    {
+        let mut data = String::from("Date: Sat, 10 Feb 2024 09:12:45 UTC\n");
+        if by_hash
+        {
+            data.push_str("Acquire-By-Hash: yes\n");
+        }
+        data.push_str("SHA256:\n");
+        for (contents, size, path) in [
+            (AMD64, AMD64.len(), "main/binary-amd64/Packages"),
+            (b"xz".as_slice(), 2, "main/binary-amd64/Packages.xz"),
+            (AMD64, AMD64.len(), "main/binary-arm64/Packages"),
+            (b"zst".as_slice(), 3, "main/binary-arm64/Packages.zst"),
+            (b"".as_slice(), 0, "main/source/Sources"),
+            (b"gz".as_slice(), 2, "main/source/Sources.gz"),
+            (b"".as_slice(), 0, "main/i18n/Translation-en")
+        ]
+        {
+            data.push_str(&format!(" {} {} {}\n", sha256 (contents), size, path));
+        }
+        return Release::from_str (&data).unwrap();
+    }
+
+    #[test]
+    fn verify_indices ()
+    {
+        let root = temp_dir ("verify-indices");
+        write (&root, "main/binary-amd64/Packages", AMD64);
+        // Same size, other content
+        write (&root, "main/binary-arm64/Packages", ARM64);
+        write (&root, "main/binary-arm64/Packages.zst", b"zstd");
+
+        // Unlisted: a compression Release leaves out, and files that are not indices
+        write (&root, "main/binary-amd64/Packages.bz2", b"bz2");
+        write (&root, "main/binary-amd64/Packages.old", b"old");
+        write (&root, "main/binary-amd64/Release", b"Archive: stable\n");
+        fs::create_dir_all(root.join("main/binary-amd64/Packages.gz")).unwrap();
+
+        let report = release (false).verify_indices (&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(report.verified, ["main/binary-amd64/Packages"]);
+        // Packages.xz is not there, but Packages is; neither Sources variant is there
+        assert_eq!(report.missing, ["main/source/Sources", "main/source/Sources.gz"]);
+        assert_eq!(report.mismatched, [
+            (String::from("main/binary-arm64/Packages"), FileMismatch::Checksum(String::from("SHA256"))),
+            (String::from("main/binary-arm64/Packages.zst"), FileMismatch::Size(3, 4))
+        ]);
+        assert_eq!(report.extra, ["main/binary-amd64/Packages.bz2"]);
+        assert!(report.stale_by_hash.is_empty());
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn verify_by_hash ()
+    {
+        let root = temp_dir ("verify-by-hash");
+        write (&root, "main/binary-amd64/Packages", AMD64);
+        write (&root, &format!("main/binary-amd64/by-hash/SHA256/{}", sha256 (AMD64)), AMD64);
+        write (&root, &format!("main/binary-amd64/by-hash/SHA256/{}", sha256 (b"older")), b"older");
+        fs::create_dir_all(root.join("main/binary-amd64/by-hash/SHA256/directory")).unwrap();
+        write (&root, "main/source/Sources", b"");
+        write (&root, &format!("main/source/by-hash/SHA256/{}", sha256 (b"")), b"");
+
+        // Only found by hash, which is enough for the index to be present
+        write (&root, &format!("main/binary-arm64/by-hash/SHA256/{}", sha256 (AMD64)), AMD64);
+
+        let report = release (true).verify_indices (&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(report.verified, [
+            String::from("main/binary-amd64/Packages"),
+            format!("main/binary-amd64/by-hash/SHA256/{}", sha256 (AMD64)),
+            format!("main/binary-arm64/by-hash/SHA256/{}", sha256 (AMD64)),
+            String::from("main/source/Sources"),
+            format!("main/source/by-hash/SHA256/{}", sha256 (b""))
+        ]);
+        assert_eq!(report.missing, ["main/binary-arm64/Packages"]);
+        assert!(report.mismatched.is_empty());
+        assert!(report.extra.is_empty());
+        assert_eq!(report.stale_by_hash, [format!("main/binary-amd64/by-hash/SHA256/{}", sha256 (b"older"))]);
+
+        // Stale by-hash files alone do not fail the check
+        let report = MirrorReport { stale_by_hash: report.stale_by_hash, ..MirrorReport::default() };
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn verify_escaping_paths ()
+    {
+        let root = temp_dir ("verify-escaping-paths");
+        for path in ["../binary-amd64/Packages", "main/../../Packages.xz", "/srv/mirror/main/source/Sources"]
+        {
+            let data = format!("Date: Sat, 10 Feb 2024 09:12:45 UTC\nSHA256:\n {} 15 {}\n", sha256 (AMD64), path);
+            let release = Release::from_str (&data).unwrap();
+            assert!(matches!(release.verify_indices (&root), Err(PakigeParseError::InvalidValue)), "{}", path);
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
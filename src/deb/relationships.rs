@@ -0,0 +1,631 @@
+use crate::{PakigeParseError, VerOp};
+use super::architectures::{arch_is, is_arch_name};
+use super::{ArchRestriction, BuildProfile, DependsPackageList, PackageRef, ProvidesPackageList, SourceRef,
+            VersionRef};
+use deb_version7::DebVersion;
+use std::fmt;
+use std::str::FromStr;
+
+// https://www.debian.org/doc/debian-policy/ch-relationships.html
+// A relationship field is a comma-separated list of relations. Each relation may be a
+// pipe-separated list of alternatives, and each alternative has the form:
+//     package[:arch] [(op version)] [[arch ...]] [<profile ...> ...]
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelationError
+{
+    EmptyRelation,
+    InvalidPackageName(String),
+    InvalidArchQualifier(String),
+    UnterminatedVersion,
+    InvalidOperator(String),
+    MissingVersion,
+    InvalidVersion(String),
+    UnterminatedArchList,
+    EmptyArchList,
+    MixedArchList,
+    InvalidArchitecture(String),
+    UnterminatedProfile,
+    EmptyProfile,
+    InvalidProfile(String),
+    TrailingText(String),
+    AlternativesNotAllowed,
+    OperatorNotAllowed(String), // As written, e.g. `>` rather than the `>=` it stands for
+    VersionRequired
+}
+
+impl fmt::Display for RelationError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            RelationError::EmptyRelation => write!(f, "empty relation"),
+            RelationError::InvalidPackageName(name) => write!(f, "invalid package name '{}'", name),
+            RelationError::InvalidArchQualifier(arch) => write!(f, "invalid architecture qualifier '{}'", arch),
+            RelationError::UnterminatedVersion => write!(f, "version restriction is missing a closing ')'"),
+            RelationError::InvalidOperator(op) => write!(f, "invalid version operator '{}'", op),
+            RelationError::MissingVersion => write!(f, "version restriction has no version"),
+            RelationError::InvalidVersion(version) => write!(f, "invalid version '{}'", version),
+            RelationError::UnterminatedArchList => write!(f, "architecture restriction is missing a closing ']'"),
+            RelationError::EmptyArchList => write!(f, "empty architecture restriction list"),
+            RelationError::MixedArchList => write!(f, "architecture restriction list mixes negated and non-negated entries"),
+            RelationError::InvalidArchitecture(arch) => write!(f, "invalid architecture '{}'", arch),
+            RelationError::UnterminatedProfile => write!(f, "build profile restriction is missing a closing '>'"),
+            RelationError::EmptyProfile => write!(f, "empty build profile restriction"),
+            RelationError::InvalidProfile(profile) => write!(f, "invalid build profile '{}'", profile),
+            RelationError::TrailingText(text) => write!(f, "unexpected text '{}'", text),
+            RelationError::AlternativesNotAllowed => write!(f, "alternatives ('|') are not allowed in this field"),
+            RelationError::OperatorNotAllowed(op) => write!(f, "version operator '{}' is not allowed in this field", op),
+            RelationError::VersionRequired => write!(f, "an exact '(= version)' is required in this field")
+        }
+    }
+}
+
+impl PackageRef
+{
+    // Mirrors dpkg's debarch_is_concerned(): a positive entry includes only the architectures it
+    // names, while a negated entry includes every architecture it does not name.
+    pub fn applies_to_arch (&self, host_arch: &str) -> bool
+    {
+        if self.arch_restrictions.is_empty()
+        {
+            return true;
+        }
+
+        let mut concerned = false;
+        for restriction in &self.arch_restrictions
+        {
+            let matches = arch_is (host_arch, &restriction.architecture);
+            if restriction.negated
+            {
+                if matches
+                {
+                    return false;
+                }
+                concerned = true;
+            }
+            else if matches
+            {
+                return true;
+            }
+        }
+
+        return concerned;
+    }
+
+    pub fn applies_to_profiles (&self, active_profiles: &[&str]) -> bool
+    {
+        if self.build_profiles.is_empty()
+        {
+            return true;
+        }
+
+        return self.build_profiles.iter().any(|term| {
+            term.iter().all(|profile| active_profiles.contains(&profile.name.as_str()) != profile.negated)
+        });
+    }
+
+    pub fn applies_to (&self, host_arch: &str, active_profiles: &[&str]) -> bool
+    {
+        return self.applies_to_arch (host_arch) && self.applies_to_profiles (active_profiles);
+    }
+}
+
+impl VersionRef
+{
+    pub fn satisfied_by (&self, version: &DebVersion) -> bool
+    {
+        return match self.operation
+        {
+            VerOp::Gt => *version > self.version_string,
+            VerOp::GtEq => *version >= self.version_string,
+            VerOp::Eq => *version == self.version_string,
+            VerOp::LtEq => *version <= self.version_string,
+            VerOp::Lt => *version < self.version_string
+        };
+    }
+}
+
+impl DependsPackageList
+{
+    // Keeps only the alternatives that apply to the host architecture and active build profiles,
+    // with their restrictions stripped. Relations left with no alternatives are dropped entirely.
+    pub fn reduce (&self, host_arch: &str, active_profiles: &[&str]) -> DependsPackageList
+    {
+        let mut groups = Vec::new();
+
+        for group in &self.0
+        {
+            let alternatives: Vec<PackageRef> = group.iter()
+                .filter(|package| package.applies_to (host_arch, active_profiles))
+                .map(|package| PackageRef {
+                    arch_restrictions: Vec::new(),
+                    build_profiles: Vec::new(),
+                    ..package.clone()
+                })
+                .collect();
+
+            if !alternatives.is_empty()
+            {
+                groups.push(alternatives);
+            }
+        }
+
+        return DependsPackageList(groups);
+    }
+}
+
+// The Source field of a binary package: `name`, or `name (version)` when the source version
+// differs from the binary's, as with binNMUs (`glibc (2.36-9)` for libc6 2.36-9+b1)
+impl FromStr for SourceRef
+{
+    type Err = PakigeParseError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let data = data.trim();
+
+        let (name, version) = match data.split_once('(')
+        {
+            Some((name, rest)) =>
+            {
+                let version = rest.strip_suffix(')').ok_or(PakigeParseError::InvalidValue)?.trim();
+                (name.trim(), Some(DebVersion::from_str (version).map_err(|_| PakigeParseError::InvalidValue)?))
+            },
+            None => (data, None)
+        };
+
+        if !is_package_name (name)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+        return Ok(SourceRef { name: name.to_string(), version });
+    }
+}
+
+/* Depends, Pre-Depends, Recommends, Suggests, Enhances */
+pub fn parse_depends_list (data: &str) -> Result<DependsPackageList, RelationError>
+{
+    let mut groups = Vec::new();
+
+    for group in split_list (data, ',')
+    {
+        let mut alternatives = Vec::new();
+        for alternative in group.split('|')
+        {
+            alternatives.push(parse_package_ref (alternative)?);
+        }
+        groups.push(alternatives);
+    }
+
+    return Ok(DependsPackageList(groups));
+}
+
+/* Which version restrictions a ProvidesPackageList field accepts */
+#[derive(Clone, Copy)]
+pub enum VersionRule
+{
+    Any, // Breaks, Conflicts, Replaces
+    EqualOnly, // Provides
+    EqualRequired // Built-Using
+}
+
+/* Breaks, Conflicts, Replaces, Provides, Built-Using */
+pub fn parse_provides_list (data: &str, rule: VersionRule) -> Result<ProvidesPackageList, RelationError>
+{
+    let mut packages = Vec::new();
+
+    for item in split_list (data, ',')
+    {
+        if item.contains('|')
+        {
+            return Err(RelationError::AlternativesNotAllowed);
+        }
+
+        let package = parse_package_ref (item)?;
+
+        match (rule, &package.version)
+        {
+            (VersionRule::EqualOnly, Some(version)) | (VersionRule::EqualRequired, Some(version))
+                if version.operation != VerOp::Eq =>
+            {
+                return Err(RelationError::OperatorNotAllowed(written_operator (item)));
+            },
+            (VersionRule::EqualRequired, None) => return Err(RelationError::VersionRequired),
+            _ => ()
+        }
+
+        packages.push(package);
+    }
+
+    return Ok(ProvidesPackageList(packages));
+}
+
+/* The version operator as written in a relation, e.g. `>` for `foo (> 1)` */
+fn written_operator (data: &str) -> String
+{
+    let after = data.split_once('(').map_or("", |(_, after)| after.trim_start());
+    return after.chars().take_while(|c| "<>=".contains(*c)).collect();
+}
+
+// Splits on the separator, allowing a single trailing separator like dpkg does (`foo, bar,`)
+pub fn split_list (data: &str, separator: char) -> Vec<&str>
+{
+    let mut items: Vec<&str> = data.split(separator).collect();
+
+    if items.len() > 1 && items.last().is_some_and(|item| item.trim().is_empty())
+    {
+        items.pop();
+    }
+
+    return items;
+}
+
+pub fn parse_package_ref (data: &str) -> Result<PackageRef, RelationError>
+{
+    let mut rest = data.trim();
+
+    if rest.is_empty()
+    {
+        return Err(RelationError::EmptyRelation);
+    }
+
+    // Package name, ending at whitespace or the start of a qualifier
+    let end = rest.find(|c: char| c.is_whitespace() || ":([<".contains(c)).unwrap_or(rest.len());
+    let package = &rest[..end];
+    if !is_package_name (package)
+    {
+        return Err(RelationError::InvalidPackageName(package.to_string()));
+    }
+    rest = &rest[end..];
+
+    // Architecture qualifier: `pkg:any`, `pkg:native`, `pkg:amd64`
+    let mut architecture = None;
+    if let Some(after) = rest.strip_prefix(':')
+    {
+        let end = after.find(|c: char| c.is_whitespace() || "([<".contains(c)).unwrap_or(after.len());
+        let arch = &after[..end];
+        if !is_arch_name (arch)
+        {
+            return Err(RelationError::InvalidArchQualifier(arch.to_string()));
+        }
+        architecture = Some(arch.to_string());
+        rest = &after[end..];
+    }
+    rest = rest.trim_start();
+
+    let mut version = None;
+    if let Some(after) = rest.strip_prefix('(')
+    {
+        let close = after.find(')').ok_or(RelationError::UnterminatedVersion)?;
+        version = Some(parse_version_ref (&after[..close])?);
+        rest = after[close + 1..].trim_start();
+    }
+
+    let mut arch_restrictions = Vec::new();
+    if let Some(after) = rest.strip_prefix('[')
+    {
+        let close = after.find(']').ok_or(RelationError::UnterminatedArchList)?;
+        arch_restrictions = parse_arch_list (&after[..close])?;
+        rest = after[close + 1..].trim_start();
+    }
+
+    let mut build_profiles = Vec::new();
+    while let Some(after) = rest.strip_prefix('<')
+    {
+        let close = after.find('>').ok_or(RelationError::UnterminatedProfile)?;
+        build_profiles.push(parse_profile_term (&after[..close])?);
+        rest = after[close + 1..].trim_start();
+    }
+
+    if !rest.is_empty()
+    {
+        return Err(RelationError::TrailingText(rest.to_string()));
+    }
+
+    return Ok(PackageRef {
+        package: package.to_string(),
+        architecture,
+        version,
+        arch_restrictions,
+        build_profiles
+    });
+}
+
+fn parse_version_ref (data: &str) -> Result<VersionRef, RelationError>
+{
+    let data = data.trim();
+    if data.is_empty()
+    {
+        return Err(RelationError::MissingVersion);
+    }
+
+    let op_end = data.find(|c: char| !"<>=".contains(c)).unwrap_or(data.len());
+    let operation = match &data[..op_end]
+    {
+        "<<" => VerOp::Lt,
+        "<=" => VerOp::LtEq,
+        "=" => VerOp::Eq,
+        ">=" => VerOp::GtEq,
+        ">>" => VerOp::Gt,
+        // Deprecated forms, which mean "or equal" rather than strictly less/greater
+        "<" => VerOp::LtEq,
+        ">" => VerOp::GtEq,
+        "" => return Err(RelationError::InvalidOperator(data.split_whitespace().next().unwrap_or(data).to_string())),
+        op => return Err(RelationError::InvalidOperator(op.to_string()))
+    };
+
+    let version_string = data[op_end..].trim();
+    if version_string.is_empty()
+    {
+        return Err(RelationError::MissingVersion);
+    }
+
+    return match DebVersion::from_str (version_string)
+    {
+        Ok(version_string) => Ok(VersionRef { operation, version_string }),
+        Err(_) => Err(RelationError::InvalidVersion(version_string.to_string()))
+    };
+}
+
+fn parse_arch_list (data: &str) -> Result<Vec<ArchRestriction>, RelationError>
+{
+    let mut restrictions: Vec<ArchRestriction> = Vec::new();
+
+    for arch in data.split_whitespace()
+    {
+        let (negated, name) = match arch.strip_prefix('!')
+        {
+            Some(name) => (true, name),
+            None => (false, arch)
+        };
+
+        if !is_arch_name (name)
+        {
+            return Err(RelationError::InvalidArchitecture(arch.to_string()));
+        }
+
+        // Either every entry is negated or none is (Policy 7.1)
+        if restrictions.first().is_some_and(|first| first.negated != negated)
+        {
+            return Err(RelationError::MixedArchList);
+        }
+
+        restrictions.push(ArchRestriction { negated, architecture: name.to_string() });
+    }
+
+    if restrictions.is_empty()
+    {
+        return Err(RelationError::EmptyArchList);
+    }
+
+    return Ok(restrictions);
+}
+
+fn parse_profile_term (data: &str) -> Result<Vec<BuildProfile>, RelationError>
+{
+    let mut term = Vec::new();
+
+    for profile in data.split_whitespace()
+    {
+        let (negated, name) = match profile.strip_prefix('!')
+        {
+            Some(name) => (true, name),
+            None => (false, profile)
+        };
+
+        if !is_profile_name (name)
+        {
+            return Err(RelationError::InvalidProfile(profile.to_string()));
+        }
+
+        term.push(BuildProfile { negated, name: name.to_string() });
+    }
+
+    if term.is_empty()
+    {
+        return Err(RelationError::EmptyProfile);
+    }
+
+    return Ok(term);
+}
+
+// Package names must consist only of lower case letters, digits, plus, minus and periods.
+// They must be at least two characters long and must start with an alphanumeric character.
+pub fn is_package_name (name: &str) -> bool
+{
+    let mut chars = name.chars();
+
+    return name.len() >= 2
+        && chars.next().is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c));
+}
+
+fn is_profile_name (name: &str) -> bool
+{
+    return !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c));
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn error (data: &str) -> RelationError
+    {
+        return parse_depends_list (data).unwrap_err();
+    }
+
+    #[test]
+    fn alternatives ()
+    {
+        let list = parse_depends_list ("libc6 (>= 2.36), default-mta | mail-transport-agent, perl,").unwrap();
+        assert_eq!(list.0.len(), 3);
+        assert_eq!(list.0[1].iter().map(|package| package.package.as_str()).collect::<Vec<_>>(),
+                   ["default-mta", "mail-transport-agent"]);
+
+        let version = list.0[0][0].version.as_ref().unwrap();
+        assert_eq!(version.operation, VerOp::GtEq);
+        assert_eq!(version.version_string.to_string(), "2.36");
+    }
+
+    #[test]
+    fn arch_qualifier ()
+    {
+        let package = parse_package_ref ("python3:any (>= 3.11)").unwrap();
+        assert_eq!(package.package, "python3");
+        assert_eq!(package.architecture.as_deref(), Some("any"));
+        assert!(package.version.is_some());
+    }
+
+    #[test]
+    fn arch_list_without_space ()
+    {
+        let package = parse_package_ref ("foo[amd64]").unwrap();
+        assert_eq!(package.package, "foo");
+        assert_eq!(package.arch_restrictions, [ArchRestriction { negated: false, architecture: String::from("amd64") }]);
+
+        let package = parse_package_ref ("foo(>= 1)[amd64]<!nocheck>").unwrap();
+        assert!(package.version.is_some());
+        assert_eq!(package.build_profiles.len(), 1);
+    }
+
+    #[test]
+    fn errors ()
+    {
+        assert_eq!(error ("foo, , bar"), RelationError::EmptyRelation);
+        assert_eq!(error ("foo |"), RelationError::EmptyRelation);
+        assert_eq!(error ("Foo"), RelationError::InvalidPackageName(String::from("Foo")));
+        assert_eq!(error ("f"), RelationError::InvalidPackageName(String::from("f")));
+        assert_eq!(error ("foo:AMD64"), RelationError::InvalidArchQualifier(String::from("AMD64")));
+        assert_eq!(error ("foo (>= 1.0"), RelationError::UnterminatedVersion);
+        assert_eq!(error ("foo (=> 1.0)"), RelationError::InvalidOperator(String::from("=>")));
+        assert_eq!(error ("foo (1.0)"), RelationError::InvalidOperator(String::from("1.0")));
+        assert_eq!(error ("foo ()"), RelationError::MissingVersion);
+        assert_eq!(error ("foo (>=)"), RelationError::MissingVersion);
+        assert_eq!(error ("foo (>= 1.0 2)"), RelationError::InvalidVersion(String::from("1.0 2")));
+        assert_eq!(error ("foo [amd64"), RelationError::UnterminatedArchList);
+        assert_eq!(error ("foo []"), RelationError::EmptyArchList);
+        assert_eq!(error ("foo [AMD64]"), RelationError::InvalidArchitecture(String::from("AMD64")));
+        assert_eq!(error ("foo [amd64 !i386]"), RelationError::MixedArchList);
+        assert_eq!(error ("foo <nocheck"), RelationError::UnterminatedProfile);
+        assert_eq!(error ("foo <>"), RelationError::EmptyProfile);
+        assert_eq!(error ("foo <No-Check>"), RelationError::InvalidProfile(String::from("No-Check")));
+        assert_eq!(error ("foo bar"), RelationError::TrailingText(String::from("bar")));
+    }
+
+    #[test]
+    fn provides_versions ()
+    {
+        let list = parse_provides_list ("mail-transport-agent, libfoo-abi (= 2)", VersionRule::EqualOnly).unwrap();
+        assert_eq!(list.0.len(), 2);
+        assert_eq!(list.0[1].version.as_ref().unwrap().operation, VerOp::Eq);
+
+        assert_eq!(parse_provides_list ("foo (>= 1)", VersionRule::EqualOnly).unwrap_err(),
+                   RelationError::OperatorNotAllowed(String::from(">=")));
+        assert_eq!(parse_provides_list ("foo (<< 1)", VersionRule::EqualOnly).unwrap_err(),
+                   RelationError::OperatorNotAllowed(String::from("<<")));
+        assert_eq!(parse_provides_list ("foo | bar", VersionRule::EqualOnly).unwrap_err(), RelationError::AlternativesNotAllowed);
+    }
+
+    #[test]
+    fn legacy_operator_in_errors ()
+    {
+        // `>` is read as `>=`, but errors show what was written
+        let package = parse_package_ref ("foo (> 1)").unwrap();
+        assert_eq!(package.version.unwrap().operation, VerOp::GtEq);
+
+        let error = parse_provides_list ("foo (> 1)", VersionRule::EqualOnly).unwrap_err();
+        assert_eq!(error, RelationError::OperatorNotAllowed(String::from(">")));
+        assert_eq!(error.to_string(), "version operator '>' is not allowed in this field");
+    }
+
+    #[test]
+    fn built_using_and_breaks ()
+    {
+        assert!(parse_provides_list ("gcc-12 (= 12.2.0-14)", VersionRule::EqualRequired).is_ok());
+        assert_eq!(parse_provides_list ("gcc-12", VersionRule::EqualRequired).unwrap_err(), RelationError::VersionRequired);
+        assert_eq!(parse_provides_list ("gcc-12 (>= 12)", VersionRule::EqualRequired).unwrap_err(),
+                   RelationError::OperatorNotAllowed(String::from(">=")));
+
+        let list = parse_provides_list ("foo (<< 2), bar (>> 1), baz", VersionRule::Any).unwrap();
+        assert_eq!(list.0.len(), 3);
+    }
+
+    #[test]
+    fn arch_lists ()
+    {
+        assert_eq!(error ("foo [!amd64 i386]"), RelationError::MixedArchList);
+
+        let positive = parse_package_ref ("foo [amd64 i386]").unwrap();
+        assert!(positive.arch_restrictions.iter().all(|restriction| !restriction.negated));
+        let negated = parse_package_ref ("foo [!amd64 !i386]").unwrap();
+        assert!(negated.arch_restrictions.iter().all(|restriction| restriction.negated));
+    }
+
+    #[test]
+    fn applies_to_arch ()
+    {
+        let positive = parse_package_ref ("foo [amd64 i386]").unwrap();
+        assert!(positive.applies_to_arch ("amd64"));
+        assert!(!positive.applies_to_arch ("arm64"));
+
+        let negated = parse_package_ref ("foo [!amd64 !i386]").unwrap();
+        assert!(!negated.applies_to_arch ("i386"));
+        assert!(negated.applies_to_arch ("arm64"));
+
+        let wildcard = parse_package_ref ("foo [linux-any]").unwrap();
+        assert!(wildcard.applies_to_arch ("armhf"));
+        assert!(!wildcard.applies_to_arch ("hurd-i386"));
+
+        assert!(parse_package_ref ("foo").unwrap().applies_to_arch ("s390x"));
+    }
+
+    #[test]
+    fn applies_to_profiles ()
+    {
+        let package = parse_package_ref ("foo <!nocheck cross> <stage1>").unwrap();
+        assert!(package.applies_to_profiles (&["cross"]));
+        assert!(package.applies_to_profiles (&["stage1", "nocheck"]));
+        assert!(!package.applies_to_profiles (&["cross", "nocheck"]));
+        assert!(!package.applies_to_profiles (&[]));
+    }
+
+    #[test]
+    fn reduce ()
+    {
+        let list = parse_depends_list ("libc6, libfoo [amd64] | libbar, libsel [linux-any], check <!nocheck>").unwrap();
+
+        let reduced = list.reduce ("hurd-i386", &["nocheck"]);
+        let names: Vec<Vec<&str>> = reduced.0.iter()
+            .map(|group| group.iter().map(|package| package.package.as_str()).collect())
+            .collect();
+        assert_eq!(names, [vec!["libc6"], vec!["libbar"]]);
+
+        let reduced = list.reduce ("amd64", &[]);
+        assert_eq!(reduced.0.len(), 4);
+        assert_eq!(reduced.0[1].len(), 2);
+        assert!(reduced.0.iter().flatten().all(|package| package.arch_restrictions.is_empty() && package.build_profiles.is_empty()));
+    }
+
+    #[test]
+    fn source_refs ()
+    {
+        let source = SourceRef::from_str ("glibc").unwrap();
+        assert_eq!(source.name, "glibc");
+        assert!(source.version.is_none());
+
+        let source = SourceRef::from_str (" glibc  ( 2.36-9 ) ").unwrap();
+        assert_eq!(source.name, "glibc");
+        assert_eq!(source.version.unwrap().to_string(), "2.36-9");
+
+        let source = SourceRef::from_str ("linux-signed-amd64 (6.1.76+1)").unwrap();
+        assert_eq!(source.version.unwrap().to_string(), "6.1.76+1");
+
+        for data in ["", "glibc (2.36-9", "glibc ()", "glibc (2.36 9)", "Glibc", "glibc 2.36-9", "(2.36-9)"]
+        {
+            assert!(matches!(SourceRef::from_str (data), Err(PakigeParseError::InvalidValue)), "{}", data);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::PakigeParseError;
+use super::{str_to_table, parse_clearsigned, DateTime, Fields, FileChecksum};
+use super::setters::{set_date, set_valid_until, set_architectures, set_components, set_acquire_by_hash,
+                     set_md5sum_table, set_sha1_table, set_sha256_table, set_sha512_table};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+// https://wiki.debian.org/DebianRepository/Format#A.22Release.22_files
+// The Release file of a distribution describes the suite and lists every index file beneath
+// it (Packages, Sources, Contents, Translation, ...) with sizes and checksums. InRelease is the
+// same file, clearsigned; Release comes with a detached Release.gpg instead.
+
+#[derive(Debug, Clone)]
+pub struct Release
+{
+    pub origin: Option<String>,
+    pub label: Option<String>,
+    pub suite: Option<String>, /* Recommended */
+    pub codename: Option<String>, /* Recommended */
+    pub version: Option<String>,
+    pub date: DateTime, /* Mandatory */
+    pub valid_until: Option<DateTime>,
+    pub architectures: Vec<String>,
+    pub components: Vec<String>,
+    pub description: Option<String>,
+    pub acquire_by_hash: bool,
+    pub no_support_for_architecture_all: Option<String>, // Usually `Packages`: `all` packages are listed in each architecture's index
+    pub md5sum: Vec<FileChecksum>,
+    pub sha1: Vec<FileChecksum>,
+    pub sha256: Vec<FileChecksum>, /* Recommended */
+    pub sha512: Vec<FileChecksum>,
+    pub signature: Option<String>, // The armored OpenPGP signature, if read from an InRelease
+    pub all_fields: Fields
+}
+
+impl Release
+{
+    fn from_fields (fields: Fields, signature: Option<String>) -> Result<Self, PakigeParseError>
+    {
+        return Ok(Release {
+            origin: fields.get("origin").cloned(),
+            label: fields.get("label").cloned(),
+            suite: fields.get("suite").cloned(), /* Recommended */
+            codename: fields.get("codename").cloned(), /* Recommended */
+            version: fields.get("version").cloned(),
+            date: set_date (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            valid_until: set_valid_until (&fields)?,
+            architectures: set_architectures (&fields)?.unwrap_or_default(),
+            components: set_components (&fields)?.unwrap_or_default(),
+            description: fields.get("description").cloned(),
+            acquire_by_hash: set_acquire_by_hash (&fields)?.unwrap_or (false), // Has default value
+            no_support_for_architecture_all: fields.get("no-support-for-architecture-all").cloned(),
+            md5sum: set_md5sum_table (&fields)?.unwrap_or_default(),
+            sha1: set_sha1_table (&fields)?.unwrap_or_default(),
+            sha256: set_sha256_table (&fields)?.unwrap_or_default(), /* Recommended */
+            sha512: set_sha512_table (&fields)?.unwrap_or_default(),
+            signature,
+            all_fields: fields
+        });
+    }
+
+    /* Whether the file came wrapped in an OpenPGP signature (which is not verified here) */
+    pub fn is_signed (&self) -> bool
+    {
+        return self.signature.is_some();
+    }
+
+    /* Whether the file is still valid at `time`, that is, not past its Valid-Until */
+    pub fn is_valid_at (&self, time: &DateTime) -> bool
+    {
+        return self.valid_until.is_none_or(|valid_until| *time <= valid_until);
+    }
+
+    /* Every path listed in any of the checksum tables, each once, in file order */
+    pub fn paths (&self) -> Vec<&str>
+    {
+        let mut paths: Vec<&str> = Vec::new();
+        let mut seen = HashSet::new();
+
+        for table in [&self.sha512, &self.sha256, &self.sha1, &self.md5sum]
+        {
+            for file in table
+            {
+                if seen.insert(file.filename.as_str())
+                {
+                    paths.push(&file.filename);
+                }
+            }
+        }
+
+        return paths;
+    }
+
+    // The index files listed for a component and architecture, relative to the Release file:
+    // `main/binary-amd64/Packages`, `main/binary-amd64/Packages.xz`, ... Pass `source` as the
+    // architecture for `main/source/Sources*`.
+    pub fn index_paths (&self, component: &str, arch: &str) -> Vec<&str>
+    {
+        let directory = match arch
+        {
+            "source" => format!("{}/source/", component),
+            _ => format!("{}/binary-{}/", component, arch)
+        };
+
+        return self.paths().into_iter()
+            .filter(|path| path.strip_prefix(directory.as_str()).is_some_and(|name| !name.contains('/')))
+            .collect();
+    }
+}
+
+// Reads either a Release file or an InRelease file
+impl FromStr for Release
+{
+    type Err = PakigeParseError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let signed = parse_clearsigned (data)?;
+        let fields = str_to_table (&signed.body)?;
+        return Release::from_fields (fields, signed.signature);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static RELEASE: &str = "\
+Origin: Debian
+Label: Debian
+Suite: stable
+Version: 12.5
+Codename: bookworm
+Date: Sat, 10 Feb 2024 09:12:45 UTC
+Valid-Until: Sat, 17 Feb 2024 09:12:45 UTC
+Acquire-By-Hash: yes
+No-Support-for-Architecture-all: Packages
+Architectures: all amd64 arm64
+Components: main contrib
+Description: Debian 12.5 Released 10 February 2024
+MD5Sum:
+ 0123456789abcdef0123456789abcdef 1000 main/binary-amd64/Packages
+ 0123456789abcdef0123456789abcdef 300 main/binary-amd64/Packages.gz
+ 0123456789abcdef0123456789abcdef 200 main/binary-amd64/Packages.bz2
+SHA256:
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 1000 main/binary-amd64/Packages
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 300 main/binary-amd64/Packages.gz
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 250 main/binary-amd64/Packages.xz
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 120 main/binary-amd64/Release
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 900 main/binary-amd64-debug/Packages
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 800 main/binary-arm64/Packages.xz
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 700 main/source/Sources.xz
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 600 main/i18n/Translation-en.xz
+ 14e0ffdc8215c81da0cde40f581237ee35177ddac4f1fc7613cad3004798d25f 500 contrib/binary-amd64/Packages
+";
+
+    #[test]
+    fn release ()
+    {
+        let release = Release::from_str (RELEASE).unwrap();
+        assert!(!release.is_signed());
+        assert_eq!(release.codename.as_deref(), Some("bookworm"));
+        assert_eq!(release.architectures, ["all", "amd64", "arm64"]);
+        assert_eq!(release.components, ["main", "contrib"]);
+        assert!(release.acquire_by_hash);
+        assert_eq!(release.no_support_for_architecture_all.as_deref(), Some("Packages"));
+        assert_eq!(release.md5sum.len(), 3);
+        assert!(release.sha1.is_empty());
+        assert_eq!(release.sha256.len(), 9);
+
+        let before = DateTime::from_str ("Sat, 17 Feb 2024 10:12:45 +0100").unwrap();
+        let after = DateTime::from_str ("Sat, 17 Feb 2024 09:12:46 +0000").unwrap();
+        assert!(release.is_valid_at (&before));
+        assert!(!release.is_valid_at (&after));
+
+        // Listed in SHA256 first, then what only MD5Sum has
+        let paths = release.paths();
+        assert_eq!(paths.len(), 10);
+        assert_eq!(paths[0], "main/binary-amd64/Packages");
+        assert_eq!(paths[9], "main/binary-amd64/Packages.bz2");
+    }
+
+    #[test]
+    fn index_paths ()
+    {
+        let release = Release::from_str (RELEASE).unwrap();
+        assert_eq!(release.index_paths ("main", "amd64"), [
+            "main/binary-amd64/Packages",
+            "main/binary-amd64/Packages.gz",
+            "main/binary-amd64/Packages.xz",
+            "main/binary-amd64/Release",
+            "main/binary-amd64/Packages.bz2"
+        ]);
+        assert_eq!(release.index_paths ("main", "arm64"), ["main/binary-arm64/Packages.xz"]);
+        assert_eq!(release.index_paths ("main", "source"), ["main/source/Sources.xz"]);
+        assert_eq!(release.index_paths ("contrib", "amd64"), ["contrib/binary-amd64/Packages"]);
+        assert!(release.index_paths ("contrib", "arm64").is_empty());
+        assert!(release.index_paths ("non-free", "amd64").is_empty());
+
+        // Checksums given under another file name are not the index itself
+        let data = RELEASE.replace("main/binary-arm64/Packages.xz", "main/binary-arm64/by-hash/SHA256/14e0ffdc");
+        assert!(Release::from_str (&data).unwrap().index_paths ("main", "arm64").is_empty());
+    }
+
+    #[test]
+    fn release_errors ()
+    {
+        let error = Release::from_str (&RELEASE.replace("Date: Sat, 10 Feb 2024 09:12:45 UTC\n", "")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::MissingMandatoryField));
+
+        let error = Release::from_str (&RELEASE.replace("Acquire-By-Hash: yes", "Acquire-By-Hash: true")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+
+        let error = Release::from_str (&RELEASE.replace(" 300 main/binary-amd64/Packages.gz", " 3OO main/binary-amd64/Packages.gz")).unwrap_err();
+        assert!(matches!(error, PakigeParseError::InvalidValue));
+    }
+}
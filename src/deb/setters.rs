@@ -1,14 +1,24 @@
-use std::{collections::HashMap, ops::Mul};
 use crate::PakigeParseError;
-use super::{BinaryDeb, DependsPackageList, Fields, MultiArch, PackageRef, ProvidesPackageList};
+use super::architectures::{is_arch_name, is_wildcard};
+use super::{ChangesFile, DateTime, Description, DependsPackageList, Fields, FileChecksum, MultiArch,
+            PackageListEntry, ProvidesPackageList, SourceRef};
+use super::relationships::{is_package_name, parse_depends_list, parse_provides_list, split_list, VersionRule};
 use regex::Regex;
 use deb_version7::DebVersion;
 use std::str::FromStr;
+use std::sync::OnceLock;
 
 
 static PACKAGENAME_RULES: &str = r"[[:lower:][:digit:]][[:lower:][:digit:][+-.]]+";
 //static ARCHITECTURE_RULES: &str = r"";
 
+// Compiled once, since the setters run for every stanza of large index files
+fn package_name_rules () -> &'static Regex
+{
+    static RULES: OnceLock<Regex> = OnceLock::new();
+    return RULES.get_or_init(|| Regex::new(PACKAGENAME_RULES).unwrap());
+}
+
 
 pub fn set_package (fields: &Fields) -> Result<Option<String>, PakigeParseError>
 {
@@ -20,7 +30,7 @@ pub fn set_package (fields: &Fields) -> Result<Option<String>, PakigeParseError>
         None => return Ok(None)
     };
 
-    let name_rules = Regex::new(PACKAGENAME_RULES).unwrap();
+    let name_rules = package_name_rules();
     if name_rules.is_match(value)
     {
         return Ok(Some(value.clone()));
@@ -28,22 +38,28 @@ pub fn set_package (fields: &Fields) -> Result<Option<String>, PakigeParseError>
     return Err(PakigeParseError::InvalidValue);
 }
 
-pub fn set_source (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+/* BinaryDeb: `name` or `name (version)` */
+pub fn set_source (fields: &Fields) -> Result<Option<SourceRef>, PakigeParseError>
 {
     let key = "source";
 
     let value = match fields.get(key)
     {
-        Some(value) => value.clone(),
+        Some(value) => value,
         None => return Ok(None)
     };
+    return Ok(Some(SourceRef::from_str (value)?));
+}
 
-    let name_rules = Regex::new(PACKAGENAME_RULES).unwrap();
-    if name_rules.is_match(&value)
+/* SourceParagraph, Dsc: a source package names itself without a version */
+pub fn set_source_name (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    return match set_source (fields)?
     {
-        return Ok(Some(value));
-    }
-    return Err(PakigeParseError::InvalidValue);
+        Some(SourceRef { name, version: None }) => Ok(Some(name)),
+        Some(_) => Err(PakigeParseError::InvalidValue),
+        None => Ok(None)
+    };
 }
 
 //TODO: create structs, validate syntax of version string
@@ -88,7 +104,9 @@ pub fn set_priority (fields: &Fields) -> Result<Option<String>, PakigeParseError
     return Ok(Some(value));
 }
 
-// TODO: reflect the official list of architectures, or at least syntax
+// Binary packages name a single concrete architecture, or `all`. Only the spelling is checked,
+// so packages for architectures missing from the tables still parse; BinaryDeb::arch() gives
+// the typed architecture when it is known.
 pub fn set_architecture (fields: &Fields) -> Result<Option<String>, PakigeParseError>
 {
     let key = "architecture";
@@ -98,6 +116,11 @@ pub fn set_architecture (fields: &Fields) -> Result<Option<String>, PakigeParseE
         Some(value) => value.clone(),
         None => return Ok(None)
     };
+
+    if !is_arch_name (&value) || is_wildcard (&value)
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
     return Ok(Some(value))
 }
 
@@ -124,63 +147,104 @@ pub fn set_essential (fields: &Fields) -> Result<Option<bool>, PakigeParseError>
 pub fn set_depends (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
 {
     let key = "depends";
-    return Ok(None);
+    return set_depends_package_list (fields, key);
 }
 
 /* DependsPackageList */
 pub fn set_recommends (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
 {
     let key = "recommends";
-    return Ok(None);
+    return set_depends_package_list (fields, key);
 }
 
 /* DependsPackageList */
 pub fn set_suggests (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
 {
     let key = "suggests";
-    return Ok(None);
+    return set_depends_package_list (fields, key);
 }
 
 /* DependsPackageList */
 pub fn set_enhances (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
 {
     let key = "enhances";
-    return Ok(None);
+    return set_depends_package_list (fields, key);
 }
 
 /* DependsPackageList */
 pub fn set_pre_depends (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
 {
     let key = "pre-depends";
-    return Ok(None);
+    return set_depends_package_list (fields, key);
+}
+
+fn set_depends_package_list (fields: &Fields, key: &str) -> Result<Option<DependsPackageList>, PakigeParseError>
+{
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    // An empty relationship field (e.g. after substitution variables expand to nothing) has no relations
+    if value.trim().is_empty()
+    {
+        return Ok(None);
+    }
+
+    return match parse_depends_list (value)
+    {
+        Ok(list) => Ok(Some(list)),
+        Err(e) => Err(PakigeParseError::InvalidRelationship(String::from(key), e))
+    };
 }
 
 /* ProvidesPackageList */
 pub fn set_breaks (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
 {
     let key = "breaks";
-    return Ok(None);
+    return set_provides_package_list (fields, key, VersionRule::Any);
 }
 
 /* ProvidesPackageList */
 pub fn set_conflicts (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
 {
     let key = "conflicts";
-    return Ok(None);
+    return set_provides_package_list (fields, key, VersionRule::Any);
 }
 
 /* ProvidesPackageList */
 pub fn set_provides (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
 {
     let key = "provides";
-    return Ok(None);
+    return set_provides_package_list (fields, key, VersionRule::EqualOnly);
 }
 
 /* ProvidesPackageList */
 pub fn set_replaces (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
 {
     let key = "replaces";
-    return Ok(None);
+    return set_provides_package_list (fields, key, VersionRule::Any);
+}
+
+fn set_provides_package_list (fields: &Fields, key: &str, rule: VersionRule) -> Result<Option<ProvidesPackageList>, PakigeParseError>
+{
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    if value.trim().is_empty()
+    {
+        return Ok(None);
+    }
+
+    return match parse_provides_list (value, rule)
+    {
+        Ok(list) => Ok(Some(list)),
+        Err(e) => Err(PakigeParseError::InvalidRelationship(String::from(key), e))
+    };
 }
 
 pub fn set_installed_size (fields: &Fields) -> Result<Option<u64>, PakigeParseError>
@@ -196,7 +260,7 @@ pub fn set_installed_size (fields: &Fields) -> Result<Option<u64>, PakigeParseEr
     return match value.parse::<u64>()
     {
         Ok(size) => Ok(Some(size)),
-        Err(e) => Err(PakigeParseError::InvalidValue)
+        Err(_) => Err(PakigeParseError::InvalidValue)
     };
 }
 
@@ -213,7 +277,7 @@ pub fn set_maintainer (fields: &Fields) -> Result<Option<String>, PakigeParseErr
     return Ok(Some(value));
 }
 
-pub fn set_description (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+pub fn set_description (fields: &Fields) -> Result<Option<Description>, PakigeParseError>
 {
     let key = "description";
 
@@ -222,7 +286,7 @@ pub fn set_description (fields: &Fields) -> Result<Option<String>, PakigeParseEr
         Some(value) => value,
         None => return Ok(None)
     };
-    return Ok(Some(value.clone()));
+    return Ok(Some(Description::from_str (value)?));
 }
 
 pub fn set_homepage (fields: &Fields) -> Result<Option<String>, PakigeParseError>
@@ -241,7 +305,7 @@ pub fn set_homepage (fields: &Fields) -> Result<Option<String>, PakigeParseError
 pub fn set_built_using (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
 {
     let key = "built-using";
-    return Ok(None);
+    return set_provides_package_list (fields, key, VersionRule::EqualRequired);
 }
 
 pub fn set_multi_arch (fields: &Fields) -> Result<Option<MultiArch>, PakigeParseError>
@@ -265,9 +329,757 @@ pub fn set_multi_arch (fields: &Fields) -> Result<Option<MultiArch>, PakigeParse
     };
 }
 
-// fn parse_packageref (stringdata: &str) -> PackageRef
-// {
-//     let pref_string = Regex::new(r"^[[:space::]]*([[:lowercase:][:digit]][[:lowercase:][:digit:][+-.]]+)[:([[:lower:][-]])]$").unwrap();
-// }
 
 
+
+/* BinaryIndexFields */
+pub fn set_filename (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "filename";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(value.clone()));
+}
+
+/* BinaryIndexFields */
+pub fn set_size (fields: &Fields) -> Result<Option<u64>, PakigeParseError>
+{
+    let key = "size";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    return match value.parse::<u64>()
+    {
+        Ok(size) => Ok(Some(size)),
+        Err(_) => Err(PakigeParseError::InvalidValue)
+    };
+}
+
+/* BinaryIndexFields */
+pub fn set_md5sum (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "md5sum";
+    return set_checksum (fields, key, 32);
+}
+
+/* BinaryIndexFields */
+pub fn set_sha1 (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "sha1";
+    return set_checksum (fields, key, 40);
+}
+
+/* BinaryIndexFields */
+pub fn set_sha256 (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "sha256";
+    return set_checksum (fields, key, 64);
+}
+
+/* BinaryIndexFields */
+pub fn set_sha512 (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "sha512";
+    return set_checksum (fields, key, 128);
+}
+
+/* BinaryIndexFields */
+pub fn set_description_md5 (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "description-md5";
+    return set_checksum (fields, key, 32);
+}
+
+// Checksums are hex digests of a fixed length
+fn set_checksum (fields: &Fields, key: &str, length: usize) -> Result<Option<String>, PakigeParseError>
+{
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    return Ok(Some(parse_checksum (value, length)?));
+}
+
+/* SourceParagraph: DependsPackageList */
+pub fn set_build_depends (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
+{
+    let key = "build-depends";
+    return set_depends_package_list (fields, key);
+}
+
+/* SourceParagraph: DependsPackageList */
+pub fn set_build_depends_indep (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
+{
+    let key = "build-depends-indep";
+    return set_depends_package_list (fields, key);
+}
+
+/* SourceParagraph: DependsPackageList */
+pub fn set_build_depends_arch (fields: &Fields) -> Result<Option<DependsPackageList>, PakigeParseError>
+{
+    let key = "build-depends-arch";
+    return set_depends_package_list (fields, key);
+}
+
+/* SourceParagraph: ProvidesPackageList */
+pub fn set_build_conflicts (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
+{
+    let key = "build-conflicts";
+    return set_provides_package_list (fields, key, VersionRule::Any);
+}
+
+/* SourceParagraph: ProvidesPackageList */
+pub fn set_build_conflicts_indep (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
+{
+    let key = "build-conflicts-indep";
+    return set_provides_package_list (fields, key, VersionRule::Any);
+}
+
+/* SourceParagraph: ProvidesPackageList */
+pub fn set_build_conflicts_arch (fields: &Fields) -> Result<Option<ProvidesPackageList>, PakigeParseError>
+{
+    let key = "build-conflicts-arch";
+    return set_provides_package_list (fields, key, VersionRule::Any);
+}
+
+/* SourceParagraph */
+pub fn set_standards_version (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "standards-version";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    // Normally 3 or 4 dot-separated numbers (4.6.2, 3.9.8.0), but the archive also has values
+    // like 3.9, so anywhere from 2 to 4 numbers are accepted
+    let value = value.trim();
+    if value.is_empty()
+    {
+        return Ok(None);
+    }
+    let parts: Vec<&str> = value.split('.').collect();
+    let valid = (2..=4).contains(&parts.len())
+        && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if !valid
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(Some(value.to_string()));
+}
+
+/* SourceParagraph */
+pub fn set_rules_requires_root (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "rules-requires-root";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    // Either `no`, `binary-targets`, or a space-separated list of `namespace/keyword` entries
+    let value = value.trim();
+    let valid = value == "no"
+        || value == "binary-targets"
+        || value.split_whitespace().all(|keyword| keyword.contains('/'));
+    if !valid || value.is_empty()
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(Some(value.to_string()));
+}
+
+/* SourceParagraph: comma-separated list */
+pub fn set_testsuite (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "testsuite";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(split_list (value, ',').iter().map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect()));
+}
+
+/* SourceParagraph: comma-separated list */
+pub fn set_uploaders (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "uploaders";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(split_list (value, ',').iter().map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect()));
+}
+
+/* SourceParagraph: every Vcs-* field, as (kind, value), e.g. ("Git", "https://...") */
+pub fn set_vcs (fields: &Fields) -> Result<Vec<(String, String)>, PakigeParseError>
+{
+    let key = "vcs-";
+
+    let mut vcs = Vec::new();
+    for (name, value) in fields.iter()
+    {
+        if name.len() > key.len() && name[..key.len()].eq_ignore_ascii_case(key)
+        {
+            vcs.push((name[key.len()..].to_string(), value.to_string()));
+        }
+    }
+    return Ok(vcs);
+}
+
+/* BinaryTemplate: space-separated architectures, wildcards, `any` or `all` */
+pub fn set_architecture_list (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "architecture";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut architectures = Vec::new();
+    for arch in value.split_whitespace()
+    {
+        if !is_arch_name (arch)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+        architectures.push(arch.to_string());
+    }
+
+    if architectures.is_empty()
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(Some(architectures));
+}
+
+/* Dsc */
+pub fn set_format (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "format";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    // A version number, with an optional variant: `1.0`, `3.0 (quilt)`, `3.0 (native)`
+    let (version, variant) = match value.split_once(' ')
+    {
+        Some((version, variant)) => (version, Some(variant)),
+        None => (value.as_str(), None)
+    };
+    let valid_version = version.split_once('.').is_some_and(|(major, minor)|
+        !major.is_empty() && !minor.is_empty() && major.chars().chain(minor.chars()).all(|c| c.is_ascii_digit()));
+    let valid_variant = variant.is_none_or(|variant|
+        variant.len() > 2 && variant.starts_with('(') && variant.ends_with(')')
+        && variant[1..variant.len() - 1].chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+
+    if !valid_version || !valid_variant
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(Some(value.clone()));
+}
+
+/* Dsc: comma-separated list of binary package names */
+pub fn set_binary_list (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "binary";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut packages = Vec::new();
+    for package in split_list (value, ',')
+    {
+        let package = package.trim();
+        if !is_package_name (package)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+        packages.push(package.to_string());
+    }
+    return Ok(Some(packages));
+}
+
+/* Dsc: one `package type section priority [key=value ...]` line per binary package */
+pub fn set_package_list (fields: &Fields) -> Result<Option<Vec<PackageListEntry>>, PakigeParseError>
+{
+    let key = "package-list";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut entries = Vec::new();
+    // The first line of the field is empty
+    for line in value.lines().filter(|line| !line.trim().is_empty())
+    {
+        let mut columns = line.split_whitespace();
+        let (package, package_type, section, priority) = match (columns.next(), columns.next(), columns.next(), columns.next())
+        {
+            (Some(package), Some(package_type), Some(section), Some(priority)) => (package, package_type, section, priority),
+            _ => return Err(PakigeParseError::InvalidValue)
+        };
+        if !is_package_name (package)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+
+        let mut entry = PackageListEntry {
+            package: package.to_string(),
+            package_type: package_type.to_string(),
+            section: section.to_string(),
+            priority: priority.to_string(),
+            architecture: Vec::new(),
+            extra: Vec::new()
+        };
+        for column in columns
+        {
+            match column.split_once('=')
+            {
+                Some(("arch", architectures)) => entry.architecture = architectures.split(',').map(String::from).collect(),
+                Some((name, value)) => entry.extra.push((name.to_string(), value.to_string())),
+                None => return Err(PakigeParseError::InvalidValue)
+            }
+        }
+        entries.push(entry);
+    }
+    return Ok(Some(entries));
+}
+
+/* Dsc: `md5 size filename` lines */
+pub fn set_files (fields: &Fields) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let key = "files";
+    return set_checksum_table (fields, key, 32);
+}
+
+/* Dsc: `sha1 size filename` lines */
+pub fn set_checksums_sha1 (fields: &Fields) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let key = "checksums-sha1";
+    return set_checksum_table (fields, key, 40);
+}
+
+/* Dsc: `sha256 size filename` lines */
+pub fn set_checksums_sha256 (fields: &Fields) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let key = "checksums-sha256";
+    return set_checksum_table (fields, key, 64);
+}
+
+// A multi-line field with one `checksum size filename` line per file, the first line being empty
+fn set_checksum_table (fields: &Fields, key: &str, length: usize) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut files: Vec<FileChecksum> = Vec::new();
+    for line in value.lines().filter(|line| !line.trim().is_empty())
+    {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let file = match columns.as_slice()
+        {
+            [checksum, size, filename] => FileChecksum {
+                checksum: parse_checksum (checksum, length)?,
+                size: size.parse::<u64>().map_err(|_| PakigeParseError::InvalidValue)?,
+                filename: filename.to_string()
+            },
+            _ => return Err(PakigeParseError::InvalidValue)
+        };
+
+        if files.iter().any(|other| other.filename == file.filename)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+        files.push(file);
+    }
+    return Ok(Some(files));
+}
+
+fn parse_checksum (value: &str, length: usize) -> Result<String, PakigeParseError>
+{
+    if value.len() != length || !value.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(value.to_lowercase());
+}
+
+/* SourceIndexFields */
+pub fn set_directory (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "directory";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(value.clone()));
+}
+
+/* SourceIndexFields */
+pub fn set_extra_source_only (fields: &Fields) -> Result<Option<bool>, PakigeParseError>
+{
+    let key = "extra-source-only";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    return match value.as_str()
+    {
+        "yes" => Ok(Some(true)),
+        "no" => Ok(Some(false)),
+        _ => Err(PakigeParseError::InvalidValue)
+    };
+}
+
+/* Changes: RFC 2822 date */
+pub fn set_date (fields: &Fields) -> Result<Option<DateTime>, PakigeParseError>
+{
+    let key = "date";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(DateTime::from_str (value)?));
+}
+
+/* Changes: `source` and the architectures of the uploaded binaries */
+pub fn set_upload_architectures (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "architecture";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut architectures = Vec::new();
+    for arch in value.split_whitespace()
+    {
+        if !is_arch_name (arch)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+        architectures.push(arch.to_string());
+    }
+
+    if architectures.is_empty()
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(Some(architectures));
+}
+
+/* Changes: space-separated list of target suites */
+pub fn set_distribution (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "distribution";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let distributions: Vec<String> = value.split_whitespace().map(String::from).collect();
+    if distributions.is_empty()
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(Some(distributions));
+}
+
+/* Changes: low, medium, high, emergency or critical, optionally followed by a comment */
+pub fn set_urgency (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "urgency";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let urgency = value.split_whitespace().next().unwrap_or("");
+    if urgency.is_empty() || !urgency.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return Err(PakigeParseError::InvalidValue);
+    }
+    return Ok(Some(value.clone()));
+}
+
+pub fn set_changed_by (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "changed-by";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value.clone(),
+        None => return Ok(None)
+    };
+    return Ok(Some(value));
+}
+
+/* Changes: space-separated bug numbers */
+pub fn set_closes (fields: &Fields) -> Result<Option<Vec<u32>>, PakigeParseError>
+{
+    let key = "closes";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut bugs = Vec::new();
+    for bug in value.split_whitespace()
+    {
+        bugs.push(bug.parse::<u32>().map_err(|_| PakigeParseError::InvalidValue)?);
+    }
+    return Ok(Some(bugs));
+}
+
+// Changes: the changelog entries, with the continuation lines unfolded: their leading space is
+// removed and ` .` lines become empty lines.
+pub fn set_changes (fields: &Fields) -> Result<Option<String>, PakigeParseError>
+{
+    let key = "changes";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut lines = Vec::new();
+    // The first line of the field is usually empty
+    for line in value.lines().skip_while(|line| line.trim().is_empty())
+    {
+        let line = line.strip_prefix([' ', '\t']).unwrap_or(line);
+        lines.push(if line.trim_end() == "." { "" } else { line });
+    }
+    return Ok(Some(lines.join("\n")));
+}
+
+/* Changes: `md5 size section priority filename` lines */
+pub fn set_changes_files (fields: &Fields) -> Result<Option<Vec<ChangesFile>>, PakigeParseError>
+{
+    let key = "files";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let mut files: Vec<ChangesFile> = Vec::new();
+    for line in value.lines().filter(|line| !line.trim().is_empty())
+    {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let file = match columns.as_slice()
+        {
+            [md5sum, size, section, priority, filename] => ChangesFile {
+                md5sum: parse_checksum (md5sum, 32)?,
+                size: size.parse::<u64>().map_err(|_| PakigeParseError::InvalidValue)?,
+                section: section.to_string(),
+                priority: priority.to_string(),
+                filename: filename.to_string()
+            },
+            _ => return Err(PakigeParseError::InvalidValue)
+        };
+
+        if files.iter().any(|other| other.filename == file.filename)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+        files.push(file);
+    }
+    return Ok(Some(files));
+}
+
+/* Release */
+pub fn set_valid_until (fields: &Fields) -> Result<Option<DateTime>, PakigeParseError>
+{
+    let key = "valid-until";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(DateTime::from_str (value)?));
+}
+
+/* Release: space-separated list */
+pub fn set_architectures (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "architectures";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(value.split_whitespace().map(String::from).collect()));
+}
+
+/* Release: space-separated list */
+pub fn set_components (fields: &Fields) -> Result<Option<Vec<String>>, PakigeParseError>
+{
+    let key = "components";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+    return Ok(Some(value.split_whitespace().map(String::from).collect()));
+}
+
+/* Release */
+pub fn set_acquire_by_hash (fields: &Fields) -> Result<Option<bool>, PakigeParseError>
+{
+    let key = "acquire-by-hash";
+
+    let value = match fields.get(key)
+    {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    return match value.as_str()
+    {
+        "yes" => Ok(Some(true)),
+        "no" => Ok(Some(false)),
+        _ => Err(PakigeParseError::InvalidValue)
+    };
+}
+
+/* Release: `md5 size path` lines */
+pub fn set_md5sum_table (fields: &Fields) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let key = "md5sum";
+    return set_checksum_table (fields, key, 32);
+}
+
+/* Release: `sha1 size path` lines */
+pub fn set_sha1_table (fields: &Fields) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let key = "sha1";
+    return set_checksum_table (fields, key, 40);
+}
+
+/* Release: `sha256 size path` lines */
+pub fn set_sha256_table (fields: &Fields) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let key = "sha256";
+    return set_checksum_table (fields, key, 64);
+}
+
+/* Release: `sha512 size path` lines */
+pub fn set_sha512_table (fields: &Fields) -> Result<Option<Vec<FileChecksum>>, PakigeParseError>
+{
+    let key = "sha512";
+    return set_checksum_table (fields, key, 128);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::deb::str_to_table;
+
+    #[test]
+    fn architecture ()
+    {
+        let fields = str_to_table ("Architecture: arc").unwrap();
+        assert_eq!(set_architecture (&fields).unwrap().as_deref(), Some("arc"));
+
+        // Not in dpkg's tables, but a valid name
+        let fields = str_to_table ("Architecture: newarch64").unwrap();
+        assert_eq!(set_architecture (&fields).unwrap().as_deref(), Some("newarch64"));
+
+        for value in ["amd64 i386", "any", "linux-any", "any-amd64", "-"]
+        {
+            let fields = str_to_table (&format!("Architecture: {}", value)).unwrap();
+            assert!(set_architecture (&fields).is_err(), "{}", value);
+        }
+
+        let fields = str_to_table ("Architecture: linux-any newarch64 all").unwrap();
+        assert_eq!(set_architecture_list (&fields).unwrap().unwrap(), ["linux-any", "newarch64", "all"]);
+    }
+
+    #[test]
+    fn standards_version ()
+    {
+        let version = |value: &str| set_standards_version (&str_to_table (&format!("Source: foo\nStandards-Version: {}", value)).unwrap());
+
+        assert_eq!(version ("4.6.2").unwrap().as_deref(), Some("4.6.2"));
+        assert_eq!(version ("3.9.8.0").unwrap().as_deref(), Some("3.9.8.0"));
+        assert_eq!(version ("3.9").unwrap().as_deref(), Some("3.9"));
+        assert_eq!(version ("4.6.2 \t").unwrap().as_deref(), Some("4.6.2"));
+        assert_eq!(version ("").unwrap(), None);
+        assert!(version ("4.6.2a").is_err());
+        assert!(version ("4.6 2").is_err());
+        for value in [".", "4.", "4..6", "...", "4", "4.6.1.0.1"]
+        {
+            assert!(version (value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rules_requires_root ()
+    {
+        let value = |value: &str| {
+            let mut fields = Fields::new();
+            fields.insert(String::from("Rules-Requires-Root"), value.to_string());
+            return set_rules_requires_root (&fields);
+        };
+
+        assert_eq!(value ("no ").unwrap().as_deref(), Some("no"));
+        assert_eq!(value (" binary-targets").unwrap().as_deref(), Some("binary-targets"));
+        assert_eq!(value ("dpkg/target-subcommand  my-tool/install ").unwrap().as_deref(), Some("dpkg/target-subcommand  my-tool/install"));
+        assert!(value ("yes").is_err());
+        assert!(value (" ").is_err());
+    }
+}
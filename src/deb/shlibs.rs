@@ -0,0 +1,150 @@
+use crate::PakigeParseError;
+use super::{DependsPackageList, StanzaError};
+use super::relationships::parse_depends_list;
+use std::str::FromStr;
+
+// https://www.debian.org/doc/debian-policy/ch-sharedlibs.html#the-shlibs-file-format
+// One line per shared library: `[type:] library-name soname-version dependencies`, where a
+// SONAME of libfoo.so.1 gives `libfoo 1` and libfoo-1.2.so gives `libfoo 1.2`. The optional type
+// (e.g. `udeb:`) restricts the line to packages of that type.
+
+#[derive(Debug, Clone)]
+pub struct ShlibsEntry
+{
+    pub package_type: Option<String>,
+    pub library: String,
+    pub version: String,
+    pub dependencies: Option<DependsPackageList>
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Shlibs
+{
+    pub entries: Vec<ShlibsEntry>
+}
+
+impl Shlibs
+{
+    // The line for a library, preferring one for `package_type` (e.g. `udeb`) over an untyped
+    // one, as dpkg-shlibdeps does
+    pub fn lookup (&self, library: &str, version: &str, package_type: Option<&str>) -> Option<&ShlibsEntry>
+    {
+        let mut matching = self.entries.iter().filter(|entry| entry.library == library && entry.version == version);
+        if package_type.is_some()
+        {
+            if let Some(entry) = matching.clone().find(|entry| entry.package_type.as_deref() == package_type)
+            {
+                return Some(entry);
+            }
+        }
+        return matching.find(|entry| entry.package_type.is_none());
+    }
+}
+
+// Comments (lines starting with `#`) and blank lines are ignored.
+impl FromStr for Shlibs
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut entries = Vec::new();
+
+        for (index, line) in data.lines().enumerate()
+        {
+            let error = |error| StanzaError { line: index + 1, error };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#')
+            {
+                continue;
+            }
+
+            let mut rest = line;
+            let mut package_type = None;
+            let (first, after) = rest.split_once([' ', '\t']).ok_or(error(PakigeParseError::InvalidFormat))?;
+            if let Some(name) = first.strip_suffix(':')
+            {
+                package_type = Some(name.to_string());
+                rest = after.trim_start();
+            }
+
+            let (library, rest) = rest.split_once([' ', '\t']).ok_or(error(PakigeParseError::InvalidFormat))?;
+            let rest = rest.trim_start();
+            let (version, dependencies) = rest.split_once([' ', '\t']).unwrap_or((rest, ""));
+            let dependencies = dependencies.trim();
+
+            let dependencies = match dependencies.is_empty()
+            {
+                true => None,
+                false => Some(parse_depends_list (dependencies)
+                    .map_err(|relation| error(PakigeParseError::InvalidRelationship(String::from("shlibs"), relation)))?)
+            };
+
+            entries.push(ShlibsEntry {
+                package_type,
+                library: library.to_string(),
+                version: version.to_string(),
+                dependencies
+            });
+        }
+
+        return Ok(Shlibs { entries });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static SHLIBS: &str = "\
+# Generated by dh_makeshlibs
+libhello 1 libhello1 (>= 1.2)
+libhello-extra 1.2 libhello-extra1.2 | libhello-all
+udeb: libhello 1 libhello1-udeb (>= 1.2)
+libempty 0
+";
+
+    #[test]
+    fn shlibs ()
+    {
+        let shlibs = Shlibs::from_str (SHLIBS).unwrap();
+        assert_eq!(shlibs.entries.len(), 4);
+
+        let entry = shlibs.lookup("libhello", "1", None).unwrap();
+        assert_eq!(entry.package_type, None);
+        let dependencies = entry.dependencies.as_ref().unwrap();
+        assert_eq!(dependencies.0.len(), 1);
+        assert_eq!(dependencies.0[0][0].package, "libhello1");
+        assert_eq!(dependencies.0[0][0].version.as_ref().unwrap().version_string.to_string(), "1.2");
+
+        let entry = shlibs.lookup("libhello", "1", Some("udeb")).unwrap();
+        assert_eq!(entry.package_type.as_deref(), Some("udeb"));
+        assert_eq!(entry.dependencies.as_ref().unwrap().0[0][0].package, "libhello1-udeb");
+
+        // No line for the type, so the untyped one is taken
+        let entry = shlibs.lookup("libhello-extra", "1.2", Some("udeb")).unwrap();
+        let alternatives: Vec<&str> = entry.dependencies.as_ref().unwrap().0[0].iter().map(|package| package.package.as_str()).collect();
+        assert_eq!(alternatives, ["libhello-extra1.2", "libhello-all"]);
+
+        assert!(shlibs.lookup("libempty", "0", None).unwrap().dependencies.is_none());
+        assert!(shlibs.lookup("libhello", "2", None).is_none());
+        assert!(shlibs.lookup("libhello-extra", "1", None).is_none());
+    }
+
+    #[test]
+    fn shlibs_errors ()
+    {
+        let error = Shlibs::from_str ("libhello 1 libhello1\nlibhello\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, PakigeParseError::InvalidFormat));
+
+        let error = Shlibs::from_str ("udeb: libhello\n").unwrap_err();
+        assert_eq!(error.line, 1);
+        assert!(matches!(error.error, PakigeParseError::InvalidFormat));
+
+        let error = Shlibs::from_str ("\nlibhello 1 libhello1 (>= 1.2\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert!(matches!(error.error, PakigeParseError::InvalidRelationship(field, _) if field == "shlibs"));
+    }
+}
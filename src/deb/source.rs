@@ -0,0 +1,322 @@
+use crate::PakigeParseError;
+use super::{Description, DependsPackageList, Fields, MultiArch, ParseMode, ProvidesPackageList, StanzaError};
+use super::deb822::StanzaReader;
+use super::relationships::{parse_depends_list, split_list};
+use super::setters::{set_source_name, set_section, set_architecture_list, set_essential, set_maintainer,
+                     set_description, set_homepage, set_multi_arch, set_package, set_uploaders,
+                     set_build_depends, set_build_depends_indep, set_build_depends_arch,
+                     set_build_conflicts, set_build_conflicts_indep, set_build_conflicts_arch,
+                     set_standards_version, set_vcs, set_rules_requires_root, set_testsuite};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// https://www.debian.org/doc/debian-policy/ch-controlfields.html#debian-source-package-template-control-files-debian-control
+// debian/control holds one source paragraph, then one paragraph per binary package built from
+// it. Binary paragraphs are templates: dpkg-gencontrol fills in fields such as Version, and
+// expands substitution variables like ${shlibs:Depends} from debian/substvars.
+
+#[derive(Debug, Clone)]
+pub struct SourceParagraph
+{
+    pub source: String,
+    pub maintainer: String,
+    pub uploaders: Vec<String>,
+    pub section: Option<String>,
+    pub priority: Option<String>,
+    pub homepage: Option<String>,
+    pub standards_version: Option<String>,
+    pub build_depends: Option<DependsPackageList>,
+    pub build_depends_indep: Option<DependsPackageList>,
+    pub build_depends_arch: Option<DependsPackageList>,
+    pub build_conflicts: Option<ProvidesPackageList>,
+    pub build_conflicts_indep: Option<ProvidesPackageList>,
+    pub build_conflicts_arch: Option<ProvidesPackageList>,
+    pub vcs: Vec<(String, String)>, // (kind, value) for every Vcs-* field, e.g. ("Git", url)
+    pub rules_requires_root: Option<String>,
+    pub testsuite: Vec<String>,
+    pub all_fields: Fields
+}
+
+#[derive(Debug, Clone)]
+pub struct BinaryTemplate
+{
+    pub package: String,
+    pub architecture: Vec<String>, // Architectures, wildcards such as `any` or `linux-any`, or `all`
+    pub section: Option<String>, // Inherited from the source paragraph if unset
+    pub priority: Option<String>, // Inherited from the source paragraph if unset
+    pub essential: bool,
+    pub multi_arch: MultiArch,
+    pub description: Description,
+    pub all_fields: Fields // Relationship fields are kept here, as they may hold substvars
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceControl
+{
+    pub source: SourceParagraph,
+    pub binaries: Vec<BinaryTemplate>
+}
+
+impl SourceParagraph
+{
+    fn from_fields (fields: Fields) -> Result<Self, PakigeParseError>
+    {
+        return Ok(SourceParagraph {
+            source: set_source_name (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            maintainer: set_maintainer (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            uploaders: set_uploaders (&fields)?.unwrap_or_default(),
+            section: fields.get("section").cloned(), /* Recommended */
+            priority: fields.get("priority").cloned(), /* Recommended */
+            homepage: set_homepage (&fields)?,
+            standards_version: set_standards_version (&fields)?, /* Recommended */
+            build_depends: set_build_depends (&fields)?,
+            build_depends_indep: set_build_depends_indep (&fields)?,
+            build_depends_arch: set_build_depends_arch (&fields)?,
+            build_conflicts: set_build_conflicts (&fields)?,
+            build_conflicts_indep: set_build_conflicts_indep (&fields)?,
+            build_conflicts_arch: set_build_conflicts_arch (&fields)?,
+            vcs: set_vcs (&fields)?,
+            rules_requires_root: set_rules_requires_root (&fields)?,
+            testsuite: set_testsuite (&fields)?.unwrap_or_default(),
+            all_fields: fields
+        });
+    }
+}
+
+impl BinaryTemplate
+{
+    fn from_fields (fields: Fields) -> Result<Self, PakigeParseError>
+    {
+        return Ok(BinaryTemplate {
+            package: set_package (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            architecture: set_architecture_list (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            section: set_section (&fields)?,
+            // Unlike in a binary package, an unset Priority falls back to the source paragraph's
+            priority: fields.get("priority").cloned(),
+            essential: set_essential (&fields)?.unwrap_or (false), // Has default value
+            multi_arch: set_multi_arch (&fields)?.unwrap_or (MultiArch::No), // Has default value
+            description: set_description (&fields)?.ok_or (PakigeParseError::MissingMandatoryField)?, /* Mandatory */
+            all_fields: fields
+        });
+    }
+
+    /* Names of the substitution variables used in the paragraph, in order of first use */
+    pub fn substvars (&self) -> Vec<String>
+    {
+        let mut names: Vec<String> = Vec::new();
+        for (_, value) in self.all_fields.iter()
+        {
+            for name in substvar_names (value)
+            {
+                if !names.iter().any(|known| known == name)
+                {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        return names;
+    }
+
+    /* The value of a field with its substitution variables expanded */
+    pub fn expand (&self, key: &str, substvars: &HashMap<String, String>) -> Option<String>
+    {
+        return self.all_fields.get(key).map(|value| substitute (value, substvars));
+    }
+
+    // Parses a relationship field such as Depends after expanding its substitution variables.
+    // Variables that expand to nothing leave empty list entries behind, which are dropped, as
+    // dpkg-gencontrol does. Returns None if the field is unset or ends up empty.
+    pub fn relationships (&self, key: &str, substvars: &HashMap<String, String>) -> Result<Option<DependsPackageList>, PakigeParseError>
+    {
+        let value = match self.expand (key, substvars)
+        {
+            Some(value) => value,
+            None => return Ok(None)
+        };
+
+        let entries: Vec<&str> = split_list (&value, ',').into_iter()
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        if entries.is_empty()
+        {
+            return Ok(None);
+        }
+
+        return match parse_depends_list (&entries.join(", "))
+        {
+            Ok(list) => Ok(Some(list)),
+            Err(e) => Err(PakigeParseError::InvalidRelationship(key.to_string(), e))
+        };
+    }
+}
+
+// Splits `${name}` references out of a value. `${}` is an escaped `$` rather than a variable.
+fn substvar_names (value: &str) -> Vec<&str>
+{
+    let mut names = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${")
+    {
+        rest = &rest[start + 2..];
+        match rest.find('}')
+        {
+            Some(end) =>
+            {
+                if end > 0
+                {
+                    names.push(&rest[..end]);
+                }
+                rest = &rest[end + 1..];
+            },
+            None => break
+        }
+    }
+
+    return names;
+}
+
+// Expands `${name}` references. Besides the given variables, the built-in ${Newline}, ${Space},
+// ${Tab} and ${} (a literal `$`) are known; unknown variables expand to nothing, as in dpkg.
+fn substitute (value: &str, substvars: &HashMap<String, String>) -> String
+{
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${")
+    {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let end = match after.find('}')
+        {
+            Some(end) => end,
+            None =>
+            {
+                // Not a reference, so keep it as it is
+                expanded.push_str(&rest[start..]);
+                return expanded;
+            }
+        };
+
+        match &after[..end]
+        {
+            "" => expanded.push('$'),
+            "Newline" => expanded.push('\n'),
+            "Space" => expanded.push(' '),
+            "Tab" => expanded.push('\t'),
+            name =>
+            {
+                if let Some(replacement) = substvars.get(name)
+                {
+                    expanded.push_str(replacement);
+                }
+            }
+        }
+        rest = &after[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    return expanded;
+}
+
+impl SourceControl
+{
+    /* Looks a binary package template up by name */
+    pub fn binary (&self, package: &str) -> Option<&BinaryTemplate>
+    {
+        return self.binaries.iter().find(|binary| binary.package == package);
+    }
+}
+
+// Comments are allowed in debian/control, and are skipped. Errors carry the line of the
+// paragraph they were found in.
+impl FromStr for SourceControl
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut reader = StanzaReader::with_mode (data.as_bytes(), ParseMode::SkipComments);
+
+        let stanza = match reader.next()
+        {
+            Some(stanza) => stanza?,
+            None => return Err(StanzaError { line: 1, error: PakigeParseError::EmptyInput })
+        };
+        let source = SourceParagraph::from_fields (stanza.to_fields())
+            .map_err(|error| StanzaError { line: stanza.line(), error })?;
+
+        let mut binaries = Vec::new();
+        for stanza in reader
+        {
+            let stanza = stanza?;
+            let binary = BinaryTemplate::from_fields (stanza.to_fields())
+                .map_err(|error| StanzaError { line: stanza.line(), error })?;
+            binaries.push(binary);
+        }
+
+        return Ok(SourceControl { source, binaries });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    static CONTROL: &str = "\
+Source: hello
+Section: devel
+Priority: optional
+Maintainer: Santiago Vila <sanvila@debian.org>
+Build-Depends: debhelper-compat (= 13),
+# libfoo-dev,
+ libbar-dev
+Standards-Version: 3.9
+Vcs-Git: https://salsa.debian.org/sanvila/hello.git
+Rules-Requires-Root: no
+
+Package: hello
+Architecture: any
+Depends: ${shlibs:Depends}, ${misc:Depends}
+Description: example package based on GNU hello
+ The GNU hello program produces a familiar, friendly greeting.
+";
+
+    #[test]
+    fn source_control ()
+    {
+        let control = SourceControl::from_str (CONTROL).unwrap();
+        assert_eq!(control.source.source, "hello");
+        assert_eq!(control.source.standards_version.as_deref(), Some("3.9"));
+        assert_eq!(control.source.build_depends.as_ref().unwrap().0.len(), 2);
+        assert_eq!(control.source.vcs, [(String::from("Git"), String::from("https://salsa.debian.org/sanvila/hello.git"))]);
+
+        let hello = control.binary ("hello").unwrap();
+        assert_eq!(hello.architecture, ["any"]);
+        assert_eq!(hello.substvars(), ["shlibs:Depends", "misc:Depends"]);
+
+        let mut substvars = HashMap::new();
+        substvars.insert(String::from("shlibs:Depends"), String::from("libc6 (>= 2.34)"));
+        let depends = hello.relationships ("Depends", &substvars).unwrap().unwrap();
+        assert_eq!(depends.to_string(), "libc6 (>= 2.34)");
+        assert!(hello.relationships ("Recommends", &substvars).unwrap().is_none());
+    }
+
+    #[test]
+    fn source_control_errors ()
+    {
+        for version in ["3.9.x", ".", "4.", "4..6", "...", "4", "4.6.2.0.1"]
+        {
+            let error = SourceControl::from_str (&CONTROL.replace("Standards-Version: 3.9", &format!("Standards-Version: {}", version))).unwrap_err();
+            assert_eq!(error.line, 1, "{}", version);
+            assert!(matches!(error.error, PakigeParseError::InvalidValue), "{}", version);
+        }
+
+        let error = SourceControl::from_str (&CONTROL.replace("Architecture: any\n", "")).unwrap_err();
+        assert_eq!(error.line, 12);
+        assert!(matches!(error.error, PakigeParseError::MissingMandatoryField));
+    }
+}
@@ -0,0 +1,282 @@
+use crate::PakigeParseError;
+use super::StanzaError;
+use deb_version7::DebVersion;
+use std::str::FromStr;
+
+// https://manpages.debian.org/deb-symbols.5
+// For each library, a header line gives the SONAME and the main dependency template, followed by
+// alternative templates (`| ...`), meta-information fields (`* Field: value`) and the symbols,
+// each indented by a space:
+//
+//   libfoo.so.1 libfoo1 #MINVER#
+//   | libfoo1-extra #MINVER#
+//   * Build-Depends-Package: libfoo-dev
+//    foo_init@Base 1.0
+//    (c++|optional)"foo::bar()@Base" 1.2 1
+//
+// `#MINVER#` in a template stands for `(>= version)`, the version being the minimal version of
+// the symbol that the dependency is generated for. A symbol picks its template by number, the
+// main one being 0 and the alternatives following in order.
+
+#[derive(Debug, Clone)]
+pub struct Symbol
+{
+    pub name: String, // Including the version node, e.g. `foo_init@Base`
+    pub tags: Vec<(String, Option<String>)>, // e.g. (`optional`, None), (`arch`, Some(`amd64`))
+    pub minimal_version: DebVersion,
+    pub dependency: usize // Index of the dependency template
+}
+
+impl Symbol
+{
+    pub fn has_tag (&self, name: &str) -> bool
+    {
+        return self.tags.iter().any(|(other, _)| other == name);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolsLibrary
+{
+    pub soname: String,
+    pub dependencies: Vec<String>, // The main template, then the alternatives
+    pub fields: Vec<(String, String)>, // Meta-information, e.g. Build-Depends-Package
+    pub symbols: Vec<Symbol>
+}
+
+impl SymbolsLibrary
+{
+    /* A meta-information field, matched case-insensitively */
+    pub fn field (&self, name: &str) -> Option<&str>
+    {
+        return self.fields.iter().find(|(other, _)| other.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str());
+    }
+
+    pub fn symbol (&self, name: &str) -> Option<&Symbol>
+    {
+        return self.symbols.iter().find(|symbol| symbol.name == name);
+    }
+
+    // The dependency a use of `symbol` calls for: its template with #MINVER# replaced. A minimal
+    // version of 0 means any version will do, so the marker is dropped instead.
+    pub fn dependency (&self, symbol: &Symbol) -> Option<String>
+    {
+        let template = self.dependencies.get(symbol.dependency)?;
+        let version = symbol.minimal_version.to_string();
+        let replacement = if version == "0" { String::new() } else { format!("(>= {})", version) };
+        return Some(template.replace("#MINVER#", &replacement).trim().to_string());
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Symbols
+{
+    pub libraries: Vec<SymbolsLibrary>
+}
+
+impl Symbols
+{
+    pub fn library (&self, soname: &str) -> Option<&SymbolsLibrary>
+    {
+        return self.libraries.iter().find(|library| library.soname == soname);
+    }
+}
+
+/* ` (tag|tag=value)"name" version [id]` -> the symbol */
+fn parse_symbol (line: &str) -> Result<Symbol, PakigeParseError>
+{
+    let mut rest = line.trim();
+    let mut tags = Vec::new();
+
+    if let Some(after) = rest.strip_prefix('(')
+    {
+        let (list, after) = after.split_once(')').ok_or(PakigeParseError::InvalidFormat)?;
+        for tag in list.split('|')
+        {
+            let tag = match tag.split_once('=')
+            {
+                Some((name, value)) => (name.trim().to_string(), Some(value.trim().to_string())),
+                None => (tag.trim().to_string(), None)
+            };
+            if tag.0.is_empty()
+            {
+                return Err(PakigeParseError::InvalidFormat);
+            }
+            tags.push(tag);
+        }
+        rest = after;
+    }
+
+    // Quoted names may contain spaces (C++ signatures)
+    let name;
+    if let Some(after) = rest.strip_prefix('"')
+    {
+        let (quoted, after) = after.split_once('"').ok_or(PakigeParseError::InvalidFormat)?;
+        name = quoted;
+        rest = after;
+    }
+    else
+    {
+        let end = rest.find([' ', '\t']).ok_or(PakigeParseError::InvalidFormat)?;
+        name = &rest[..end];
+        rest = &rest[end..];
+    }
+    if name.is_empty()
+    {
+        return Err(PakigeParseError::InvalidFormat);
+    }
+
+    let mut words = rest.split_whitespace();
+    let minimal_version = DebVersion::from_str (words.next().ok_or(PakigeParseError::InvalidFormat)?)
+        .map_err(|_| PakigeParseError::InvalidValue)?;
+    let dependency = match words.next()
+    {
+        Some(id) => id.parse().map_err(|_| PakigeParseError::InvalidValue)?,
+        None => 0
+    };
+    if words.next().is_some()
+    {
+        return Err(PakigeParseError::InvalidFormat);
+    }
+
+    return Ok(Symbol { name: name.to_string(), tags, minimal_version, dependency });
+}
+
+// Lines starting with `#` are comments. A symbol that refers to a dependency template the
+// library does not have is an error, reported at the symbol's line.
+impl FromStr for Symbols
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut libraries: Vec<SymbolsLibrary> = Vec::new();
+
+        for (index, line) in data.lines().enumerate()
+        {
+            let error = |error| StanzaError { line: index + 1, error };
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#')
+            {
+                continue;
+            }
+
+            if !line.starts_with([' ', '\t', '|', '*'])
+            {
+                let (soname, template) = line.split_once([' ', '\t']).ok_or(error(PakigeParseError::InvalidFormat))?;
+                libraries.push(SymbolsLibrary {
+                    soname: soname.to_string(),
+                    dependencies: vec![template.trim().to_string()],
+                    fields: Vec::new(),
+                    symbols: Vec::new()
+                });
+                continue;
+            }
+
+            // Everything else belongs to a library
+            let library = libraries.last_mut().ok_or(error(PakigeParseError::InvalidFormat))?;
+
+            if let Some(template) = line.strip_prefix('|')
+            {
+                library.dependencies.push(template.trim().to_string());
+            }
+            else if let Some(field) = line.strip_prefix('*')
+            {
+                let (name, value) = field.split_once(':').ok_or(error(PakigeParseError::InvalidFormat))?;
+                library.fields.push((name.trim().to_string(), value.trim().to_string()));
+            }
+            else
+            {
+                let symbol = parse_symbol (line).map_err(error)?;
+                if symbol.dependency >= library.dependencies.len()
+                {
+                    return Err(error(PakigeParseError::InvalidValue));
+                }
+                library.symbols.push(symbol);
+            }
+        }
+
+        return Ok(Symbols { libraries });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing::assert_stanza_error;
+
+    static SYMBOLS: &str = "\
+# Symbols of the hello libraries
+libhello.so.1 libhello1 #MINVER#
+| libhello1-extra #MINVER#, libhello-common
+* Build-Depends-Package: libhello-dev
+ hello_init@Base 1.0
+ hello_exit@Base 0
+ (c++|optional)\"hello::greet(std::string const&)@Base\" 1.2 1
+ (arch=amd64 arm64|symver)HELLO_1.0 1.0~rc1
+libhello-extra.so.0 libhello-extra0 (>= 0.5)
+ extra_init@Base 0.5
+";
+
+    #[test]
+    fn symbols ()
+    {
+        let symbols = Symbols::from_str (SYMBOLS).unwrap();
+        assert_eq!(symbols.libraries.len(), 2);
+
+        let library = symbols.library("libhello.so.1").unwrap();
+        assert_eq!(library.dependencies, ["libhello1 #MINVER#", "libhello1-extra #MINVER#, libhello-common"]);
+        assert_eq!(library.field("build-depends-package"), Some("libhello-dev"));
+        assert_eq!(library.field("Ignore-Blacklist-Groups"), None);
+        assert_eq!(library.symbols.len(), 4);
+
+        let init = library.symbol("hello_init@Base").unwrap();
+        assert!(init.tags.is_empty());
+        assert_eq!(init.minimal_version.to_string(), "1.0");
+        assert_eq!(init.dependency, 0);
+        assert_eq!(library.dependency(init).as_deref(), Some("libhello1 (>= 1.0)"));
+
+        // Any version will do
+        let exit = library.symbol("hello_exit@Base").unwrap();
+        assert_eq!(library.dependency(exit).as_deref(), Some("libhello1"));
+
+        let greet = library.symbol("hello::greet(std::string const&)@Base").unwrap();
+        assert!(greet.has_tag("c++") && greet.has_tag("optional") && !greet.has_tag("arch"));
+        assert_eq!(greet.dependency, 1);
+        assert_eq!(library.dependency(greet).as_deref(), Some("libhello1-extra (>= 1.2), libhello-common"));
+
+        let version = library.symbol("HELLO_1.0").unwrap();
+        assert_eq!(version.tags, [(String::from("arch"), Some(String::from("amd64 arm64"))), (String::from("symver"), None)]);
+        assert_eq!(version.minimal_version.to_string(), "1.0~rc1");
+
+        let extra = symbols.library("libhello-extra.so.0").unwrap();
+        assert_eq!(extra.dependency(&extra.symbols[0]).as_deref(), Some("libhello-extra0 (>= 0.5)"));
+        assert!(extra.fields.is_empty());
+        assert!(symbols.library("libhello.so.2").is_none());
+    }
+
+    #[test]
+    fn symbols_errors ()
+    {
+        let header = "libhello.so.1 libhello1 #MINVER#\n";
+        let cases = [
+            (String::from(" hello_init@Base 1.0\n"), 1, PakigeParseError::InvalidFormat),
+            (String::from("libhello.so.1\n"), 1, PakigeParseError::InvalidFormat),
+            (format!("{}* Build-Depends-Package libhello-dev\n", header), 2, PakigeParseError::InvalidFormat),
+            (format!("{} hello_init@Base\n", header), 2, PakigeParseError::InvalidFormat),
+            (format!("{} hello_init@Base 1.0 0 extra\n", header), 2, PakigeParseError::InvalidFormat),
+            (format!("{} (optional\"hello_init@Base\" 1.0\n", header), 2, PakigeParseError::InvalidFormat),
+            (format!("{} (|optional)hello_init@Base 1.0\n", header), 2, PakigeParseError::InvalidFormat),
+            (format!("{} \"hello::greet()@Base 1.0\n", header), 2, PakigeParseError::InvalidFormat),
+            (format!("{} \"\" 1.0\n", header), 2, PakigeParseError::InvalidFormat),
+            (format!("{} hello_init@Base 1.0 one\n", header), 2, PakigeParseError::InvalidValue),
+            (format!("{}\n hello_init@Base 1.0 1\n", header), 3, PakigeParseError::InvalidValue),
+            (format!("{} hello_init@Base :1.0\n", header), 2, PakigeParseError::InvalidValue)
+        ];
+        for (data, line, expected) in cases
+        {
+            assert_stanza_error (Symbols::from_str (&data), &data, line, expected);
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use crate::PakigeParseError;
+use super::ArchiveError;
+use std::io::{self, Read};
+
+// Reads the tar members of a .deb. dpkg-deb writes GNU tar (long names as `L` and `K`
+// entries), while other tools may use ustar prefixes or pax headers; all three are handled.
+// Paths are returned without the leading `./` that packages conventionally use.
+
+const BLOCK: usize = 512;
+// GNU long names and pax headers are read into memory; anything larger is taken as corrupt
+const MAX_EXTENSION: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind
+{
+    File,
+    HardLink,
+    Symlink,
+    CharDevice,
+    BlockDevice,
+    Directory,
+    Fifo
+}
+
+#[derive(Debug, Clone)]
+pub struct TarEntry
+{
+    pub path: String, // Relative, e.g. `usr/bin/hello`; the root directory is an empty path
+    pub kind: EntryKind,
+    pub mode: u32, // Permission bits, including setuid, setgid and sticky
+    pub uid: u32,
+    pub gid: u32,
+    pub user: String, // Owner names, which dpkg prefers over the ids when they exist
+    pub group: String,
+    pub size: u64,
+    pub mtime: i64, // Seconds since the epoch
+    pub link_target: Option<String> // As written for symlinks; an archive path for hard links
+}
+
+// Reads entries one at a time from a stream, so that a payload never has to be held in memory
+// whole. The data of the last entry returned can be read with contents(); whatever is left of
+// it is skipped when the next entry is read.
+pub struct TarReader<R: Read>
+{
+    reader: R,
+    remaining: u64, // Data of the current entry not read yet
+    padding: u64, // Then up to the next block
+    done: bool
+}
+
+/* A NUL-terminated header field */
+fn text (field: &[u8]) -> String
+{
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    return String::from_utf8_lossy(&field[..end]).into_owned();
+}
+
+/* An octal number padded with spaces or NULs, or a big-endian base-256 one when the high bit is set */
+fn number (field: &[u8]) -> Result<u64, ArchiveError>
+{
+    if field.first().is_some_and(|b| b & 0x80 != 0)
+    {
+        let mut value = (field[0] & 0x7F) as u64;
+        for byte in &field[1..]
+        {
+            value = value.checked_mul(256).ok_or(ArchiveError::InvalidTar)? | *byte as u64;
+        }
+        return Ok(value);
+    }
+
+    let digits = text (field);
+    let digits = digits.trim_matches(' ');
+    if digits.is_empty()
+    {
+        return Ok(0);
+    }
+    return u64::from_str_radix(digits, 8).map_err(|_| ArchiveError::InvalidTar);
+}
+
+fn id (value: u64) -> Result<u32, ArchiveError>
+{
+    return u32::try_from(value).map_err(|_| ArchiveError::InvalidTar);
+}
+
+fn normalize (path: &str) -> String
+{
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    return path.trim_end_matches('/').to_string();
+}
+
+/* Parses pax extended header records (`<length> <key>=<value>\n`) */
+fn pax_records (data: &[u8]) -> Result<Vec<(String, String)>, ArchiveError>
+{
+    let mut records = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty()
+    {
+        let space = rest.iter().position(|b| *b == b' ').ok_or(ArchiveError::InvalidTar)?;
+        let length: usize = std::str::from_utf8(&rest[..space]).ok()
+            .and_then(|length| length.parse().ok())
+            .filter(|length| *length > space && *length <= rest.len())
+            .ok_or(ArchiveError::InvalidTar)?;
+        let record = &rest[space + 1..length];
+        let record = record.strip_suffix(b"\n").ok_or(ArchiveError::InvalidTar)?;
+        let equals = record.iter().position(|b| *b == b'=').ok_or(ArchiveError::InvalidTar)?;
+        records.push((
+            String::from_utf8_lossy(&record[..equals]).into_owned(),
+            String::from_utf8_lossy(&record[equals + 1..]).into_owned()
+        ));
+        rest = &rest[length..];
+    }
+    return Ok(records);
+}
+
+// Errors of the underlying reader end the archive. Running out of data part way through is a
+// truncated tar; errors from decompressing it carry their own ArchiveError.
+fn read_error (error: io::Error) -> PakigeParseError
+{
+    let inner = error.get_ref().is_some_and(|inner| inner.is::<ArchiveError>());
+    if error.kind() == io::ErrorKind::UnexpectedEof && !inner
+    {
+        return ArchiveError::InvalidTar.into();
+    }
+    return error.into();
+}
+
+impl<R: Read> TarReader<R>
+{
+    pub fn new (reader: R) -> Self
+    {
+        return TarReader { reader, remaining: 0, padding: 0, done: false };
+    }
+
+    /* Skips what is left of the current entry's data, and its padding */
+    fn skip (&mut self) -> Result<(), PakigeParseError>
+    {
+        let length = self.remaining.checked_add(self.padding).ok_or(ArchiveError::InvalidTar)?;
+        let skipped = io::copy(&mut (&mut self.reader).take(length), &mut io::sink()).map_err(read_error)?;
+        if skipped != length
+        {
+            return Err(ArchiveError::InvalidTar.into());
+        }
+        self.remaining = 0;
+        self.padding = 0;
+        return Ok(());
+    }
+
+    /* The next header block, or None at the end of the stream */
+    fn read_header (&mut self) -> Result<Option<[u8; BLOCK]>, PakigeParseError>
+    {
+        let mut header = [0u8; BLOCK];
+        let mut filled = 0;
+        while filled < BLOCK
+        {
+            match self.reader.read(&mut header[filled..])
+            {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(read_error (error))
+            }
+        }
+
+        // Some writers leave out the end-of-archive blocks
+        return match filled
+        {
+            0 => Ok(None),
+            BLOCK => Ok(Some(header)),
+            _ => Err(ArchiveError::InvalidTar.into())
+        };
+    }
+
+    /* Reads the data of a GNU long name or pax header entry */
+    fn read_extension (&mut self) -> Result<Vec<u8>, PakigeParseError>
+    {
+        if self.remaining > MAX_EXTENSION
+        {
+            return Err(ArchiveError::InvalidTar.into());
+        }
+        let mut data = Vec::new();
+        self.contents().read_to_end(&mut data).map_err(read_error)?;
+        return Ok(data);
+    }
+
+    /* Reads the next entry, skipping any data of the previous one that was not read */
+    pub fn next_entry (&mut self) -> Result<Option<TarEntry>, PakigeParseError>
+    {
+        if self.done
+        {
+            return Ok(None);
+        }
+        let entry = self.read_entry();
+        if !matches!(entry, Ok(Some(_)))
+        {
+            self.done = true;
+        }
+        return entry;
+    }
+
+    /* The data of the entry last returned; empty for everything but regular files */
+    pub fn contents (&mut self) -> EntryContents<'_, R>
+    {
+        return EntryContents { tar: self };
+    }
+
+    /* The next header and the size of its data, whatever its type */
+    fn next_raw (&mut self) -> Result<Option<([u8; BLOCK], u64)>, PakigeParseError>
+    {
+        self.skip()?;
+
+        let header = match self.read_header()?
+        {
+            Some(header) => header,
+            None => return Ok(None)
+        };
+        if header.iter().all(|b| *b == 0)
+        {
+            return Ok(None);
+        }
+
+        let expected = number (&header[148..156])?;
+        let sum: u64 = header.iter().enumerate()
+            .map(|(index, byte)| if (148..156).contains(&index) { b' ' as u64 } else { *byte as u64 })
+            .sum();
+        if sum != expected
+        {
+            return Err(ArchiveError::InvalidTar.into());
+        }
+
+        let size = number (&header[124..136])?;
+        self.set_remaining (size)?;
+        return Ok(Some((header, size)));
+    }
+
+    /* A size whose padding runs past u64::MAX can only come from a corrupt header */
+    fn set_remaining (&mut self, size: u64) -> Result<(), ArchiveError>
+    {
+        let end = size.checked_next_multiple_of(BLOCK as u64).ok_or(ArchiveError::InvalidTar)?;
+        self.remaining = size;
+        self.padding = end - size;
+        return Ok(());
+    }
+
+    fn read_entry (&mut self) -> Result<Option<TarEntry>, PakigeParseError>
+    {
+        let mut long_name: Option<String> = None;
+        let mut long_link: Option<String> = None;
+        let mut pax: Vec<(String, String)> = Vec::new();
+
+        loop
+        {
+            let (header, size) = match self.next_raw()?
+            {
+                Some(entry) => entry,
+                None => return Ok(None)
+            };
+
+            let kind = match header[156]
+            {
+                b'0' | b'\0' | b'7' => EntryKind::File,
+                b'1' => EntryKind::HardLink,
+                b'2' => EntryKind::Symlink,
+                b'3' => EntryKind::CharDevice,
+                b'4' => EntryKind::BlockDevice,
+                b'5' => EntryKind::Directory,
+                b'6' => EntryKind::Fifo,
+                b'L' =>
+                {
+                    long_name = Some(text (&self.read_extension()?));
+                    continue;
+                },
+                b'K' =>
+                {
+                    long_link = Some(text (&self.read_extension()?));
+                    continue;
+                },
+                b'x' =>
+                {
+                    pax = pax_records (&self.read_extension()?)?;
+                    continue;
+                },
+                // Global pax headers apply to the whole archive and carry nothing needed here
+                _ => continue
+            };
+
+            let mut path = text (&header[..100]);
+            if &header[257..263] == b"ustar\0" && header[345] != 0
+            {
+                path = format!("{}/{}", text (&header[345..500]), path);
+            }
+            if let Some(name) = long_name.take()
+            {
+                path = name;
+            }
+            let mut link = text (&header[157..257]);
+            if let Some(name) = long_link.take()
+            {
+                link = name;
+            }
+
+            let mut entry = TarEntry {
+                path: String::new(),
+                kind,
+                mode: (number (&header[100..108])? & 0o7777) as u32,
+                uid: id (number (&header[108..116])?)?,
+                gid: id (number (&header[116..124])?)?,
+                user: text (&header[265..297]),
+                group: text (&header[297..329]),
+                size,
+                mtime: number (&header[136..148])? as i64,
+                link_target: None
+            };
+
+            for (key, value) in &pax
+            {
+                let invalid = |_| ArchiveError::InvalidTar;
+                match key.as_str()
+                {
+                    "path" => path = value.clone(),
+                    "linkpath" => link = value.clone(),
+                    "uid" => entry.uid = value.parse().map_err(invalid)?,
+                    "gid" => entry.gid = value.parse().map_err(invalid)?,
+                    "uname" => entry.user = value.clone(),
+                    "gname" => entry.group = value.clone(),
+                    // May have a fractional part, which is dropped
+                    "mtime" => entry.mtime = value.split('.').next().unwrap_or("").parse().map_err(invalid)?,
+                    // The real size was too large for the header; the data follows as usual
+                    "size" =>
+                    {
+                        entry.size = value.parse().map_err(invalid)?;
+                        self.set_remaining (entry.size)?;
+                    },
+                    _ => ()
+                }
+            }
+
+            entry.path = normalize (&path);
+            entry.link_target = match kind
+            {
+                EntryKind::HardLink => Some(normalize (&link)),
+                EntryKind::Symlink => Some(link),
+                _ => None
+            };
+            // Only the data of regular files is handed out; anything else is skipped
+            if kind != EntryKind::File
+            {
+                self.padding = self.padding.checked_add(self.remaining).ok_or(ArchiveError::InvalidTar)?;
+                self.remaining = 0;
+            }
+            return Ok(Some(entry));
+        }
+    }
+}
+
+impl<R: Read> Iterator for TarReader<R>
+{
+    type Item = Result<TarEntry, PakigeParseError>;
+
+    fn next (&mut self) -> Option<Self::Item>
+    {
+        return self.next_entry().transpose();
+    }
+}
+
+/* Reads the data of the current entry of a TarReader */
+pub struct EntryContents<'a, R: Read>
+{
+    tar: &'a mut TarReader<R>
+}
+
+impl<R: Read> Read for EntryContents<'_, R>
+{
+    fn read (&mut self, buffer: &mut [u8]) -> io::Result<usize>
+    {
+        if self.tar.remaining == 0 || buffer.is_empty()
+        {
+            return Ok(0);
+        }
+        let length = buffer.len().min(usize::try_from(self.tar.remaining).unwrap_or(usize::MAX));
+        let read = self.tar.reader.read(&mut buffer[..length])?;
+        if read == 0
+        {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, ArchiveError::InvalidTar));
+        }
+        self.tar.remaining -= read as u64;
+        return Ok(read);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /* A ustar header with a valid checksum; `size` is the raw size field */
+    fn header (name: &str, kind: u8, size: &[u8; 12]) -> [u8; BLOCK]
+    {
+        let mut header = [0u8; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..136].copy_from_slice(size);
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|b| *b as u32).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        return header;
+    }
+
+    fn octal (size: u64) -> [u8; 12]
+    {
+        let mut field = [0u8; 12];
+        field[..11].copy_from_slice(format!("{:011o}", size).as_bytes());
+        return field;
+    }
+
+    fn entries (data: &[u8]) -> Result<Vec<TarEntry>, PakigeParseError>
+    {
+        return TarReader::new (data).collect();
+    }
+
+    #[test]
+    fn sizes ()
+    {
+        let mut data = header ("hello", b'0', &octal (5)).to_vec();
+        data.extend_from_slice(b"hello");
+        data.resize(2 * BLOCK, 0);
+        let found = entries (&data).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].path.as_str(), found[0].size), ("hello", 5));
+
+        // Base-256 sizes whose padding would run past u64::MAX
+        let mut huge = [0xffu8; 12];
+        huge[..4].copy_from_slice(&[0x80, 0, 0, 0]);
+        for kind in [b'0', b'5']
+        {
+            let data = header ("big", kind, &huge);
+            assert!(matches!(entries (&data), Err(PakigeParseError::InvalidArchive(ArchiveError::InvalidTar))));
+        }
+
+        // The same size from a pax record
+        let record = format!(" size={}\n", u64::MAX);
+        let record = format!("{}{}", record.len() + 2, record);
+        let mut data = header ("pax", b'x', &octal (record.len() as u64)).to_vec();
+        data.extend_from_slice(record.as_bytes());
+        data.resize(2 * BLOCK, 0);
+        data.extend_from_slice(&header ("big", b'0', &octal (0)));
+        assert!(matches!(entries (&data), Err(PakigeParseError::InvalidArchive(ArchiveError::InvalidTar))));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::PakigeParseError;
+use super::StanzaError;
+use std::str::FromStr;
+
+// https://manpages.debian.org/deb-triggers.5
+// One directive per line: `interest`, `interest-await` or `interest-noawait` declares that the
+// package's postinst handles a trigger, and `activate`, `activate-await` or `activate-noawait`
+// activates one when the package is unpacked. The plain forms are the awaiting ones. Trigger
+// names starting with `/` are file triggers, activated by changes below that path.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerKind
+{
+    Interest,
+    Activate
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger
+{
+    pub kind: TriggerKind,
+    pub name: String,
+    pub noawait: bool // The triggering package need not wait for the trigger to be processed
+}
+
+impl Trigger
+{
+    pub fn is_file_trigger (&self) -> bool
+    {
+        return self.name.starts_with('/');
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Triggers
+{
+    pub triggers: Vec<Trigger>
+}
+
+impl Triggers
+{
+    pub fn interests (&self) -> impl Iterator<Item = &Trigger>
+    {
+        return self.triggers.iter().filter(|trigger| trigger.kind == TriggerKind::Interest);
+    }
+
+    pub fn activations (&self) -> impl Iterator<Item = &Trigger>
+    {
+        return self.triggers.iter().filter(|trigger| trigger.kind == TriggerKind::Activate);
+    }
+}
+
+// Comments (`#` to the end of the line) and blank lines are ignored, as dpkg does.
+impl FromStr for Triggers
+{
+    type Err = StanzaError;
+
+    fn from_str (data: &str) -> Result<Self, Self::Err>
+    {
+        let mut triggers = Vec::new();
+
+        for (index, line) in data.lines().enumerate()
+        {
+            let error = |error| StanzaError { line: index + 1, error };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty()
+            {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let directive = words.next().unwrap_or("");
+            let name = words.next().ok_or(error(PakigeParseError::InvalidFormat))?;
+            if words.next().is_some()
+            {
+                return Err(error(PakigeParseError::InvalidFormat));
+            }
+
+            let (kind, noawait) = match directive
+            {
+                "interest" | "interest-await" => (TriggerKind::Interest, false),
+                "interest-noawait" => (TriggerKind::Interest, true),
+                "activate" | "activate-await" => (TriggerKind::Activate, false),
+                "activate-noawait" => (TriggerKind::Activate, true),
+                _ => return Err(error(PakigeParseError::InvalidValue))
+            };
+
+            // Trigger names are printable ASCII without spaces
+            if !name.chars().all(|c| c.is_ascii_graphic())
+            {
+                return Err(error(PakigeParseError::InvalidValue));
+            }
+
+            triggers.push(Trigger { kind, name: name.to_string(), noawait });
+        }
+
+        return Ok(Triggers { triggers });
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn triggers ()
+    {
+        let triggers = Triggers::from_str ("\
+# Rebuild the cache when other packages add icons
+interest-noawait /usr/share/icons/hicolor
+interest update-hello-cache # from postinst
+
+activate-noawait ldconfig
+activate-await update-hello-cache
+").unwrap();
+        assert_eq!(triggers.triggers, vec![
+            Trigger { kind: TriggerKind::Interest, name: String::from("/usr/share/icons/hicolor"), noawait: true },
+            Trigger { kind: TriggerKind::Interest, name: String::from("update-hello-cache"), noawait: false },
+            Trigger { kind: TriggerKind::Activate, name: String::from("ldconfig"), noawait: true },
+            Trigger { kind: TriggerKind::Activate, name: String::from("update-hello-cache"), noawait: false }
+        ]);
+        assert!(triggers.triggers[0].is_file_trigger());
+        assert!(!triggers.triggers[1].is_file_trigger());
+
+        let interests: Vec<&str> = triggers.interests().map(|trigger| trigger.name.as_str()).collect();
+        assert_eq!(interests, ["/usr/share/icons/hicolor", "update-hello-cache"]);
+        let activations: Vec<&str> = triggers.activations().map(|trigger| trigger.name.as_str()).collect();
+        assert_eq!(activations, ["ldconfig", "update-hello-cache"]);
+    }
+
+    #[test]
+    fn triggers_errors ()
+    {
+        for (data, line) in [("interest\n", 1), ("activate a b\n", 1), ("# comment\ninterest a\n\nactivate # b\n", 4)]
+        {
+            let error = Triggers::from_str (data).unwrap_err();
+            assert_eq!(error.line, line, "{}", data);
+            assert!(matches!(error.error, PakigeParseError::InvalidFormat), "{}", data);
+        }
+        for data in ["interested a", "activate-later a", "activate cache\u{e9}"]
+        {
+            let error = Triggers::from_str (data).unwrap_err();
+            assert_eq!(error.line, 1, "{}", data);
+            assert!(matches!(error.error, PakigeParseError::InvalidValue), "{}", data);
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::PakigeParseError;
+use crate::digest::{hash_reader, Md5};
+use super::{DebArchive, EntryKind, Md5Sums, Stanza, StanzaReader};
+use super::mirror::is_relative_path;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+// Checks the files of a package under a filesystem root against their MD5 digests, as
+// `dpkg --verify` does. Ordinary files are checked against md5sums. Conffiles are checked
+// against the digest of the version that was shipped, which dpkg keeps in the Conffiles field of
+// its status file (md5sums often leaves conffiles out); a changed conffile is usually a local
+// edit rather than damage, so it is reported apart from modified files. Files that cannot be
+// read are reported as well, and checking goes on with the rest.
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport
+{
+    pub verified: Vec<String>, // Absolute paths, as dpkg prints them
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    pub unreadable: Vec<String>, // e.g. replaced by a directory, or not readable by this user
+    pub conffiles_changed: Vec<String>
+}
+
+impl VerifyReport
+{
+    /* No missing, modified or unreadable files; changed conffiles are taken as local configuration */
+    pub fn is_ok (&self) -> bool
+    {
+        return self.missing.is_empty() && self.modified.is_empty() && self.unreadable.is_empty();
+    }
+}
+
+/* The hex MD5 of a file, or None if it does not exist */
+fn file_md5 (path: &Path) -> io::Result<Option<String>>
+{
+    let file = match File::open(path)
+    {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error)
+    };
+    let (digest, _) = hash_reader (Md5::new(), BufReader::new(file))?;
+    return Ok(Some(digest));
+}
+
+// Verifies the files in `md5sums` and the conffiles in `conffiles`, given as (path, digest), under
+// `root`. Files listed in both are only checked as conffiles. A path that would lead out of the
+// root (through `..`) is an invalid value, and nothing is checked.
+pub fn verify (root: &Path, md5sums: &Md5Sums, conffiles: &[(String, String)]) -> Result<VerifyReport, PakigeParseError>
+{
+    let paths = md5sums.entries().iter().map(|(path, _)| path.as_str())
+        .chain(conffiles.iter().map(|(path, _)| path.trim_start_matches('/')));
+    for path in paths
+    {
+        if !is_relative_path (path)
+        {
+            return Err(PakigeParseError::InvalidValue);
+        }
+    }
+
+    let mut report = VerifyReport::default();
+    let is_conffile = |path: &str| conffiles.iter().any(|(other, _)| other.trim_start_matches('/') == path);
+
+    for (path, digest) in md5sums.entries()
+    {
+        if is_conffile (path)
+        {
+            continue;
+        }
+        match file_md5 (&root.join(path))
+        {
+            Err(_) => report.unreadable.push(format!("/{}", path)),
+            Ok(None) => report.missing.push(format!("/{}", path)),
+            Ok(Some(found)) if found != *digest => report.modified.push(format!("/{}", path)),
+            Ok(Some(_)) => report.verified.push(format!("/{}", path))
+        }
+    }
+
+    for (path, digest) in conffiles
+    {
+        let path = path.trim_start_matches('/');
+        match file_md5 (&root.join(path))
+        {
+            Err(_) => report.unreadable.push(format!("/{}", path)),
+            Ok(None) => report.missing.push(format!("/{}", path)),
+            Ok(Some(found)) if !found.eq_ignore_ascii_case(digest) => report.conffiles_changed.push(format!("/{}", path)),
+            Ok(Some(_)) => report.verified.push(format!("/{}", path))
+        }
+    }
+
+    return Ok(report);
+}
+
+// The conffiles of a status stanza that can be verified. Each line of the field is
+// `<path> <digest> [flags]`; obsolete and remove-on-upgrade conffiles are no longer expected to
+// be present, and `newconffile` stands in for the digest of one that was never configured.
+fn status_conffiles (stanza: &Stanza) -> Vec<(String, String)>
+{
+    let mut conffiles = Vec::new();
+    for line in stanza.get("Conffiles").unwrap_or("").lines()
+    {
+        let mut words: Vec<&str> = line.split(' ').filter(|word| !word.is_empty()).collect();
+        let mut skip = false;
+        while words.last().is_some_and(|word| matches!(*word, "obsolete" | "remove-on-upgrade"))
+        {
+            skip = true;
+            words.pop();
+        }
+        if words.len() < 2 || skip || words[words.len() - 1] == "newconffile"
+        {
+            continue;
+        }
+        let digest = words.pop().unwrap_or("");
+        conffiles.push((words.join(" "), digest.to_string()));
+    }
+    return conffiles;
+}
+
+// Verifies an installed package, from the dpkg database: `info_dir` is dpkg's info directory
+// (`<root>/var/lib/dpkg/info`), next to which the status file is looked for. `package` may carry
+// an architecture qualifier (`libc6:amd64`), as the files of Multi-Arch: same packages are named.
+// A package without an md5sums file only has its conffiles checked, as with dpkg. Only packages
+// whose files are on the system count: a removed package whose conffiles were kept is skipped.
+pub fn verify_installed (info_dir: &Path, package: &str, root: &Path) -> Result<VerifyReport, PakigeParseError>
+{
+    let (name, arch) = match package.split_once(':')
+    {
+        Some((name, arch)) => (name, Some(arch)),
+        None => (package, None)
+    };
+
+    let mut md5sums_path = info_dir.join(format!("{}.md5sums", package));
+    if !md5sums_path.is_file()
+    {
+        md5sums_path = info_dir.join(format!("{}.md5sums", name));
+    }
+    let md5sums = match fs::read(&md5sums_path)
+    {
+        Ok(data) =>
+        {
+            // Text that is not UTF-8 is a broken md5sums file, not a failure to read it
+            let text = std::str::from_utf8(&data).map_err(|_| PakigeParseError::InvalidFormat)?;
+            Md5Sums::from_str (text).map_err(|error| error.error)?
+        },
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Md5Sums::default(),
+        Err(error) => return Err(error.into())
+    };
+
+    let mut conffiles = Vec::new();
+    if let Some(status) = info_dir.parent().map(|admin| admin.join("status")).filter(|status| status.is_file())
+    {
+        let mut reader = StanzaReader::new (BufReader::new(File::open(status)?));
+        let mut stanza = Stanza::new();
+        loop
+        {
+            match reader.read_stanza(&mut stanza)
+            {
+                Ok(true) => (),
+                Ok(false) => break,
+                // Skip a bad stanza, unless the file itself could not be read; text that is not
+                // UTF-8 is only a bad line
+                Err(error) => match error.error
+                {
+                    PakigeParseError::IoError(error) if error.kind() != io::ErrorKind::InvalidData => return Err(error.into()),
+                    _ => continue
+                }
+            }
+
+            // Packages removed but not purged still have a stanza, but no files to check
+            let state = stanza.get("Status").and_then(|status| status.split_whitespace().nth(2));
+            let matches = stanza.get("Package") == Some(name)
+                && arch.is_none_or(|arch| stanza.get("Architecture") == Some(arch))
+                && !matches!(state, Some("not-installed" | "config-files"));
+            if matches
+            {
+                conffiles = status_conffiles (&stanza);
+                break;
+            }
+        }
+    }
+
+    return verify (root, &md5sums, &conffiles);
+}
+
+impl DebArchive<'_>
+{
+    // Verifies the files of this package as installed under `root`. Conffile digests are taken
+    // from md5sums when listed there, and otherwise computed from the payload.
+    pub fn verify (&self, root: &Path) -> Result<VerifyReport, PakigeParseError>
+    {
+        let md5sums = self.md5sums().map_err(|error| error.error)?.unwrap_or_default();
+        let shipped: Vec<String> = match self.conffiles().map_err(|error| error.error)?
+        {
+            Some(conffiles) => conffiles.shipped().map(|file| file.path.trim_start_matches('/').to_string()).collect(),
+            None => Vec::new()
+        };
+
+        let mut conffiles = Vec::new();
+        let mut unlisted = Vec::new();
+        for path in shipped
+        {
+            match md5sums.get(&path)
+            {
+                Some(digest) => conffiles.push((path, digest.to_string())),
+                None => unlisted.push(path)
+            }
+        }
+
+        if !unlisted.is_empty()
+        {
+            let mut data = self.data()?;
+            while let Some(entry) = data.next_entry()?
+            {
+                if entry.kind == EntryKind::File && unlisted.contains(&entry.path)
+                {
+                    let (digest, _) = hash_reader (Md5::new(), data.contents())?;
+                    conffiles.push((entry.path, digest));
+                }
+            }
+        }
+
+        return verify (root, &md5sums, &conffiles);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::digest::{to_hex, Digest};
+    use crate::testing::{temp_dir, write};
+
+    fn md5 (data: &[u8]) -> String
+    {
+        let mut digest = Md5::new();
+        digest.update(data);
+        return to_hex (&digest.finish());
+    }
+
+    fn sorted (paths: &[String]) -> Vec<&str>
+    {
+        let mut paths: Vec<&str> = paths.iter().map(|path| path.as_str()).collect();
+        paths.sort();
+        return paths;
+    }
+
+    #[test]
+    fn verify_files ()
+    {
+        let root = temp_dir ("verify-files");
+        write (&root, "usr/bin/hello", b"hello");
+        write (&root, "usr/bin/changed", b"changed");
+        write (&root, "etc/hello.conf", b"edited");
+        write (&root, "etc/kept.conf", b"kept");
+
+        let md5sums = Md5Sums::from_str (&format!("{}  usr/bin/hello\n{}  usr/bin/changed\n{}  usr/bin/gone\n{}  etc/hello.conf\n",
+            md5 (b"hello"), md5 (b"original"), md5 (b"gone"), md5 (b"edited"))).unwrap();
+        let conffiles = [
+            (String::from("/etc/hello.conf"), md5 (b"shipped")),
+            (String::from("/etc/kept.conf"), md5 (b"kept").to_ascii_uppercase()),
+            (String::from("/etc/gone.conf"), md5 (b"gone"))
+        ];
+
+        let report = verify (&root, &md5sums, &conffiles).unwrap();
+        assert_eq!(sorted (&report.verified), ["/etc/kept.conf", "/usr/bin/hello"]);
+        assert_eq!(report.modified, ["/usr/bin/changed"]);
+        assert_eq!(sorted (&report.missing), ["/etc/gone.conf", "/usr/bin/gone"]);
+        // Checked against the shipped digest, not md5sums
+        assert_eq!(report.conffiles_changed, ["/etc/hello.conf"]);
+        assert!(!report.is_ok());
+
+        let report = verify (&root, &Md5Sums::from_str (&format!("{}  usr/bin/hello\n", md5 (b"hello"))).unwrap(), &conffiles[..2]).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.conffiles_changed, ["/etc/hello.conf"]);
+
+        // A file replaced by a directory is reported, and the others are still checked
+        fs::create_dir_all(root.join("usr/bin/dir")).unwrap();
+        let md5sums = Md5Sums::from_str (&format!("{}  usr/bin/dir\n{}  usr/bin/hello\n", md5 (b"dir"), md5 (b"hello"))).unwrap();
+        let report = verify (&root, &md5sums, &[]).unwrap();
+        assert_eq!(report.unreadable, ["/usr/bin/dir"]);
+        assert_eq!(report.verified, ["/usr/bin/hello"]);
+        assert!(!report.is_ok());
+
+        // Paths may not lead out of the root
+        let md5sums = Md5Sums::from_str (&format!("{}  usr/../../etc/passwd\n", md5 (b"root"))).unwrap();
+        assert!(matches!(verify (&root, &md5sums, &[]), Err(PakigeParseError::InvalidValue)));
+        let conffiles = [(String::from("/etc/../../shadow"), md5 (b"root"))];
+        assert!(matches!(verify (&root, &Md5Sums::default(), &conffiles), Err(PakigeParseError::InvalidValue)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn verify_installed_package ()
+    {
+        let root = temp_dir ("verify-installed");
+        let info = root.join("var/lib/dpkg/info");
+        write (&root, "usr/bin/hello", b"hello");
+        write (&root, "etc/hello.conf", b"greeting");
+        write (&root, "usr/lib/x86_64-linux-gnu/libhello.so.1", b"amd64");
+        write (&root, "etc/libhello.conf", b"edited");
+
+        write (&info, "hello.md5sums", format!("{}  usr/bin/hello\n", md5 (b"hello")).as_bytes());
+        write (&info, "libhello1:amd64.md5sums", format!("{}  usr/lib/x86_64-linux-gnu/libhello.so.1\n", md5 (b"amd64")).as_bytes());
+        let status = format!("\
+Package: hello
+Status: deinstall ok config-files
+Architecture: all
+Conffiles:
+ /etc/hello.conf {}
+
+Package: libhello1
+Description: caf?
+Status: install ok installed
+Architecture: arm64
+Conffiles:
+ /etc/libhello.conf {}
+
+Package: hello
+Status: install ok installed
+Architecture: all
+Conffiles:
+ /etc/hello.conf {}
+ /etc/hello/old.conf {} obsolete
+ /etc/hello/removed.conf {} remove-on-upgrade
+ /etc/hello/new.conf newconffile
+ /etc/hello/both.conf {} remove-on-upgrade obsolete
+
+Package: libhello1
+Status: install ok installed
+Architecture: amd64
+Conffiles:
+ /etc/libhello.conf {}
+", md5 (b"removed"), md5 (b"edited"), md5 (b"greeting"), md5 (b"old"), md5 (b"removed"), md5 (b"both"), md5 (b"shipped"));
+        // A byte that is not UTF-8 only spoils its own stanza
+        let mut status = status.into_bytes();
+        let at = status.windows(4).position(|window| window == b"caf?").unwrap();
+        status[at + 3] = 0xe9;
+        write (&root, "var/lib/dpkg/status", &status);
+
+        let report = verify_installed (&info, "hello", &root).unwrap();
+        assert_eq!(sorted (&report.verified), ["/etc/hello.conf", "/usr/bin/hello"]);
+        assert!(report.missing.is_empty() && report.modified.is_empty() && report.conffiles_changed.is_empty());
+
+        // The qualified name picks both the md5sums file and the stanza of that architecture
+        let report = verify_installed (&info, "libhello1:amd64", &root).unwrap();
+        assert_eq!(report.verified, ["/usr/lib/x86_64-linux-gnu/libhello.so.1"]);
+        assert_eq!(report.conffiles_changed, ["/etc/libhello.conf"]);
+        let report = verify_installed (&info, "libhello1:arm64", &root).unwrap();
+        assert!(report.verified.is_empty());
+
+        // Without md5sums, only the conffiles are checked
+        fs::remove_file(info.join("hello.md5sums")).unwrap();
+        let report = verify_installed (&info, "hello", &root).unwrap();
+        assert_eq!(report.verified, ["/etc/hello.conf"]);
+
+        // Nothing known about the package at all
+        let report = verify_installed (&info, "unknown", &root).unwrap();
+        assert!(report.verified.is_empty() && report.is_ok());
+
+        write (&info, "hello.md5sums", b"d41d8cd98f00b204e9800998ecf8427e  usr/bin/h\xe9llo\n");
+        assert!(matches!(verify_installed (&info, "hello", &root), Err(PakigeParseError::InvalidFormat)));
+        write (&info, "hello.md5sums", b"d41d8cd98f00b204e9800998ecf8427  usr/bin/hello\n");
+        assert!(matches!(verify_installed (&info, "hello", &root), Err(PakigeParseError::InvalidValue)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn verify_archive ()
+    {
+        let files: [(&str, &[u8]); 4] = [
+            ("usr/bin/hello", b"#!/bin/sh\necho \"Hello, world!\"\n"),
+            ("usr/bin/hello-again", b"#!/bin/sh\necho \"Hello, world!\"\n"),
+            ("usr/share/doc/hello/examples/a-directory-name-long-enough-that-the-path/does-not-fit-in-a-tar-header.txt", b"A long path.\n"),
+            ("etc/hello.conf", b"greeting=Hello\n")
+        ];
+        let archive = DebArchive::from_bytes (include_bytes!("../../tests/data/hello_xz.deb")).unwrap();
+
+        let root = temp_dir ("verify-archive");
+        for (path, data) in files
+        {
+            write (&root, path, data);
+        }
+        let report = archive.verify(&root).unwrap();
+        assert!(report.is_ok());
+        // The conffile is not in md5sums, so its digest comes from the payload
+        assert_eq!(sorted (&report.verified), ["/etc/hello.conf", "/usr/bin/hello", "/usr/bin/hello-again", &format!("/{}", files[2].0)]);
+
+        write (&root, "etc/hello.conf", b"greeting=Hi\n");
+        write (&root, "usr/bin/hello-again", b"#!/bin/sh\n");
+        fs::remove_file(root.join(files[2].0)).unwrap();
+        let report = archive.verify(&root).unwrap();
+        assert_eq!(report.verified, ["/usr/bin/hello"]);
+        assert_eq!(report.modified, ["/usr/bin/hello-again"]);
+        assert_eq!(report.missing, [format!("/{}", files[2].0)]);
+        assert_eq!(report.conffiles_changed, ["/etc/hello.conf"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
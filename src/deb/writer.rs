@@ -0,0 +1,322 @@
+use std::fmt;
+use super::{ArchRestriction, BinaryDeb, BuildProfile, DependsPackageList, MultiArch, PackageRef,
+            ProvidesPackageList, SourceRef, VersionRef};
+
+// Field order of a binary control stanza, as dpkg writes it (Dpkg::Control::FieldsCore for
+// CTRL_PKG_DEB). Fields not in this list are written afterwards, in the order they were read.
+static BINARY_FIELD_ORDER: &[&str] = &[
+    "Package",
+    "Package-Type",
+    "Source",
+    "Version",
+    "Built-Using",
+    "Static-Built-Using",
+    "Kernel-Version",
+    "Built-For-Profiles",
+    "Auto-Built-Package",
+    "Architecture",
+    "Subarchitecture",
+    "Installer-Menu-Item",
+    "Essential",
+    "Protected",
+    "Multi-Arch",
+    "Maintainer",
+    "Installed-Size",
+    "Pre-Depends",
+    "Depends",
+    "Recommends",
+    "Suggests",
+    "Breaks",
+    "Conflicts",
+    "Provides",
+    "Replaces",
+    "Enhances",
+    "Section",
+    "Priority",
+    "Homepage",
+    "Description",
+    "Tag",
+    "Task"
+];
+
+// Writes `Name: value`, folding a multi-line value into continuation lines. Lines that are
+// already indented are kept as they are. A field cannot hold an empty line, so in Description
+// one becomes the ` .` paragraph separator, and in other fields it is left out.
+pub fn write_field<W: fmt::Write> (f: &mut W, name: &str, value: &str) -> fmt::Result
+{
+    let mut lines = value.split('\n');
+    let is_description = name.eq_ignore_ascii_case("Description");
+
+    write!(f, "{}:", name)?;
+    match lines.next()
+    {
+        Some(first) if !first.is_empty() => write!(f, " {}", first)?,
+        _ => ()
+    }
+    writeln!(f)?;
+
+    for line in lines
+    {
+        if line.trim().is_empty()
+        {
+            if is_description
+            {
+                writeln!(f, " .")?;
+            }
+        }
+        else if line.starts_with([' ', '\t'])
+        {
+            writeln!(f, "{}", line)?;
+        }
+        else
+        {
+            writeln!(f, " {}", line)?;
+        }
+    }
+
+    return Ok(());
+}
+
+impl fmt::Display for VersionRef
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{} {}", self.operation, self.version_string)
+    }
+}
+
+impl fmt::Display for ArchRestriction
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}{}", if self.negated { "!" } else { "" }, self.architecture)
+    }
+}
+
+impl fmt::Display for BuildProfile
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}{}", if self.negated { "!" } else { "" }, self.name)
+    }
+}
+
+/* name [(version)] */
+impl fmt::Display for SourceRef
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version
+        {
+            write!(f, " ({})", version)?;
+        }
+        return Ok(());
+    }
+}
+
+/* pkg[:arch] [(op version)] [[arch ...]] [<profile ...> ...] */
+impl fmt::Display for PackageRef
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}", self.package)?;
+        if let Some(architecture) = &self.architecture
+        {
+            write!(f, ":{}", architecture)?;
+        }
+        if let Some(version) = &self.version
+        {
+            write!(f, " ({})", version)?;
+        }
+        if !self.arch_restrictions.is_empty()
+        {
+            let restrictions: Vec<String> = self.arch_restrictions.iter().map(|arch| arch.to_string()).collect();
+            write!(f, " [{}]", restrictions.join(" "))?;
+        }
+        for term in &self.build_profiles
+        {
+            let profiles: Vec<String> = term.iter().map(|profile| profile.to_string()).collect();
+            write!(f, " <{}>", profiles.join(" "))?;
+        }
+        return Ok(());
+    }
+}
+
+impl fmt::Display for DependsPackageList
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let groups: Vec<String> = self.0.iter()
+            .map(|group| group.iter().map(|package| package.to_string()).collect::<Vec<String>>().join(" | "))
+            .collect();
+        write!(f, "{}", groups.join(", "))
+    }
+}
+
+impl fmt::Display for ProvidesPackageList
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let packages: Vec<String> = self.0.iter().map(|package| package.to_string()).collect();
+        write!(f, "{}", packages.join(", "))
+    }
+}
+
+impl fmt::Display for MultiArch
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            MultiArch::No => write!(f, "no"),
+            MultiArch::Same => write!(f, "same"),
+            MultiArch::Foreign => write!(f, "foreign"),
+            MultiArch::Allowed => write!(f, "allowed")
+        }
+    }
+}
+
+impl BinaryDeb
+{
+    // The value of a field as modelled by BinaryDeb, or None if the field is not modelled (or
+    // is unset). Fields with a default (Essential, Multi-Arch, Priority) are only written when
+    // they differ from it or were present in the original stanza.
+    fn field_value (&self, name: &str) -> Option<Option<String>>
+    {
+        let present = self.all_fields.contains_key(name);
+
+        let value = match name
+        {
+            "Package" => Some(self.package.clone()),
+            "Source" => self.source.as_ref().map(|source| source.to_string()),
+            "Version" => Some(self.version.to_string()),
+            "Section" => self.section.clone(),
+            "Priority" => self.priority.clone().filter(|priority| present || priority != "optional"),
+            "Architecture" => Some(self.architecture.clone()),
+            "Essential" => (self.essential || present).then(|| String::from(if self.essential { "yes" } else { "no" })),
+            "Depends" => self.depends.as_ref().map(|list| list.to_string()),
+            "Recommends" => self.recommends.as_ref().map(|list| list.to_string()),
+            "Suggests" => self.suggests.as_ref().map(|list| list.to_string()),
+            "Enhances" => self.enhances.as_ref().map(|list| list.to_string()),
+            "Pre-Depends" => self.pre_depends.as_ref().map(|list| list.to_string()),
+            "Breaks" => self.breaks.as_ref().map(|list| list.to_string()),
+            "Conflicts" => self.conflicts.as_ref().map(|list| list.to_string()),
+            "Provides" => self.provides.as_ref().map(|list| list.to_string()),
+            "Replaces" => self.replaces.as_ref().map(|list| list.to_string()),
+            "Installed-Size" => self.installed_size.map(|size| size.to_string()),
+            "Maintainer" => Some(self.maintainer.clone()),
+            "Description" => Some(self.description.to_control()),
+            "Homepage" => self.homepage.clone(),
+            "Built-Using" => self.built_using.as_ref().map(|list| list.to_string()),
+            "Multi-Arch" => (self.multi_arch != MultiArch::No || present).then(|| self.multi_arch.to_string()),
+            _ => return None
+        };
+
+        return Some(value);
+    }
+}
+
+// Writes a canonical control stanza: modelled fields from their parsed values, in dpkg's order,
+// then any other fields from all_fields as they were read.
+impl fmt::Display for BinaryDeb
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        for name in BINARY_FIELD_ORDER
+        {
+            match self.field_value (name)
+            {
+                Some(Some(value)) => write_field (f, name, &value)?,
+                Some(None) => (),
+                None =>
+                {
+                    if let Some(value) = self.all_fields.get(name)
+                    {
+                        write_field (f, name, value)?;
+                    }
+                }
+            }
+        }
+
+        for (name, value) in self.all_fields.iter()
+        {
+            if !BINARY_FIELD_ORDER.iter().any(|known| known.eq_ignore_ascii_case(name))
+            {
+                write_field (f, name, value)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::str::FromStr;
+
+    fn field (name: &str, value: &str) -> String
+    {
+        let mut text = String::new();
+        write_field (&mut text, name, value).unwrap();
+        return text;
+    }
+
+    #[test]
+    fn simple_fields ()
+    {
+        assert_eq!(field ("Package", "hello"), "Package: hello\n");
+        assert_eq!(field ("Conffiles", ""), "Conffiles:\n");
+    }
+
+    #[test]
+    fn folded_fields ()
+    {
+        // As stored by str_to_table, with the continuation lines' leading space
+        assert_eq!(field ("Depends", "libc6,\n libfoo1"), "Depends: libc6,\n libfoo1\n");
+        // Lines without one are indented
+        assert_eq!(field ("Depends", "libc6,\nlibfoo1"), "Depends: libc6,\n libfoo1\n");
+        assert_eq!(field ("Files", "\n abc 1 foo.dsc\n def 2 foo.tar.xz"), "Files:\n abc 1 foo.dsc\n def 2 foo.tar.xz\n");
+    }
+
+    #[test]
+    fn multi_line_fields ()
+    {
+        assert_eq!(field ("Description", "short\n first\n\n  verbatim"), "Description: short\n first\n .\n  verbatim\n");
+        assert_eq!(field ("Description", "short\n first\n ."), "Description: short\n first\n .\n");
+        // Only Description has paragraphs; elsewhere an empty line cannot be written
+        assert_eq!(field ("Build-Depends", "debhelper-compat (= 13),\n\n libfoo-dev"), "Build-Depends: debhelper-compat (= 13),\n libfoo-dev\n");
+        assert_eq!(field ("X-Notes", "one\n \n two"), "X-Notes: one\n two\n");
+    }
+
+    #[test]
+    fn binary_stanza ()
+    {
+        let control = "\
+Package: hello
+Version: 2.10-3
+Architecture: amd64
+Maintainer: Santiago Vila <sanvila@debian.org>
+Installed-Size: 280
+Depends: libc6 (>= 2.34)
+Section: devel
+Priority: optional
+Description: example package based on GNU hello
+ The GNU hello program produces a familiar, friendly greeting.
+ .
+ It allows non-programmers to use a classic computer science tool.
+X-Custom: kept
+";
+        let deb = BinaryDeb::from_str (control).unwrap();
+        assert_eq!(deb.to_string(), control);
+    }
+
+    #[test]
+    fn trailing_description_separator ()
+    {
+        let control = "Package: hello\nVersion: 1\nArchitecture: all\nMaintainer: A <a@example.org>\nDescription: short\n text\n .\n";
+        let deb = BinaryDeb::from_str (control).unwrap();
+        assert_eq!(deb.to_string(), control);
+    }
+}
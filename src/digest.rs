@@ -0,0 +1,159 @@
+use std::io::{self, Read};
+
+// Message digests used by package metadata to check files: SHA-256 and SHA-512 (FIPS 180-4),
+// from the sha2 crate, and MD5 (RFC 1321), from md-5, which dpkg still uses for installed files
+// and conffiles. They are wrapped behind one small trait so that callers can hash streams the same
+// way whatever the algorithm.
+
+pub trait Digest
+{
+    fn update (&mut self, data: &[u8]);
+    fn finish (self) -> Vec<u8>;
+}
+
+/* Lowercase hex, as digests are written in control files */
+pub fn to_hex (bytes: &[u8]) -> String
+{
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes
+    {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    return hex;
+}
+
+/* Hashes everything read from `reader`. Returns the hex digest and the number of bytes read */
+pub fn hash_reader<D: Digest, R: Read> (mut digest: D, mut reader: R) -> io::Result<(String, u64)>
+{
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+
+    loop
+    {
+        let read = match reader.read(&mut buffer)
+        {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e)
+        };
+        digest.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    return Ok((to_hex (&digest.finish()), size));
+}
+
+#[derive(Clone, Default)]
+pub struct Sha256(sha2::Sha256);
+
+impl Sha256
+{
+    pub fn new () -> Self
+    {
+        return Sha256::default();
+    }
+}
+
+impl Digest for Sha256
+{
+    fn update (&mut self, data: &[u8])
+    {
+        sha2::Digest::update(&mut self.0, data);
+    }
+
+    fn finish (self) -> Vec<u8>
+    {
+        return sha2::Digest::finalize(self.0).to_vec();
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Sha512(sha2::Sha512);
+
+impl Sha512
+{
+    pub fn new () -> Self
+    {
+        return Sha512::default();
+    }
+}
+
+impl Digest for Sha512
+{
+    fn update (&mut self, data: &[u8])
+    {
+        sha2::Digest::update(&mut self.0, data);
+    }
+
+    fn finish (self) -> Vec<u8>
+    {
+        return sha2::Digest::finalize(self.0).to_vec();
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Md5(md5::Md5);
+
+impl Md5
+{
+    pub fn new () -> Self
+    {
+        return Md5::default();
+    }
+}
+
+impl Digest for Md5
+{
+    fn update (&mut self, data: &[u8])
+    {
+        md5::Digest::update(&mut self.0, data);
+    }
+
+    fn finish (self) -> Vec<u8>
+    {
+        return md5::Digest::finalize(self.0).to_vec();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn hex<D: Digest> (mut digest: D, data: &[u8]) -> String
+    {
+        digest.update(data);
+        return to_hex (&digest.finish());
+    }
+
+    #[test]
+    fn sha256 ()
+    {
+        assert_eq!(hex (Sha256::new(), b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hex (Sha256::new(), b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        // Read in several pieces
+        let data = vec![b'a'; 1_000_000];
+        let (digest, size) = hash_reader (Sha256::new(), data.as_slice()).unwrap();
+        assert_eq!(digest, "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+        assert_eq!(size, 1_000_000);
+    }
+
+    #[test]
+    fn sha512 ()
+    {
+        assert_eq!(hex (Sha512::new(), b""),
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e");
+        assert_eq!(hex (Sha512::new(), b"abc"),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+    }
+
+    #[test]
+    fn md5 ()
+    {
+        assert_eq!(hex (Md5::new(), b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex (Md5::new(), b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(hex (Md5::new(), b"#!/bin/sh\necho \"Hello, world!\"\n"), "d9ee44d59390c7097f20a0ec1c449048");
+    }
+}
@@ -1,10 +1,15 @@
 // Pakige
+#![allow(clippy::needless_return)]
 use std::fmt;
 
+pub mod compression;
 pub mod deb;
+pub mod digest;
 pub mod rpm;
+#[cfg(test)]
+mod testing;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum VerOp
 {
     Gt,
@@ -14,6 +19,20 @@ pub enum VerOp
     Lt
 }
 
+impl fmt::Display for VerOp
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            VerOp::Gt => write!(f, ">>"),
+            VerOp::GtEq => write!(f, ">="),
+            VerOp::Eq => write!(f, "="),
+            VerOp::LtEq => write!(f, "<="),
+            VerOp::Lt => write!(f, "<<")
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum PakigeParseError
 {
@@ -21,7 +40,10 @@ pub enum PakigeParseError
     MissingMandatoryField,
     InvalidFormat,
     InvalidValue,
-    DuplicateField
+    DuplicateField,
+    InvalidRelationship(String, deb::RelationError),
+    InvalidArchive(deb::ArchiveError),
+    IoError(std::io::Error)
 }
 
 impl fmt::Display for PakigeParseError 
@@ -33,7 +55,10 @@ impl fmt::Display for PakigeParseError
             PakigeParseError::MissingMandatoryField => write!(f, "Input is missing a mandatory field."),
             PakigeParseError::InvalidFormat => write!(f, "Input is not in a valid format."),
             PakigeParseError::InvalidValue => write!(f, "A given field has an invalid value."),
-            PakigeParseError::DuplicateField => write!(f, "A given field was present twice in the stanza.")
+            PakigeParseError::DuplicateField => write!(f, "A given field was present twice in the stanza."),
+            PakigeParseError::InvalidRelationship(field, error) => write!(f, "Field '{}' has an invalid relationship: {}.", field, error),
+            PakigeParseError::InvalidArchive(error) => write!(f, "Package archive could not be read: {}.", error),
+            PakigeParseError::IoError(error) => write!(f, "Input could not be read: {}.", error)
         }
     }
 }
@@ -41,12 +66,25 @@ impl fmt::Display for PakigeParseError
 // TODO: handle different kinds of errors from deb_version?
 impl From<deb_version7::Error> for PakigeParseError
 {
-    fn from (error: deb_version7::Error) -> PakigeParseError
+    fn from (_error: deb_version7::Error) -> PakigeParseError
     {
         return PakigeParseError::InvalidFormat;
     }
 }
 
+// Readers that unpack package archives report bad archives as io::Errors wrapping an ArchiveError
+impl From<std::io::Error> for PakigeParseError
+{
+    fn from (error: std::io::Error) -> PakigeParseError
+    {
+        if let Some(archive) = error.get_ref().and_then(|inner| inner.downcast_ref::<deb::ArchiveError>())
+        {
+            return PakigeParseError::InvalidArchive(archive.clone());
+        }
+        return PakigeParseError::IoError(error);
+    }
+}
+
 impl std::error::Error for PakigeParseError {}
 
 pub trait Pakige
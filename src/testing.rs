@@ -0,0 +1,35 @@
+use crate::PakigeParseError;
+use crate::deb::StanzaError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Helpers shared by the tests of several modules
+
+/* An empty directory of its own for each test, under the system temporary directory */
+pub fn temp_dir (name: &str) -> PathBuf
+{
+    let dir = std::env::temp_dir().join(format!("pakige-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+/* Writes a file under `root`, creating the directories it needs */
+pub fn write (root: &Path, path: &str, data: &[u8])
+{
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, data).unwrap();
+}
+
+/* Checks that parsing `data` failed at `line` with an error of the same variant as `expected` */
+pub fn assert_stanza_error<T> (result: Result<T, StanzaError>, data: &str, line: usize, expected: PakigeParseError)
+{
+    let error = match result
+    {
+        Ok(_) => panic!("parsed without error: {:?}", data),
+        Err(error) => error
+    };
+    assert_eq!(error.line, line, "{:?}", data);
+    assert_eq!(std::mem::discriminant(&error.error), std::mem::discriminant(&expected), "{:?}: {:?}", data, error.error);
+}
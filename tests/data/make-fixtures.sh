@@ -0,0 +1,54 @@
+#!/bin/sh
+# Builds the .deb fixtures used by the archive tests. dpkg-deb makes one package per compression,
+# with GNU tar long names; the pax and ustar ones are assembled with ar, from data.tar archives
+# written by GNU tar in those formats.
+set -e
+
+cd "$(dirname "$0")"
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+
+long=usr/share/doc/hello/examples/a-directory-name-long-enough-that-the-path/does-not-fit-in-a-tar-header.txt
+
+root=$work/hello
+mkdir -p "$root/DEBIAN" "$root/usr/bin" "$root/etc" "$root/$(dirname $long)"
+printf '#!/bin/sh\necho "Hello, world!"\n' > "$root/usr/bin/hello"
+chmod 755 "$root/usr/bin/hello"
+ln "$root/usr/bin/hello" "$root/usr/bin/hello-again"
+ln -s hello "$root/usr/bin/hi"
+printf 'greeting=Hello\n' > "$root/etc/hello.conf"
+printf 'A long path.\n' > "$root/$long"
+
+cat > "$root/DEBIAN/control" <<CONTROL
+Package: hello
+Version: 1.0
+Architecture: all
+Maintainer: Nobody <nobody@example.org>
+Description: example package
+ A package for testing.
+CONTROL
+printf '/etc/hello.conf\n' > "$root/DEBIAN/conffiles"
+(cd "$root" && find usr -type f | sort | xargs md5sum) > "$root/DEBIAN/md5sums"
+
+find "$root" -exec touch -h -d @1700000000 {} +
+
+for compression in gzip xz zstd none
+do
+    SOURCE_DATE_EPOCH=1700000000 dpkg-deb --root-owner-group -Z$compression -b "$root" "hello_$compression.deb" > /dev/null
+done
+
+# Same package, with the payload in other tar formats
+mkdir "$work/parts"
+(cd "$work/parts" && ar x "$OLDPWD/hello_gzip.deb" debian-binary control.tar.gz)
+for format in pax ustar
+do
+    options=
+    if [ $format = pax ]
+    then
+        options=--pax-option=delete=atime,delete=ctime
+    fi
+    tar -C "$root" --format=$format $options --blocking-factor=1 --owner=root --group=root --sort=name \
+        --exclude=DEBIAN -cf "$work/parts/data.tar" ./
+    rm -f "hello_$format.deb"
+    (cd "$work/parts" && ar rcD "$OLDPWD/hello_$format.deb" debian-binary control.tar.gz data.tar)
+done